@@ -0,0 +1,390 @@
+//! Achievements driven by gameplay events, with titles and rewards
+//!
+//! There is no crafting system yet, so crafting achievements (such as crafting 50 items)
+//! wait on one to add a crafting criterion and report its events
+
+use spacetimedb::{table, reducer, client_visibility_filter, Filter, ReducerContext, Identity, Timestamp, Table};
+use shared_module::*;
+use server_module::*;
+use crate::mail::deliver_mail;
+use crate::wallet::PlayerWallet;
+use crate::world::BIOME_TYPES;
+
+/// What an achievement counts
+#[derive(Clone, Debug, PartialEq)]
+pub enum AchievementCriteria {
+    /// Kill NPCs of the target type (any type if empty)
+    KillNpc,
+    /// Reach the required character level
+    ReachLevel,
+    /// Reach the required level in the target skill (any skill if empty)
+    SkillLevel,
+    /// Use items with the target id (any item if empty)
+    UseItem,
+    /// Discover the target biome, or that many distinct biomes if empty
+    DiscoverBiome,
+}
+
+impl std::fmt::Display for AchievementCriteria {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AchievementCriteria::KillNpc => "kill_npc",
+            AchievementCriteria::ReachLevel => "reach_level",
+            AchievementCriteria::SkillLevel => "skill_level",
+            AchievementCriteria::UseItem => "use_item",
+            AchievementCriteria::DiscoverBiome => "discover_biome",
+        })
+    }
+}
+
+impl AchievementCriteria {
+    pub fn from_string(s: &str) -> Option<AchievementCriteria> {
+        match s {
+            "kill_npc" => Some(AchievementCriteria::KillNpc),
+            "reach_level" => Some(AchievementCriteria::ReachLevel),
+            "skill_level" => Some(AchievementCriteria::SkillLevel),
+            "use_item" => Some(AchievementCriteria::UseItem),
+            "discover_biome" => Some(AchievementCriteria::DiscoverBiome),
+            _ => None,
+        }
+    }
+}
+
+/// A gameplay event that can advance achievements
+#[derive(Clone, Debug)]
+pub enum AchievementEvent {
+    NpcKilled { npc_type: String },
+    LevelReached { level: u32 },
+    SkillLevelReached { skill_name: String, level: u32 },
+    ItemUsed { item_id: String },
+    BiomeDiscovered { biome_type: String },
+}
+
+/// Achievement definition
+#[derive(Clone, Debug)]
+#[table(name = achievement_definitions, public)]
+pub struct AchievementDefinition {
+    #[primary_key]
+    pub achievement_id: String,
+    pub name: String,
+    pub description: String,
+
+    /// AchievementCriteria as a string
+    pub criteria: String,
+    /// NPC type, skill, item or biome the criteria applies to; empty matches any
+    pub criteria_target: String,
+    pub required_count: u64,
+
+    pub reward_title: Option<String>,
+    pub reward_experience: u64,
+    pub reward_currency: u64,
+    pub reward_item_id: Option<String>,
+    pub reward_item_quantity: u32,
+}
+
+/// A player's progress towards one achievement
+#[derive(Clone, Debug)]
+#[table(name = player_achievement, public)]
+pub struct PlayerAchievement {
+    #[primary_key]
+    #[auto_inc]
+    pub progress_id: u64,
+
+    #[index(btree)]
+    pub player_identity: Identity,
+    pub achievement_id: String,
+
+    pub progress: u64,
+    pub completed_at: Option<Timestamp>,
+}
+
+/// A title earned from an achievement
+#[derive(Clone, Debug)]
+#[table(name = player_title, public)]
+pub struct PlayerTitle {
+    #[primary_key]
+    #[auto_inc]
+    pub title_id: u64,
+
+    #[index(btree)]
+    pub player_identity: Identity,
+    pub title: String,
+    pub achievement_id: String,
+
+    /// The title shown next to the player's name; at most one per player
+    pub active: bool,
+    pub earned_at: Timestamp,
+}
+
+/// A biome a player has discovered
+#[derive(Clone, Debug)]
+#[table(name = player_biome_discovery, public)]
+pub struct PlayerBiomeDiscovery {
+    #[primary_key]
+    #[auto_inc]
+    pub discovery_id: u64,
+
+    #[index(btree)]
+    pub player_identity: Identity,
+    pub biome_type: String,
+    pub discovered_at: Timestamp,
+}
+
+/// Players only see their own achievement progress
+#[client_visibility_filter]
+const PLAYER_ACHIEVEMENT_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM player_achievement WHERE player_identity = :sender"
+);
+
+/// Biome discoveries are private to the explorer
+#[client_visibility_filter]
+const PLAYER_BIOME_DISCOVERY_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM player_biome_discovery WHERE player_identity = :sender"
+);
+
+impl AchievementDefinition {
+    /// Progress an event makes towards this achievement, if it applies
+    /// Counted events add to progress; milestone events set it
+    fn progress_from(&self, event: &AchievementEvent, current: u64, biomes_discovered: u64) -> Option<u64> {
+        let criteria = AchievementCriteria::from_string(&self.criteria)?;
+        let matches_target = |subject: &str| self.criteria_target.is_empty() || self.criteria_target == subject;
+
+        match (criteria, event) {
+            (AchievementCriteria::KillNpc, AchievementEvent::NpcKilled { npc_type }) if matches_target(npc_type) => {
+                Some(current.saturating_add(1))
+            },
+            (AchievementCriteria::ReachLevel, AchievementEvent::LevelReached { level }) => {
+                Some(current.max(*level as u64))
+            },
+            (AchievementCriteria::SkillLevel, AchievementEvent::SkillLevelReached { skill_name, level }) if matches_target(skill_name) => {
+                Some(current.max(*level as u64))
+            },
+            (AchievementCriteria::UseItem, AchievementEvent::ItemUsed { item_id }) if matches_target(item_id) => {
+                Some(current.saturating_add(1))
+            },
+            (AchievementCriteria::DiscoverBiome, AchievementEvent::BiomeDiscovered { biome_type }) => {
+                if self.criteria_target.is_empty() {
+                    Some(biomes_discovered)
+                } else if self.criteria_target == *biome_type {
+                    Some(1)
+                } else {
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+}
+
+impl PlayerAchievement {
+    /// All achievement progress for a player
+    pub fn get_for_player(ctx: &ReducerContext, identity: &Identity) -> Vec<PlayerAchievement> {
+        ctx.db.player_achievement().player_identity().filter(identity).collect()
+    }
+
+    /// Whether a player has completed an achievement
+    pub fn is_completed(ctx: &ReducerContext, identity: &Identity, achievement_id: &str) -> bool {
+        Self::get_for_player(ctx, identity).iter()
+            .any(|entry| entry.achievement_id == achievement_id && entry.completed_at.is_some())
+    }
+}
+
+impl PlayerBiomeDiscovery {
+    /// Whether the player has already discovered a biome
+    pub fn has_discovered(ctx: &ReducerContext, identity: &Identity, biome_type: &str) -> bool {
+        ctx.db.player_biome_discovery().player_identity()
+            .filter(identity)
+            .any(|entry| entry.biome_type == biome_type)
+    }
+
+    /// Record a discovery, returning the player's distinct biome count
+    fn discover(ctx: &ReducerContext, identity: &Identity, biome_type: &str) -> u64 {
+        let discovered: Vec<PlayerBiomeDiscovery> = ctx.db.player_biome_discovery().player_identity()
+            .filter(identity)
+            .collect();
+
+        if discovered.iter().any(|entry| entry.biome_type == biome_type) {
+            return discovered.len() as u64;
+        }
+
+        ctx.db.player_biome_discovery().insert(PlayerBiomeDiscovery {
+            discovery_id: 0, // auto_inc
+            player_identity: *identity,
+            biome_type: biome_type.to_string(),
+            discovered_at: ctx.timestamp,
+        });
+
+        discovered.len() as u64 + 1
+    }
+}
+
+/// Set up achievement definitions
+pub fn initialize_achievements(ctx: &ReducerContext) -> Result<(), String> {
+    if ctx.db.achievement_definitions().count() == 0 {
+        create_default_achievements(ctx);
+    }
+
+    log::info!("Achievement system initialized");
+    Ok(())
+}
+
+/// Create default achievement definitions
+fn create_default_achievements(ctx: &ReducerContext) {
+    let default_achievements = vec![
+        ("goblin_slayer", "Goblin Slayer", "Kill 100 goblins", AchievementCriteria::KillNpc, "goblin", 100, Some("Goblin Slayer"), 500, 0, None),
+        ("dragon_bane", "Dragon Bane", "Kill a dragon", AchievementCriteria::KillNpc, "dragon", 1, Some("Dragon Bane"), 2000, 500, None),
+        ("adventurer", "Adventurer", "Reach level 10", AchievementCriteria::ReachLevel, "", 10, None, 0, 100, Some(("potion_health", 5))),
+        ("veteran", "Veteran", "Reach level 50", AchievementCriteria::ReachLevel, "", 50, Some("Veteran"), 0, 1000, None),
+        ("journeyman", "Journeyman", "Reach level 10 in any skill", AchievementCriteria::SkillLevel, "", 10, Some("Journeyman"), 250, 0, None),
+        ("explorer", "Explorer", "Visit every biome", AchievementCriteria::DiscoverBiome, "", BIOME_TYPES.len() as u64, Some("Explorer"), 500, 0, None),
+        ("first_aid", "First Aid", "Use 25 consumables", AchievementCriteria::UseItem, "", 25, None, 100, 0, Some(("potion_health", 5))),
+    ];
+
+    for (id, name, desc, criteria, target, count, title, experience, currency, item) in default_achievements {
+        ctx.db.achievement_definitions().insert(AchievementDefinition {
+            achievement_id: id.to_string(),
+            name: name.to_string(),
+            description: desc.to_string(),
+            criteria: criteria.to_string(),
+            criteria_target: target.to_string(),
+            required_count: count,
+            reward_title: title.map(|title| title.to_string()),
+            reward_experience: experience,
+            reward_currency: currency,
+            reward_item_id: item.map(|(item_id, _)| item_id.to_string()),
+            reward_item_quantity: item.map(|(_, quantity)| quantity).unwrap_or(0),
+        });
+    }
+}
+
+/// Advance a player's achievements from a gameplay event
+/// Never fails, so hooks cannot abort the action that triggered them
+pub fn record_achievement_event(ctx: &ReducerContext, identity: &Identity, event: AchievementEvent) {
+    let biomes_discovered = match &event {
+        AchievementEvent::BiomeDiscovered { biome_type } => PlayerBiomeDiscovery::discover(ctx, identity, biome_type),
+        _ => 0,
+    };
+
+    let progress_rows = PlayerAchievement::get_for_player(ctx, identity);
+    let mut completed = Vec::new();
+
+    for definition in ctx.db.achievement_definitions().iter() {
+        let existing = progress_rows.iter().find(|entry| entry.achievement_id == definition.achievement_id);
+        if existing.is_some_and(|entry| entry.completed_at.is_some()) {
+            continue;
+        }
+
+        let current = existing.map(|entry| entry.progress).unwrap_or(0);
+        let Some(progress) = definition.progress_from(&event, current, biomes_discovered) else {
+            continue;
+        };
+        if progress == current && existing.is_some() {
+            continue;
+        }
+
+        let progress = progress.min(definition.required_count);
+        let completed_at = (progress >= definition.required_count).then_some(ctx.timestamp);
+
+        match existing {
+            Some(entry) => {
+                ctx.db.player_achievement().progress_id().update(PlayerAchievement {
+                    progress,
+                    completed_at,
+                    ..entry.clone()
+                });
+            },
+            None => {
+                ctx.db.player_achievement().insert(PlayerAchievement {
+                    progress_id: 0, // auto_inc
+                    player_identity: *identity,
+                    achievement_id: definition.achievement_id.clone(),
+                    progress,
+                    completed_at,
+                });
+            },
+        }
+
+        if completed_at.is_some() {
+            completed.push(definition);
+        }
+    }
+
+    // Rewards can raise the player's level, which records further events
+    for definition in completed {
+        complete_achievement(ctx, identity, &definition);
+    }
+}
+
+/// Announce a completed achievement and hand out its rewards
+fn complete_achievement(ctx: &ReducerContext, identity: &Identity, definition: &AchievementDefinition) {
+    let Some(player) = ctx.db.game_players().identity().find(identity) else {
+        return;
+    };
+
+    PlayerNotification::send(ctx, *identity, "achievement", format!("Achievement unlocked: {}", definition.name));
+    log::info!("Player {} completed achievement {}", player.username, definition.achievement_id);
+
+    if let Some(title) = &definition.reward_title {
+        ctx.db.player_title().insert(PlayerTitle {
+            title_id: 0, // auto_inc
+            player_identity: *identity,
+            title: title.clone(),
+            achievement_id: definition.achievement_id.clone(),
+            active: false,
+            earned_at: ctx.timestamp,
+        });
+        PlayerNotification::send(ctx, *identity, "achievement", format!("You earned the title \"{}\"", title));
+    }
+
+    if definition.reward_currency > 0 {
+        if let Err(e) = PlayerWallet::credit(ctx, identity, definition.reward_currency) {
+            log::warn!("Could not credit achievement currency to {}: {}", player.username, e);
+        }
+    }
+
+    // Items arrive by mail so a full inventory never loses a reward
+    if let Some(item_id) = &definition.reward_item_id {
+        if definition.reward_item_quantity > 0 {
+            deliver_mail(
+                ctx,
+                ctx.identity(),
+                ACHIEVEMENT_SENDER_NAME.to_string(),
+                *identity,
+                player.username.clone(),
+                format!("Achievement reward: {}", definition.name),
+                String::new(),
+                vec![(item_id.clone(), definition.reward_item_quantity, None)],
+                0
+            );
+        }
+    }
+
+    if definition.reward_experience > 0 {
+        if let Some(new_level) = Player::grant_experience(ctx, identity, definition.reward_experience) {
+            PlayerNotification::send(ctx, *identity, "progression", format!("You reached level {}", new_level));
+            record_achievement_event(ctx, identity, AchievementEvent::LevelReached { level: new_level });
+        }
+    }
+}
+
+/// Show an earned title next to your name, or clear it with None
+#[reducer]
+pub fn set_active_title(ctx: &ReducerContext, title: Option<String>) -> Result<(), String> {
+    let titles: Vec<PlayerTitle> = ctx.db.player_title().player_identity().filter(&ctx.sender).collect();
+
+    if let Some(title) = &title {
+        if !titles.iter().any(|entry| entry.title == *title) {
+            return Err("You have not earned that title".to_string());
+        }
+    }
+
+    for mut entry in titles {
+        let active = title.as_ref() == Some(&entry.title);
+        if entry.active != active {
+            entry.active = active;
+            ctx.db.player_title().title_id().update(entry);
+        }
+    }
+
+    Ok(())
+}
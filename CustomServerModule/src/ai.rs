@@ -1,6 +1,6 @@
 //! AI system for NPCs and game entities
 
-use spacetimedb::{reducer, ReducerContext};
+use spacetimedb::{reducer, ReducerContext, Identity, Table};
 use shared_module::*;
 use crate::*;
 
@@ -63,14 +63,13 @@ pub fn spawn_npc(
     // Generate unique NPC ID
     let npc_id = generate_unique_id(&ctx.sender, ctx.timestamp);
     
-    // Determine stats based on NPC type
-    let (health, max_health) = match npc_type.as_str() {
-        "goblin" => (50.0, 50.0),
-        "orc" => (100.0, 100.0),
-        "dragon" => (1000.0, 1000.0),
-        "merchant" => (100.0, 100.0),
-        _ => (50.0, 50.0), // Default
-    };
+    // Stats come from the type's template; types without one get defaults
+    if ContentRecord::is_retired(ctx, CONTENT_KIND_NPC_TEMPLATE, &npc_type) {
+        return Err(format!("NPC type '{}' has been retired", npc_type));
+    }
+    let template = NpcTemplate::find_active(ctx, &npc_type);
+    let max_health = template.as_ref().map(|template| template.max_health).unwrap_or(DEFAULT_NPC_MAX_HEALTH);
+    let health = max_health;
     
     // Create the NPC
     ctx.db.npcs().insert(NPC {
@@ -87,6 +86,15 @@ pub fn spawn_npc(
         respawn_time: ctx.timestamp,
     });
     
+    // Vendor types open for business with default prices and an empty shelf
+    if template.is_some_and(|template| template.vendor) {
+        ctx.db.vendor().insert(Vendor {
+            npc_id,
+            buy_multiplier: DEFAULT_VENDOR_BUY_MULTIPLIER,
+            sell_multiplier: DEFAULT_VENDOR_SELL_MULTIPLIER,
+        });
+    }
+    
     log::info!("Spawned NPC '{}' of type '{}' at ({}, {}, {})", name, npc_type, x, y, z);
     Ok(npc_id)
 }
@@ -113,9 +121,9 @@ pub fn update_npc_ai(ctx: &ReducerContext, npc_id: u64) -> Result<(), String> {
     let new_state = match current_state {
         AIState::Idle => {
             if !nearby_players.is_empty() {
-                match npc.npc_type.as_str() {
-                    "goblin" | "orc" => AIState::Chasing,
-                    "merchant" => AIState::Idle, // Merchants don't chase
+                match NpcTemplate::find_active(ctx, &npc.npc_type) {
+                    Some(template) if template.aggressive => AIState::Chasing,
+                    Some(template) if template.vendor => AIState::Idle, // Vendors don't chase
                     _ => AIState::Patrolling,
                 }
             } else {
@@ -123,7 +131,7 @@ pub fn update_npc_ai(ctx: &ReducerContext, npc_id: u64) -> Result<(), String> {
             }
         },
         AIState::Patrolling => {
-            if !nearby_players.is_empty() && is_aggressive_npc(&npc.npc_type) {
+            if !nearby_players.is_empty() && is_aggressive_npc(ctx, &npc.npc_type) {
                 AIState::Chasing
             } else {
                 AIState::Patrolling
@@ -166,6 +174,11 @@ pub fn update_npc_ai(ctx: &ReducerContext, npc_id: u64) -> Result<(), String> {
         },
     };
     
+    // An attacking NPC hits the nearest player, wearing down their armor
+    if let (AIState::Attacking, Some(target)) = (&new_state, nearby_players.first()) {
+        wear_equipped_item(ctx, &target.identity, "armor", ITEM_WEAR_PER_HIT_TAKEN);
+    }
+    
     // Update NPC state if changed
     if new_state.to_string() != npc.ai_state {
         npc.ai_state = new_state.to_string();
@@ -241,14 +254,19 @@ pub fn attack_npc(
         return Err("Too far away to attack".to_string());
     }
     
-    // Apply damage
+    // Apply damage; every swing wears the equipped weapon
     npc.health = (npc.health - damage).max(0.0);
+    wear_equipped_item(ctx, &player.identity, "weapon", ITEM_WEAR_PER_ATTACK);
     
     // If NPC dies, set respawn timer
     if npc.health <= 0.0 {
         npc.ai_state = AIState::Dead.to_string();
         npc.respawn_time = ctx.timestamp;
         log::info!("NPC '{}' killed by player '{}'", npc.name, player.username);
+        
+        award_kill_experience(ctx, &player, &npc);
+        PlayerCombatStats::record_npc_kill(ctx, &player.identity);
+        record_achievement_event(ctx, &player.identity, AchievementEvent::NpcKilled { npc_type: npc.npc_type.clone() });
     } else {
         // NPC becomes aggressive if not already
         if npc.ai_state == AIState::Idle.to_string() {
@@ -260,6 +278,46 @@ pub fn attack_npc(
     Ok(())
 }
 
+/// Experience an NPC is worth, scaled by how tough it is
+fn npc_kill_experience(npc: &NPC) -> u64 {
+    (npc.max_health / 2.0).max(1.0) as u64
+}
+
+/// Give kill experience to the killer, or split it between party members in range
+/// Each extra member adds a bonus to the pool so grouping is never a loss
+/// Everyone who shares the experience also gets quest credit for the kill
+fn award_kill_experience(ctx: &ReducerContext, killer: &server_module::Player, npc: &NPC) {
+    let base_experience = npc_kill_experience(npc);
+    
+    let recipients: Vec<Identity> = match PartyMember::find_by_member(ctx, &killer.identity) {
+        Some(membership) => PartyMember::get_members_in_range(
+            ctx,
+            membership.party_id,
+            &killer.current_zone,
+            npc.position_x, npc.position_y, npc.position_z,
+            PARTY_XP_SHARE_RANGE
+        )
+        .into_iter()
+        .map(|member| member.member_identity)
+        .collect(),
+        None => vec![killer.identity],
+    };
+    
+    // The killer always counts, even if their party copy is momentarily stale
+    let recipients = if recipients.contains(&killer.identity) { recipients } else { vec![killer.identity] };
+    
+    let bonus = 1.0 + PARTY_XP_BONUS_PER_MEMBER * (recipients.len() - 1) as f64;
+    let share = ((base_experience as f64 * bonus) / recipients.len() as f64).ceil() as u64;
+    
+    for identity in &recipients {
+        if let Some(new_level) = Player::grant_experience(ctx, identity, share) {
+            PlayerNotification::send(ctx, *identity, "progression", format!("You reached level {}", new_level));
+            record_achievement_event(ctx, identity, AchievementEvent::LevelReached { level: new_level });
+        }
+        record_quest_event(ctx, identity, QuestEvent::NpcKilled { npc_type: npc.npc_type.clone() });
+    }
+}
+
 /// Get nearby players to determine AI behavior
 fn get_nearby_players(
     ctx: &ReducerContext,
@@ -282,8 +340,8 @@ fn get_nearby_players(
         .collect()
 }
 
-fn is_aggressive_npc(npc_type: &str) -> bool {
-    matches!(npc_type, "goblin" | "orc" | "dragon")
+fn is_aggressive_npc(ctx: &ReducerContext, npc_type: &str) -> bool {
+    NpcTemplate::find_active(ctx, npc_type).is_some_and(|template| template.aggressive)
 }
 
 fn get_patrol_position(current_x: f32, current_y: f32) -> (f32, f32) {
@@ -0,0 +1,372 @@
+//! Auction house: escrowed listings, bids, buyouts and settlement by mail
+
+use spacetimedb::{table, reducer, ReducerContext, Identity, Timestamp, ScheduleAt, Table};
+use shared_module::*;
+use server_module::*;
+use server_module::utils::validation::{validate_player_in_game, validate_rate_limit};
+use crate::mechanics::*;
+use crate::mail::deliver_mail;
+use crate::item_instance::escrow_item_instance;
+use crate::wallet::PlayerWallet;
+
+/// An item stack up for auction
+/// The items are held here, out of the seller's inventory, until the auction ends;
+/// the leading bid is likewise held out of the bidder's wallet
+/// A unique item is listed as its instance, owned by the module until the auction ends
+/// Clients browse by subscribing through the item_type index, e.g.
+/// `SELECT * FROM auction_listing WHERE item_type = 'weapon'`
+#[derive(Clone, Debug)]
+#[table(name = auction_listing, public)]
+pub struct AuctionListing {
+    #[primary_key]
+    #[auto_inc]
+    pub listing_id: u64,
+
+    #[index(btree)]
+    pub seller_identity: Identity,
+    pub seller_username: String,
+
+    pub item_id: String,
+    /// Copied from GameItem for browsing by type
+    #[index(btree)]
+    pub item_type: String,
+    pub item_name: String,
+    pub quantity: u32,
+    pub instance_id: Option<u64>,
+
+    pub start_bid: u64,
+    /// 0 when the auction has no buyout
+    pub buyout_price: u64,
+
+    pub current_bid: u64,
+    pub bidder_identity: Option<Identity>,
+    pub bidder_username: String,
+
+    /// Paid when listing; returned with the proceeds if the item sells
+    pub deposit: u64,
+
+    pub created_at: Timestamp,
+    #[index(btree)]
+    pub expires_at: Timestamp,
+}
+
+/// Periodic job that settles expired auctions
+#[table(name = auction_expiry_schedule, scheduled(process_expired_auctions))]
+pub struct AuctionExpirySchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub scheduled_id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+impl AuctionListing {
+    /// Find listing by ID
+    pub fn filter_by_listing_id(ctx: &ReducerContext, listing_id: u64) -> Option<AuctionListing> {
+        ctx.db.auction_listing().listing_id().find(&listing_id)
+    }
+
+    /// Count a seller's active listings
+    pub fn count_for_seller(ctx: &ReducerContext, seller_identity: &Identity) -> usize {
+        ctx.db.auction_listing().seller_identity().filter(seller_identity).count()
+    }
+
+    pub fn has_bids(&self) -> bool {
+        self.bidder_identity.is_some()
+    }
+
+    pub fn minimum_bid(&self) -> u64 {
+        minimum_next_bid(self.start_bid, self.current_bid, self.has_bids())
+    }
+}
+
+/// Start the auction expiry job
+pub fn initialize_auction_house(ctx: &ReducerContext) -> Result<(), String> {
+    if ctx.db.auction_expiry_schedule().count() == 0 {
+        let interval = std::time::Duration::from_secs(AUCTION_EXPIRY_CHECK_INTERVAL_SECONDS);
+        ctx.db.auction_expiry_schedule().insert(AuctionExpirySchedule {
+            scheduled_id: 0, // auto_inc
+            scheduled_at: ScheduleAt::Interval(interval.into()),
+        });
+    }
+
+    log::info!("Auction house initialized");
+    Ok(())
+}
+
+/// Send the auction house's mail
+fn send_auction_mail(
+    ctx: &ReducerContext,
+    recipient_identity: Identity,
+    recipient_username: String,
+    subject: String,
+    attachments: Vec<(String, u32, Option<u64>)>,
+    currency: u64
+) {
+    deliver_mail(
+        ctx,
+        ctx.identity(),
+        AUCTION_HOUSE_SENDER_NAME.to_string(),
+        recipient_identity,
+        recipient_username,
+        subject,
+        String::new(),
+        attachments,
+        currency
+    );
+}
+
+/// Give the current high bidder their money back
+fn refund_bidder(ctx: &ReducerContext, listing: &AuctionListing) {
+    if let Some(bidder_identity) = listing.bidder_identity {
+        send_auction_mail(
+            ctx,
+            bidder_identity,
+            listing.bidder_username.clone(),
+            format!("Outbid on {}", listing.item_name),
+            Vec::new(),
+            listing.current_bid
+        );
+    }
+}
+
+/// Close an auction as sold: items to the buyer, proceeds and deposit (less the cut) to the seller
+fn settle_sale(
+    ctx: &ReducerContext,
+    listing: &AuctionListing,
+    buyer_identity: Identity,
+    buyer_username: String,
+    price: u64
+) {
+    ctx.db.auction_listing().listing_id().delete(&listing.listing_id);
+
+    send_auction_mail(
+        ctx,
+        buyer_identity,
+        buyer_username,
+        format!("Auction won: {}", listing.item_name),
+        vec![(listing.item_id.clone(), listing.quantity, listing.instance_id)],
+        0
+    );
+
+    let proceeds = price - auction_sales_cut(price);
+    send_auction_mail(
+        ctx,
+        listing.seller_identity,
+        listing.seller_username.clone(),
+        format!("Auction sold: {}", listing.item_name),
+        Vec::new(),
+        proceeds.saturating_add(listing.deposit)
+    );
+
+    log::info!("Auction {} sold for {}", listing.listing_id, price);
+}
+
+/// Put an inventory stack up for auction
+/// The stack leaves your inventory straight away and the deposit is taken from your wallet
+#[reducer]
+pub fn create_auction(
+    ctx: &ReducerContext,
+    inventory_id: u64,
+    quantity: u32,
+    start_bid: u64,
+    buyout_price: u64,
+    duration_hours: u32
+) -> Result<(), String> {
+    let seller = validate_player_in_game(ctx)?;
+
+    if quantity == 0 {
+        return Err("Quantity must be at least 1".to_string());
+    }
+
+    if !AUCTION_DURATION_HOURS.contains(&duration_hours) {
+        return Err(format!("Auction duration must be one of {:?} hours", AUCTION_DURATION_HOURS));
+    }
+
+    if start_bid == 0 || start_bid > MAX_WALLET_BALANCE {
+        return Err("Starting bid must be at least 1".to_string());
+    }
+
+    if buyout_price != 0 && (buyout_price < start_bid || buyout_price > MAX_WALLET_BALANCE) {
+        return Err("Buyout price cannot be lower than the starting bid".to_string());
+    }
+
+    if AuctionListing::count_for_seller(ctx, &seller.identity) >= MAX_AUCTION_LISTINGS_PER_PLAYER {
+        return Err(format!("You can have at most {} auctions at once", MAX_AUCTION_LISTINGS_PER_PLAYER));
+    }
+
+    let mut stack = ctx.db.player_inventory().inventory_id().find(&inventory_id)
+        .filter(|stack| stack.player_identity == seller.identity)
+        .ok_or("Item not found in your inventory")?;
+
+    if stack.quantity < quantity {
+        return Err("Not enough items in inventory".to_string());
+    }
+
+    let item = ctx.db.game_items().item_id().find(&stack.item_id)
+        .ok_or("Item not found")?;
+
+    validate_rate_limit(ctx, "create_auction")?;
+
+    let deposit = auction_deposit(item.value, quantity, duration_hours);
+    PlayerWallet::debit(ctx, &seller.identity, deposit)
+        .map_err(|_| format!("You need {} to cover the listing deposit", deposit))?;
+
+    // Escrow: the items now exist only in the listing
+    let instance_id = stack.instance_id;
+    if let Some(instance_id) = instance_id {
+        escrow_item_instance(ctx, instance_id)?;
+    }
+
+    if stack.quantity == quantity {
+        ctx.db.player_inventory().inventory_id().delete(&inventory_id);
+    } else {
+        stack.quantity -= quantity;
+        ctx.db.player_inventory().inventory_id().update(stack);
+    }
+
+    let listing = ctx.db.auction_listing().insert(AuctionListing {
+        listing_id: 0, // auto_inc
+        seller_identity: seller.identity,
+        seller_username: seller.username.clone(),
+        item_id: item.item_id.clone(),
+        item_type: item.item_type.clone(),
+        item_name: item.item_name.clone(),
+        quantity,
+        instance_id,
+        start_bid,
+        buyout_price,
+        current_bid: 0,
+        bidder_identity: None,
+        bidder_username: String::new(),
+        deposit,
+        created_at: ctx.timestamp,
+        expires_at: ctx.timestamp + std::time::Duration::from_secs(duration_hours as u64 * 3600),
+    });
+
+    log::info!("{} listed {}x {} as auction {}", seller.username, quantity, item.item_id, listing.listing_id);
+    Ok(())
+}
+
+/// Take down your auction; only possible before anyone has bid, and the deposit is kept
+#[reducer]
+pub fn cancel_auction(ctx: &ReducerContext, listing_id: u64) -> Result<(), String> {
+    let listing = AuctionListing::filter_by_listing_id(ctx, listing_id)
+        .filter(|listing| listing.seller_identity == ctx.sender)
+        .ok_or("Auction not found")?;
+
+    if listing.has_bids() {
+        return Err("You cannot cancel an auction that has bids".to_string());
+    }
+
+    ctx.db.auction_listing().listing_id().delete(&listing_id);
+
+    send_auction_mail(
+        ctx,
+        listing.seller_identity,
+        listing.seller_username.clone(),
+        format!("Auction cancelled: {}", listing.item_name),
+        vec![(listing.item_id, listing.quantity, listing.instance_id)],
+        0
+    );
+
+    Ok(())
+}
+
+/// Bid on an auction; the bid is held until you are outbid or the auction ends
+/// A bid at or above the buyout price buys the item outright
+#[reducer]
+pub fn bid_on_auction(ctx: &ReducerContext, listing_id: u64, amount: u64) -> Result<(), String> {
+    let bidder = validate_player_in_game(ctx)?;
+
+    let mut listing = AuctionListing::filter_by_listing_id(ctx, listing_id)
+        .filter(|listing| listing.expires_at > ctx.timestamp)
+        .ok_or("Auction not found")?;
+
+    if listing.seller_identity == bidder.identity {
+        return Err("You cannot bid on your own auction".to_string());
+    }
+
+    if listing.bidder_identity == Some(bidder.identity) {
+        return Err("You are already the highest bidder".to_string());
+    }
+
+    if listing.buyout_price != 0 && amount >= listing.buyout_price {
+        return buyout_auction(ctx, listing_id);
+    }
+
+    if amount < listing.minimum_bid() {
+        return Err(format!("The minimum bid is {}", listing.minimum_bid()));
+    }
+
+    PlayerWallet::debit(ctx, &bidder.identity, amount)?;
+    refund_bidder(ctx, &listing);
+
+    if let Some(previous) = listing.bidder_identity {
+        PlayerNotification::send(ctx, previous, "auction", format!("You have been outbid on {}", listing.item_name));
+    }
+
+    listing.current_bid = amount;
+    listing.bidder_identity = Some(bidder.identity);
+    listing.bidder_username = bidder.username;
+    ctx.db.auction_listing().listing_id().update(listing);
+
+    Ok(())
+}
+
+/// Buy an auction outright at its buyout price
+#[reducer]
+pub fn buyout_auction(ctx: &ReducerContext, listing_id: u64) -> Result<(), String> {
+    let buyer = validate_player_in_game(ctx)?;
+
+    let listing = AuctionListing::filter_by_listing_id(ctx, listing_id)
+        .filter(|listing| listing.expires_at > ctx.timestamp)
+        .ok_or("Auction not found")?;
+
+    if listing.seller_identity == buyer.identity {
+        return Err("You cannot buy your own auction".to_string());
+    }
+
+    if listing.buyout_price == 0 {
+        return Err("That auction has no buyout price".to_string());
+    }
+
+    PlayerWallet::debit(ctx, &buyer.identity, listing.buyout_price)?;
+    refund_bidder(ctx, &listing);
+    settle_sale(ctx, &listing, buyer.identity, buyer.username, listing.buyout_price);
+
+    Ok(())
+}
+
+/// Close auctions whose time is up: sell to the high bidder or return the items to the seller
+/// Runs on the auction_expiry_schedule interval
+#[reducer]
+pub fn process_expired_auctions(ctx: &ReducerContext, _schedule: AuctionExpirySchedule) -> Result<(), String> {
+    // Only the scheduler may run this
+    if ctx.sender != ctx.identity() {
+        return Err("process_expired_auctions may only be invoked by the scheduler".to_string());
+    }
+
+    let expired: Vec<AuctionListing> = ctx.db.auction_listing().expires_at().filter(..=ctx.timestamp).collect();
+
+    for listing in expired {
+        match listing.bidder_identity {
+            Some(bidder_identity) => {
+                let bidder_username = listing.bidder_username.clone();
+                settle_sale(ctx, &listing, bidder_identity, bidder_username, listing.current_bid);
+            },
+            None => {
+                ctx.db.auction_listing().listing_id().delete(&listing.listing_id);
+                send_auction_mail(
+                    ctx,
+                    listing.seller_identity,
+                    listing.seller_username.clone(),
+                    format!("Auction expired: {}", listing.item_name),
+                    vec![(listing.item_id.clone(), listing.quantity, listing.instance_id)],
+                    0
+                );
+            },
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! Game-specific chat commands registered with the ServerModule command parser
+
+use spacetimedb::ReducerContext;
+use server_module::*;
+use crate::content::ZoneDefinition;
+
+/// Seconds a player must wait between /stuck uses
+const STUCK_COOLDOWN_SECONDS: f64 = 300.0;
+
+/// This module's chat commands
+const CUSTOM_CHAT_COMMANDS: &[ChatCommand] = &[
+    ChatCommand {
+        name: "stuck",
+        aliases: &["unstuck"],
+        usage: "",
+        help: "Return to your zone's spawn point (5 minute cooldown)",
+        min_args: 0,
+        max_args: 0,
+        handler: command_stuck,
+    },
+    ChatCommand {
+        name: "played",
+        aliases: &[],
+        usage: "",
+        help: "Show how long you have been playing",
+        min_args: 0,
+        max_args: 0,
+        handler: command_played,
+    },
+];
+
+server_module::register_chat_commands!(CUSTOM_CHAT_COMMANDS);
+
+/// Move a stuck player back to their zone's spawn point, or the zone origin if it has none
+/// A retired zone has no spawn point any more, so its players go to the origin
+fn command_stuck(ctx: &ReducerContext, command_ctx: &ChatCommandContext, _args: &[&str]) -> Result<(), String> {
+    let allowed = RateLimitBucket::try_consume(
+        ctx,
+        &command_ctx.player.identity,
+        "command:stuck",
+        1.0,
+        1.0 / STUCK_COOLDOWN_SECONDS
+    );
+
+    if !allowed {
+        return Err("You can only use /stuck once every 5 minutes".to_string());
+    }
+
+    let (spawn_x, spawn_y, spawn_z) = ZoneDefinition::find_active(ctx, &command_ctx.player.current_zone)
+        .map(|zone| (zone.spawn_x, zone.spawn_y, zone.spawn_z))
+        .unwrap_or((0.0, 0.0, 0.0));
+
+    Player::update_position(
+        ctx,
+        &command_ctx.player.identity,
+        spawn_x,
+        spawn_y,
+        spawn_z,
+        command_ctx.player.rotation_yaw,
+        ctx.timestamp
+    );
+
+    PlayerNotification::send(
+        ctx,
+        command_ctx.player.identity,
+        "command",
+        format!("You have been returned to the spawn point of {}", command_ctx.player.current_zone)
+    );
+    Ok(())
+}
+
+/// Report total played time across all sessions, and the current session
+fn command_played(ctx: &ReducerContext, command_ctx: &ChatCommandContext, _args: &[&str]) -> Result<(), String> {
+    let identity = &command_ctx.player.identity;
+    let session_seconds = GameSession::filter_by_identity(ctx, identity)
+        .map(|session| session.played_seconds(ctx.timestamp))
+        .unwrap_or(0);
+    let total_seconds = PlayerPlaytime::total_seconds(ctx, identity) + session_seconds;
+
+    let text = format!(
+        "Total time played: {}. This session: {}. Level {}",
+        format_played_time(total_seconds),
+        format_played_time(session_seconds),
+        command_ctx.player.level
+    );
+
+    PlayerNotification::send(ctx, *identity, "command", text);
+    Ok(())
+}
+
+fn format_played_time(seconds: u64) -> String {
+    format!("{}d {}h {}m", seconds / 86_400, (seconds % 86_400) / 3600, (seconds % 3600) / 60)
+}
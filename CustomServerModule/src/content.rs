@@ -0,0 +1,444 @@
+//! Versioned content imports: item, NPC template and zone definitions loaded from data files
+//!
+//! `server_tools import-content` validates the definition files and then calls, in order,
+//! `begin_content_import`, one `upsert_*` reducer per definition and `finish_content_import`.
+//! Every definition is tracked in `content_record`, so re-imports only touch what changed
+//! and definitions missing from a finished import are retired rather than deleted.
+
+use sha2::{Digest, Sha256};
+use spacetimedb::{table, reducer, ReducerContext, Identity, Timestamp, Table};
+use shared_module::*;
+use server_module::utils::validation::validate_admin_permissions;
+use crate::mechanics::*;
+use crate::vendor::*;
+
+/// Content kinds tracked in content_record
+pub const CONTENT_KIND_ITEM: &str = "item";
+pub const CONTENT_KIND_NPC_TEMPLATE: &str = "npc_template";
+pub const CONTENT_KIND_ZONE: &str = "zone";
+
+/// Stats and behaviour for every NPC of a type
+#[derive(Clone, Debug)]
+#[table(name = npc_templates, public)]
+pub struct NpcTemplate {
+    #[primary_key]
+    pub npc_type: String,
+    pub max_health: f32,
+    /// Chases players who come near
+    pub aggressive: bool,
+    /// Opens a vendor with default prices when spawned
+    pub vendor: bool,
+}
+
+/// A zone players can be in
+#[derive(Clone, Debug)]
+#[table(name = zone_definitions, public)]
+pub struct ZoneDefinition {
+    #[primary_key]
+    pub zone_id: String,
+    pub display_name: String,
+    pub min_level: u32,
+    pub spawn_x: f32,
+    pub spawn_y: f32,
+    pub spawn_z: f32,
+}
+
+/// Import bookkeeping for one definition
+#[derive(Clone, Debug)]
+#[table(name = content_record, public)]
+pub struct ContentRecord {
+    /// "<kind>:<id>", e.g. "item:sword_iron"
+    #[primary_key]
+    pub content_key: String,
+    pub kind: String,
+    pub content_id: String,
+
+    /// Hash of the definition's fields, to tell whether an import changed it
+    pub checksum: u64,
+    /// Import that last changed the definition
+    pub changed_in: String,
+    /// Import that last included the definition
+    pub seen_in: String,
+    pub updated_at: Timestamp,
+
+    /// Left out of a later import; kept so existing references keep working
+    pub retired: bool,
+}
+
+/// One run of the content import pipeline
+#[derive(Clone, Debug)]
+#[table(name = content_import, public)]
+pub struct ContentImport {
+    #[primary_key]
+    pub version: String,
+    pub started_by: Identity,
+    pub started_at: Timestamp,
+    pub finished_at: Option<Timestamp>,
+
+    pub added: u32,
+    pub updated: u32,
+    pub unchanged: u32,
+    pub retired: u32,
+}
+
+impl NpcTemplate {
+    /// Template for a type, unless it has been retired
+    pub fn find_active(ctx: &ReducerContext, npc_type: &str) -> Option<NpcTemplate> {
+        if ContentRecord::is_retired(ctx, CONTENT_KIND_NPC_TEMPLATE, npc_type) {
+            return None;
+        }
+        ctx.db.npc_templates().npc_type().find(&npc_type.to_string())
+    }
+
+    fn checksum(&self) -> u64 {
+        checksum_of(&[
+            self.npc_type.as_bytes(),
+            &self.max_health.to_le_bytes(),
+            &[self.aggressive as u8],
+            &[self.vendor as u8],
+        ])
+    }
+}
+
+impl ZoneDefinition {
+    /// Definition for a zone, unless it has been retired
+    pub fn find_active(ctx: &ReducerContext, zone_id: &str) -> Option<ZoneDefinition> {
+        if ContentRecord::is_retired(ctx, CONTENT_KIND_ZONE, zone_id) {
+            return None;
+        }
+        ctx.db.zone_definitions().zone_id().find(&zone_id.to_string())
+    }
+
+    fn checksum(&self) -> u64 {
+        checksum_of(&[
+            self.zone_id.as_bytes(),
+            self.display_name.as_bytes(),
+            &self.min_level.to_le_bytes(),
+            &self.spawn_x.to_le_bytes(),
+            &self.spawn_y.to_le_bytes(),
+            &self.spawn_z.to_le_bytes(),
+        ])
+    }
+}
+
+fn item_checksum(item: &GameItem) -> u64 {
+    checksum_of(&[
+        item.item_id.as_bytes(),
+        item.item_name.as_bytes(),
+        item.item_type.as_bytes(),
+        item.description.as_bytes(),
+        &item.max_stack_size.to_le_bytes(),
+        &item.value.to_le_bytes(),
+        item.properties_json.as_bytes(),
+    ])
+}
+
+/// SHA-256 of the length-prefixed fields, truncated to 64 bits
+/// Unlike std's hasher this is stable across Rust versions, so stored checksums stay comparable
+fn checksum_of(fields: &[&[u8]]) -> u64 {
+    let mut hasher = Sha256::new();
+    for field in fields {
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field);
+    }
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[..8].try_into().expect("SHA-256 digest is 32 bytes"))
+}
+
+/// What an upsert did to a definition
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ContentChange {
+    Added,
+    Updated,
+    Unchanged,
+}
+
+impl ContentRecord {
+    fn key(kind: &str, content_id: &str) -> String {
+        format!("{}:{}", kind, content_id)
+    }
+
+    /// Whether a definition was left out of a later import
+    pub fn is_retired(ctx: &ReducerContext, kind: &str, content_id: &str) -> bool {
+        ctx.db.content_record().content_key().find(&Self::key(kind, content_id))
+            .is_some_and(|record| record.retired)
+    }
+
+    /// Note that an import included a definition, returning whether it changed
+    /// Definitions that were retired come back into use
+    fn track(ctx: &ReducerContext, import: &ContentImport, kind: &str, content_id: &str, checksum: u64, exists: bool) -> ContentChange {
+        let key = Self::key(kind, content_id);
+        let existing = ctx.db.content_record().content_key().find(&key);
+
+        let change = match &existing {
+            _ if !exists => ContentChange::Added,
+            Some(record) if record.checksum == checksum && !record.retired => ContentChange::Unchanged,
+            _ => ContentChange::Updated,
+        };
+
+        let record = ContentRecord {
+            content_key: key,
+            kind: kind.to_string(),
+            content_id: content_id.to_string(),
+            checksum,
+            changed_in: match (&existing, change) {
+                (Some(record), ContentChange::Unchanged) => record.changed_in.clone(),
+                _ => import.version.clone(),
+            },
+            seen_in: import.version.clone(),
+            updated_at: ctx.timestamp,
+            retired: false,
+        };
+
+        if existing.is_some() {
+            ctx.db.content_record().content_key().update(record);
+        } else {
+            ctx.db.content_record().insert(record);
+        }
+
+        change
+    }
+}
+
+impl ContentImport {
+    /// The import that is still open for a version
+    fn find_open(ctx: &ReducerContext, version: &str) -> Result<ContentImport, String> {
+        ctx.db.content_import().version().find(&version.to_string())
+            .filter(|import| import.finished_at.is_none())
+            .ok_or_else(|| format!("No open content import for version '{}'", version))
+    }
+
+    fn count(ctx: &ReducerContext, mut import: ContentImport, change: ContentChange) {
+        match change {
+            ContentChange::Added => import.added += 1,
+            ContentChange::Updated => import.updated += 1,
+            ContentChange::Unchanged => import.unchanged += 1,
+        }
+        ctx.db.content_import().version().update(import);
+    }
+}
+
+/// Seed NPC templates for the built-in NPC types
+pub fn initialize_content(ctx: &ReducerContext) -> Result<(), String> {
+    if ctx.db.npc_templates().count() == 0 {
+        let default_templates = vec![
+            ("goblin", 50.0, true, false),
+            ("orc", 100.0, true, false),
+            ("dragon", 1000.0, true, false),
+            ("merchant", 100.0, false, true),
+        ];
+
+        for (npc_type, max_health, aggressive, vendor) in default_templates {
+            ctx.db.npc_templates().insert(NpcTemplate {
+                npc_type: npc_type.to_string(),
+                max_health,
+                aggressive,
+                vendor,
+            });
+        }
+    }
+
+    log::info!("Content definitions initialized");
+    Ok(())
+}
+
+/// Open a content import (admin function)
+/// Only one import can be open at a time; reopening an unfinished one restarts its counts
+/// A finished version can be imported again: its definitions all come back unchanged,
+/// and anything a later version retired or added is put back the way the version had it
+#[reducer]
+pub fn begin_content_import(ctx: &ReducerContext, version: String) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+
+    if version.trim().is_empty() {
+        return Err("Content imports need a version".to_string());
+    }
+
+    let other_open = ctx.db.content_import().iter()
+        .find(|import| import.finished_at.is_none() && import.version != version);
+    if let Some(other) = other_open {
+        return Err(format!("Content import '{}' is still open", other.version));
+    }
+
+    let import = ContentImport {
+        version: version.clone(),
+        started_by: ctx.sender,
+        started_at: ctx.timestamp,
+        finished_at: None,
+        added: 0,
+        updated: 0,
+        unchanged: 0,
+        retired: 0,
+    };
+
+    match ctx.db.content_import().version().find(&version) {
+        Some(existing) => {
+            if existing.finished_at.is_some() {
+                log::info!("Content version '{}' was already imported; importing it again", version);
+            }
+            ctx.db.content_import().version().update(import);
+        },
+        None => {
+            ctx.db.content_import().insert(import);
+        },
+    }
+
+    log::info!("Content import '{}' started", version);
+    Ok(())
+}
+
+/// Add or change an item definition as part of an open import (admin function)
+#[reducer]
+pub fn upsert_game_item(
+    ctx: &ReducerContext,
+    version: String,
+    item_id: String,
+    item_name: String,
+    item_type: String,
+    description: String,
+    max_stack_size: u32,
+    value: u32,
+    properties_json: String
+) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+    let import = ContentImport::find_open(ctx, &version)?;
+
+    let item = GameItem {
+        item_id,
+        item_name,
+        item_type,
+        description,
+        max_stack_size,
+        value,
+        properties_json,
+    };
+
+    let exists = ctx.db.game_items().item_id().find(&item.item_id).is_some();
+    let change = ContentRecord::track(ctx, &import, CONTENT_KIND_ITEM, &item.item_id, item_checksum(&item), exists);
+    if change != ContentChange::Unchanged {
+        GameItem::upsert(ctx, item)?;
+    }
+
+    ContentImport::count(ctx, import, change);
+    Ok(())
+}
+
+/// Add or change an NPC template as part of an open import (admin function)
+/// NPCs already in the world keep their stats until they are spawned again
+#[reducer]
+pub fn upsert_npc_template(
+    ctx: &ReducerContext,
+    version: String,
+    npc_type: String,
+    max_health: f32,
+    aggressive: bool,
+    vendor: bool
+) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+    let import = ContentImport::find_open(ctx, &version)?;
+
+    if npc_type.is_empty() {
+        return Err("NPC templates need a type".to_string());
+    }
+    if !max_health.is_finite() || max_health <= 0.0 {
+        return Err(format!("NPC template '{}' needs positive health", npc_type));
+    }
+
+    let template = NpcTemplate { npc_type, max_health, aggressive, vendor };
+
+    let exists = ctx.db.npc_templates().npc_type().find(&template.npc_type).is_some();
+    let change = ContentRecord::track(ctx, &import, CONTENT_KIND_NPC_TEMPLATE, &template.npc_type, template.checksum(), exists);
+    match change {
+        ContentChange::Added => {
+            ctx.db.npc_templates().insert(template);
+        },
+        ContentChange::Updated => {
+            ctx.db.npc_templates().npc_type().update(template);
+        },
+        ContentChange::Unchanged => {},
+    }
+
+    ContentImport::count(ctx, import, change);
+    Ok(())
+}
+
+/// Add or change a zone definition as part of an open import (admin function)
+#[reducer]
+pub fn upsert_zone_definition(
+    ctx: &ReducerContext,
+    version: String,
+    zone_id: String,
+    display_name: String,
+    min_level: u32,
+    spawn_x: f32,
+    spawn_y: f32,
+    spawn_z: f32
+) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+    let import = ContentImport::find_open(ctx, &version)?;
+
+    if zone_id.is_empty() || display_name.is_empty() {
+        return Err("Zones need an id and a display name".to_string());
+    }
+    if !spawn_x.is_finite() || !spawn_y.is_finite() || !spawn_z.is_finite() {
+        return Err(format!("Zone '{}' has an invalid spawn point", zone_id));
+    }
+
+    let zone = ZoneDefinition { zone_id, display_name, min_level, spawn_x, spawn_y, spawn_z };
+
+    let exists = ctx.db.zone_definitions().zone_id().find(&zone.zone_id).is_some();
+    let change = ContentRecord::track(ctx, &import, CONTENT_KIND_ZONE, &zone.zone_id, zone.checksum(), exists);
+    match change {
+        ContentChange::Added => {
+            ctx.db.zone_definitions().insert(zone);
+        },
+        ContentChange::Updated => {
+            ctx.db.zone_definitions().zone_id().update(zone);
+        },
+        ContentChange::Unchanged => {},
+    }
+
+    ContentImport::count(ctx, import, change);
+    Ok(())
+}
+
+/// Close an import, retiring every tracked definition it did not include (admin function)
+/// Retired items stay defined so stacks, mail and auctions holding them keep working,
+/// but vendors stop selling them; retired NPC types can no longer be spawned
+#[reducer]
+pub fn finish_content_import(ctx: &ReducerContext, version: String) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+    let mut import = ContentImport::find_open(ctx, &version)?;
+
+    let stale: Vec<ContentRecord> = ctx.db.content_record().iter()
+        .filter(|record| !record.retired && record.seen_in != version)
+        .collect();
+
+    for record in stale {
+        if record.kind == CONTENT_KIND_ITEM {
+            let stock: Vec<VendorStock> = ctx.db.vendor_stock().iter()
+                .filter(|stock| stock.item_id == record.content_id)
+                .collect();
+            for entry in stock {
+                ctx.db.vendor_stock().stock_id().delete(&entry.stock_id);
+            }
+        }
+
+        log::info!("Retiring {} '{}'", record.kind, record.content_id);
+        ctx.db.content_record().content_key().update(ContentRecord {
+            retired: true,
+            updated_at: ctx.timestamp,
+            ..record
+        });
+        import.retired += 1;
+    }
+
+    import.finished_at = Some(ctx.timestamp);
+    log::info!(
+        "Content import '{}' finished: {} added, {} updated, {} unchanged, {} retired",
+        import.version, import.added, import.updated, import.unchanged, import.retired
+    );
+    ctx.db.content_import().version().update(import);
+
+    Ok(())
+}
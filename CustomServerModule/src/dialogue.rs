@@ -0,0 +1,433 @@
+//! NPC dialogue graphs with conditional choices and actions
+
+use spacetimedb::{table, reducer, client_visibility_filter, Filter, ReducerContext, Identity, Timestamp, Table};
+use shared_module::*;
+use server_module::*;
+use server_module::utils::validation::{validate_player_in_game, validate_world_position};
+use crate::*;
+use crate::mechanics::*;
+use crate::quest::*;
+
+/// What must be true for a player to be offered a dialogue option
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogueCondition {
+    /// Always offered
+    None,
+    /// Player level is at least the condition value
+    MinLevel,
+    /// The target quest has never been accepted or is a daily quest off cooldown
+    QuestNotStarted,
+    /// The target quest is in the player's quest log
+    QuestActive,
+    /// The target quest is in the quest log with every objective done
+    QuestReady,
+    /// The target quest has been turned in at least once
+    QuestCompleted,
+    /// The player holds at least the condition value of the target item
+    HasItem,
+}
+
+impl std::fmt::Display for DialogueCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DialogueCondition::None => "none",
+            DialogueCondition::MinLevel => "min_level",
+            DialogueCondition::QuestNotStarted => "quest_not_started",
+            DialogueCondition::QuestActive => "quest_active",
+            DialogueCondition::QuestReady => "quest_ready",
+            DialogueCondition::QuestCompleted => "quest_completed",
+            DialogueCondition::HasItem => "has_item",
+        })
+    }
+}
+
+impl DialogueCondition {
+    pub fn from_string(s: &str) -> Option<DialogueCondition> {
+        match s {
+            "none" | "" => Some(DialogueCondition::None),
+            "min_level" => Some(DialogueCondition::MinLevel),
+            "quest_not_started" => Some(DialogueCondition::QuestNotStarted),
+            "quest_active" => Some(DialogueCondition::QuestActive),
+            "quest_ready" => Some(DialogueCondition::QuestReady),
+            "quest_completed" => Some(DialogueCondition::QuestCompleted),
+            "has_item" => Some(DialogueCondition::HasItem),
+            _ => None,
+        }
+    }
+}
+
+/// What happens when a player picks a dialogue option
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogueAction {
+    /// Just move to the next node
+    None,
+    /// Open the NPC's vendor window
+    OpenVendor,
+    /// Accept the target quest
+    GiveQuest,
+    /// Turn in the target quest
+    TurnInQuest,
+    /// Move the player to the action position in the target zone
+    Teleport,
+}
+
+impl std::fmt::Display for DialogueAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DialogueAction::None => "none",
+            DialogueAction::OpenVendor => "open_vendor",
+            DialogueAction::GiveQuest => "give_quest",
+            DialogueAction::TurnInQuest => "turn_in_quest",
+            DialogueAction::Teleport => "teleport",
+        })
+    }
+}
+
+impl DialogueAction {
+    pub fn from_string(s: &str) -> Option<DialogueAction> {
+        match s {
+            "none" | "" => Some(DialogueAction::None),
+            "open_vendor" => Some(DialogueAction::OpenVendor),
+            "give_quest" => Some(DialogueAction::GiveQuest),
+            "turn_in_quest" => Some(DialogueAction::TurnInQuest),
+            "teleport" => Some(DialogueAction::Teleport),
+            _ => None,
+        }
+    }
+}
+
+/// A line an NPC says
+#[derive(Clone, Debug)]
+#[table(name = dialogue_nodes, public)]
+pub struct DialogueNode {
+    #[primary_key]
+    pub node_id: String,
+    pub text: String,
+}
+
+/// A reply the player can pick at a dialogue node
+#[derive(Clone, Debug)]
+#[table(name = dialogue_options, public)]
+pub struct DialogueOption {
+    #[primary_key]
+    #[auto_inc]
+    pub option_id: u64,
+
+    #[index(btree)]
+    pub node_id: String,
+    /// Display order within the node
+    pub option_index: u32,
+    pub text: String,
+
+    /// DialogueCondition as a string
+    pub condition: String,
+    /// Quest or item the condition applies to
+    pub condition_target: String,
+    /// Level or item count the condition needs
+    pub condition_value: u32,
+
+    /// DialogueAction as a string
+    pub action: String,
+    /// Quest or zone the action applies to
+    pub action_target: String,
+    /// Destination for teleport actions
+    pub action_x: f32,
+    pub action_y: f32,
+    pub action_z: f32,
+
+    /// Node to show next; None ends the conversation
+    pub next_node_id: Option<String>,
+}
+
+/// Which dialogue an NPC starts with
+/// Keyed by NPC name, falling back to NPC type (e.g. every "merchant")
+#[derive(Clone, Debug)]
+#[table(name = npc_dialogue, public)]
+pub struct NpcDialogue {
+    #[primary_key]
+    pub npc_key: String,
+    pub start_node_id: String,
+}
+
+/// A player's conversation with an NPC
+#[derive(Clone, Debug)]
+#[table(name = player_conversation, public)]
+pub struct PlayerConversation {
+    #[primary_key]
+    pub player_identity: Identity,
+    pub npc_id: u64,
+
+    /// Node being shown; None once the dialogue has finished but a window it opened is still up
+    pub node_id: Option<String>,
+    /// Options at the current node whose conditions the player meets
+    pub available_option_ids: Vec<u64>,
+
+    /// Set by open_vendor; the client shows the NPC's vendor window
+    pub vendor_open: bool,
+    pub updated_at: Timestamp,
+}
+
+/// Players only see their own conversation
+#[client_visibility_filter]
+const PLAYER_CONVERSATION_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM player_conversation WHERE player_identity = :sender"
+);
+
+impl DialogueOption {
+    /// Whether a player meets this option's condition
+    /// Options with conditions this build does not know are never offered
+    pub fn is_available(&self, ctx: &ReducerContext, player: &Player) -> bool {
+        let Some(condition) = DialogueCondition::from_string(&self.condition) else {
+            return false;
+        };
+
+        let quest = || ctx.db.quest_definitions().quest_id().find(&self.condition_target);
+        let active = || PlayerQuest::find(ctx, &player.identity, &self.condition_target);
+        let completion = || QuestCompletion::find(ctx, &player.identity, &self.condition_target);
+
+        match condition {
+            DialogueCondition::None => true,
+            DialogueCondition::MinLevel => player.level >= self.condition_value,
+            DialogueCondition::QuestNotStarted => {
+                active().is_none() && match (quest(), completion()) {
+                    (Some(_), None) => true,
+                    (Some(quest), Some(completion)) => quest.daily && !completion.on_cooldown,
+                    (None, _) => false,
+                }
+            },
+            DialogueCondition::QuestActive => active().is_some(),
+            DialogueCondition::QuestReady => match (quest(), active()) {
+                (Some(quest), Some(entry)) => entry.is_complete(&quest.objectives(ctx)),
+                _ => false,
+            },
+            DialogueCondition::QuestCompleted => completion().is_some(),
+            DialogueCondition::HasItem => {
+                count_inventory_item(ctx, &player.identity, &self.condition_target) >= self.condition_value.max(1)
+            },
+        }
+    }
+}
+
+impl DialogueNode {
+    /// The node's options in display order
+    pub fn options(ctx: &ReducerContext, node_id: &str) -> Vec<DialogueOption> {
+        let mut options: Vec<DialogueOption> = ctx.db.dialogue_options().node_id().filter(&node_id.to_string()).collect();
+        options.sort_by_key(|option| option.option_index);
+        options
+    }
+}
+
+impl NpcDialogue {
+    /// The node an NPC's dialogue starts at, by name and then by type
+    pub fn start_node_for(ctx: &ReducerContext, npc: &NPC) -> Option<String> {
+        ctx.db.npc_dialogue().npc_key().find(&npc.name)
+            .or_else(|| ctx.db.npc_dialogue().npc_key().find(&npc.npc_type))
+            .map(|dialogue| dialogue.start_node_id)
+    }
+}
+
+impl PlayerConversation {
+    /// Show a node to the player, working out which options they get
+    fn enter_node(ctx: &ReducerContext, player: &Player, npc_id: u64, node_id: Option<String>, vendor_open: bool) {
+        let available_option_ids = match &node_id {
+            Some(node_id) => DialogueNode::options(ctx, node_id).into_iter()
+                .filter(|option| option.is_available(ctx, player))
+                .map(|option| option.option_id)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        // Nothing left to show and no window open
+        if node_id.is_none() && !vendor_open {
+            ctx.db.player_conversation().player_identity().delete(&player.identity);
+            return;
+        }
+
+        let conversation = PlayerConversation {
+            player_identity: player.identity,
+            npc_id,
+            node_id,
+            available_option_ids,
+            vendor_open,
+            updated_at: ctx.timestamp,
+        };
+
+        if ctx.db.player_conversation().player_identity().find(&player.identity).is_some() {
+            ctx.db.player_conversation().player_identity().update(conversation);
+        } else {
+            ctx.db.player_conversation().insert(conversation);
+        }
+    }
+}
+
+/// Set up default dialogue
+pub fn initialize_dialogue(ctx: &ReducerContext) -> Result<(), String> {
+    if ctx.db.dialogue_nodes().count() == 0 {
+        create_default_dialogue(ctx);
+    }
+
+    log::info!("Dialogue system initialized");
+    Ok(())
+}
+
+/// Create default dialogue for the default quest givers and merchants
+fn create_default_dialogue(ctx: &ReducerContext) {
+    let default_nodes = vec![
+        ("aldric_greeting", "Keep your blade sharp, traveller. These are troubled times."),
+        ("aldric_goblins", "Goblins have been raiding the farms. Thin them out and I will see you paid."),
+        ("aldric_thanks", "Good work. The farmers will sleep easier tonight."),
+        ("merchant_greeting", "Welcome! Have a look at my wares."),
+    ];
+
+    for (node_id, text) in default_nodes {
+        ctx.db.dialogue_nodes().insert(DialogueNode {
+            node_id: node_id.to_string(),
+            text: text.to_string(),
+        });
+    }
+
+    // (node, text, condition, condition target and value, action, action target and position, next node)
+    let default_options = vec![
+        ("aldric_greeting", "Is there any work?", DialogueCondition::QuestNotStarted, ("goblin_menace", 0), DialogueAction::None, ("", (0.0, 0.0, 0.0)), Some("aldric_goblins")),
+        ("aldric_greeting", "The goblins are dealt with.", DialogueCondition::QuestReady, ("goblin_menace", 0), DialogueAction::TurnInQuest, ("goblin_menace", (0.0, 0.0, 0.0)), Some("aldric_thanks")),
+        ("aldric_greeting", "I have scouted the ridge.", DialogueCondition::QuestReady, ("scouting_report", 0), DialogueAction::TurnInQuest, ("scouting_report", (0.0, 0.0, 0.0)), Some("aldric_thanks")),
+        ("aldric_greeting", "Can you get me to the eastern ridge?", DialogueCondition::QuestActive, ("scouting_report", 0), DialogueAction::Teleport, (DEFAULT_STARTING_ZONE, (490.0, 0.0, 0.0)), None),
+        ("aldric_greeting", "Farewell.", DialogueCondition::None, ("", 0), DialogueAction::None, ("", (0.0, 0.0, 0.0)), None),
+        ("aldric_goblins", "I will take care of it.", DialogueCondition::None, ("", 0), DialogueAction::GiveQuest, ("goblin_menace", (0.0, 0.0, 0.0)), None),
+        ("aldric_goblins", "Not now.", DialogueCondition::None, ("", 0), DialogueAction::None, ("", (0.0, 0.0, 0.0)), None),
+        ("aldric_thanks", "Farewell.", DialogueCondition::None, ("", 0), DialogueAction::None, ("", (0.0, 0.0, 0.0)), None),
+        ("merchant_greeting", "Show me what you have.", DialogueCondition::None, ("", 0), DialogueAction::OpenVendor, ("", (0.0, 0.0, 0.0)), None),
+        ("merchant_greeting", "Just looking.", DialogueCondition::None, ("", 0), DialogueAction::None, ("", (0.0, 0.0, 0.0)), None),
+    ];
+
+    let mut next_index: std::collections::HashMap<&str, u32> = std::collections::HashMap::new();
+    for (node_id, text, condition, (condition_target, condition_value), action, (action_target, (x, y, z)), next_node) in default_options {
+        let index = next_index.entry(node_id).or_insert(0);
+        ctx.db.dialogue_options().insert(DialogueOption {
+            option_id: 0, // auto_inc
+            node_id: node_id.to_string(),
+            option_index: *index,
+            text: text.to_string(),
+            condition: condition.to_string(),
+            condition_target: condition_target.to_string(),
+            condition_value,
+            action: action.to_string(),
+            action_target: action_target.to_string(),
+            action_x: x,
+            action_y: y,
+            action_z: z,
+            next_node_id: next_node.map(|node: &str| node.to_string()),
+        });
+        *index += 1;
+    }
+
+    for (npc_key, start_node) in [("Captain Aldric", "aldric_greeting"), ("merchant", "merchant_greeting")] {
+        ctx.db.npc_dialogue().insert(NpcDialogue {
+            npc_key: npc_key.to_string(),
+            start_node_id: start_node.to_string(),
+        });
+    }
+}
+
+/// Move a player to a teleport destination
+fn teleport_player(ctx: &ReducerContext, player: &Player, zone: &str, x: f32, y: f32, z: f32) -> Result<(), String> {
+    validate_world_position(x, y, z)?;
+
+    let mut moved = player.clone();
+    if !zone.is_empty() {
+        moved.current_zone = zone.to_string();
+    }
+    moved.position_x = x;
+    moved.position_y = y;
+    moved.position_z = z;
+    moved.last_seen = ctx.timestamp;
+
+    PartyMember::sync_from_player(ctx, &moved);
+    ctx.db.game_players().identity().update(moved.clone());
+
+    record_quest_event(ctx, &player.identity, QuestEvent::PlayerMoved {
+        zone: moved.current_zone,
+        x,
+        y,
+        z,
+    });
+    Ok(())
+}
+
+/// Start talking to an NPC you are standing next to
+/// NPCs without dialogue still count for talk objectives
+#[reducer]
+pub fn talk_to_npc(ctx: &ReducerContext, npc_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    let npc = ctx.db.npcs().npc_id().find(&npc_id)
+        .ok_or("NPC not found")?;
+    validate_npc_in_range(&player, &npc)?;
+
+    record_quest_event(ctx, &player.identity, QuestEvent::NpcTalkedTo { npc_name: npc.name.clone() });
+
+    // Progress from the talk can change which options are offered
+    let player = validate_player_in_game(ctx)?;
+    let start_node = NpcDialogue::start_node_for(ctx, &npc);
+    PlayerConversation::enter_node(ctx, &player, npc_id, start_node, false);
+    Ok(())
+}
+
+/// Pick one of the options offered at the current dialogue node
+#[reducer]
+pub fn choose_dialogue_option(ctx: &ReducerContext, option_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+
+    let conversation = ctx.db.player_conversation().player_identity().find(&player.identity)
+        .ok_or("You are not talking to anyone")?;
+
+    let npc = ctx.db.npcs().npc_id().find(&conversation.npc_id)
+        .ok_or("NPC not found")?;
+    // Walking away ends the conversation; an error would roll the delete back,
+    // so the player is told with a notification instead
+    if let Err(e) = validate_npc_in_range(&player, &npc) {
+        ctx.db.player_conversation().player_identity().delete(&player.identity);
+        PlayerNotification::send(ctx, player.identity, "dialogue", e);
+        return Ok(());
+    }
+
+    let option = ctx.db.dialogue_options().option_id().find(&option_id)
+        .filter(|option| Some(&option.node_id) == conversation.node_id.as_ref())
+        .filter(|option| conversation.available_option_ids.contains(&option.option_id))
+        .ok_or("That option is not available")?;
+
+    // Conditions may have changed since the node was shown
+    if !option.is_available(ctx, &player) {
+        return Err("That option is not available".to_string());
+    }
+
+    let action = DialogueAction::from_string(&option.action)
+        .ok_or("That option does nothing")?;
+
+    let mut vendor_open = false;
+    match action {
+        DialogueAction::None => {},
+        DialogueAction::OpenVendor => {
+            ctx.db.vendor().npc_id().find(&npc.npc_id)
+                .ok_or_else(|| format!("{} has nothing to trade", npc.name))?;
+            vendor_open = true;
+        },
+        DialogueAction::GiveQuest => accept_quest(ctx, option.action_target.clone())?,
+        DialogueAction::TurnInQuest => turn_in_quest(ctx, option.action_target.clone())?,
+        DialogueAction::Teleport => {
+            teleport_player(ctx, &player, &option.action_target, option.action_x, option.action_y, option.action_z)?;
+        },
+    }
+
+    // Actions can change the player (level, quests, position), so re-read before showing the next node
+    let player = validate_player_in_game(ctx)?;
+    let next_node = if action == DialogueAction::Teleport { None } else { option.next_node_id };
+    PlayerConversation::enter_node(ctx, &player, npc.npc_id, next_node, vendor_open);
+    Ok(())
+}
+
+/// Walk away from the NPC you are talking to
+#[reducer]
+pub fn end_conversation(ctx: &ReducerContext) -> Result<(), String> {
+    ctx.db.player_conversation().player_identity().delete(&ctx.sender);
+    Ok(())
+}
@@ -0,0 +1,128 @@
+//! Guild bank deposits and withdrawals
+//!
+//! The bank tables live with the rest of the guild schema in ServerModule;
+//! moving items in and out needs the inventory, which lives here.
+
+use spacetimedb::{reducer, ReducerContext, Table};
+use shared_module::*;
+use server_module::*;
+use server_module::reducers::guild::find_guild_with_permission;
+use crate::mechanics::*;
+use crate::item_instance::{escrow_item_instance, give_item_instance};
+
+/// Move items from your inventory into the guild bank
+/// Tops up existing stacks of the same item before taking new bank slots
+/// A unique item always takes a slot of its own and is owned by the module while banked
+#[reducer]
+pub fn deposit_to_guild_bank(ctx: &ReducerContext, inventory_id: u64, quantity: u32) -> Result<(), String> {
+    let (membership, _rank, guild) = find_guild_with_permission(ctx, GUILD_PERM_BANK_DEPOSIT)?;
+
+    if quantity == 0 {
+        return Err("Quantity must be at least 1".to_string());
+    }
+
+    let mut stack = ctx.db.player_inventory().inventory_id().find(&inventory_id)
+        .filter(|stack| stack.player_identity == ctx.sender)
+        .ok_or("Item not found in your inventory")?;
+
+    if stack.quantity < quantity {
+        return Err("Not enough items in inventory".to_string());
+    }
+
+    let item = ctx.db.game_items().item_id().find(&stack.item_id)
+        .ok_or("Item not found")?;
+
+    // Take from the player first; an error further down rolls this back
+    if let Some(instance_id) = stack.instance_id {
+        escrow_item_instance(ctx, instance_id)?;
+    }
+
+    if stack.quantity == quantity {
+        ctx.db.player_inventory().inventory_id().delete(&inventory_id);
+    } else {
+        stack.quantity -= quantity;
+        ctx.db.player_inventory().inventory_id().update(stack.clone());
+    }
+
+    let mut remaining = quantity;
+    for mut bank_stack in GuildBankItem::get_items(ctx, guild.guild_id) {
+        if remaining == 0 || stack.is_unique() {
+            break;
+        }
+        if bank_stack.item_id != item.item_id || bank_stack.instance_id.is_some() || bank_stack.quantity >= item.max_stack_size {
+            continue;
+        }
+
+        let moved = remaining.min(item.max_stack_size - bank_stack.quantity);
+        bank_stack.quantity += moved;
+        remaining -= moved;
+        ctx.db.guild_bank_item().bank_item_id().update(bank_stack);
+    }
+
+    while remaining > 0 {
+        let slot_index = GuildBankItem::next_free_slot(ctx, guild.guild_id)
+            .ok_or("The guild bank is full")?;
+
+        let moved = remaining.min(item.max_stack_size.max(1));
+        ctx.db.guild_bank_item().insert(GuildBankItem {
+            bank_item_id: 0, // auto_inc
+            guild_id: guild.guild_id,
+            item_type: item.item_type.clone(),
+            item_id: item.item_id.clone(),
+            quantity: moved,
+            slot_index,
+            instance_id: stack.instance_id,
+        });
+        remaining -= moved;
+    }
+
+    GuildBankLog::record(ctx, guild.guild_id, &membership, "deposit", &item.item_id, quantity);
+
+    log::info!("{} deposited {}x {} into guild {}'s bank", membership.member_username, quantity, item.item_id, guild.guild_id);
+    Ok(())
+}
+
+/// Take items from the guild bank, within your rank's daily withdrawal limit
+#[reducer]
+pub fn withdraw_from_guild_bank(ctx: &ReducerContext, bank_item_id: u64, quantity: u32) -> Result<(), String> {
+    let (membership, rank, guild) = find_guild_with_permission(ctx, GUILD_PERM_BANK_WITHDRAW)?;
+
+    if quantity == 0 {
+        return Err("Quantity must be at least 1".to_string());
+    }
+
+    let mut bank_stack = ctx.db.guild_bank_item().bank_item_id().find(&bank_item_id)
+        .filter(|bank_stack| bank_stack.guild_id == guild.guild_id)
+        .ok_or("Item not found in the guild bank")?;
+
+    if bank_stack.quantity < quantity {
+        return Err("Not enough items in the guild bank".to_string());
+    }
+
+    // The guild master's rank is unlimited; everyone else counts against a rolling day
+    if !rank.is_guild_master() {
+        let since = ctx.timestamp - std::time::Duration::from_secs(GUILD_WITHDRAW_WINDOW_SECONDS);
+        let withdrawn = GuildBankLog::withdrawn_since(ctx, guild.guild_id, &membership.member_identity, since);
+        let allowance = rank.daily_withdraw_limit.saturating_sub(withdrawn);
+
+        if quantity > allowance {
+            return Err(format!("Your rank may withdraw {} more item(s) today", allowance));
+        }
+    }
+
+    if bank_stack.quantity == quantity {
+        ctx.db.guild_bank_item().bank_item_id().delete(&bank_item_id);
+    } else {
+        bank_stack.quantity -= quantity;
+        ctx.db.guild_bank_item().bank_item_id().update(bank_stack.clone());
+    }
+
+    match bank_stack.instance_id {
+        Some(instance_id) => give_item_instance(ctx, instance_id, &ctx.sender)?,
+        None => add_item_to_inventory(ctx, &ctx.sender, &bank_stack.item_id, quantity)?,
+    }
+
+    GuildBankLog::record(ctx, guild.guild_id, &membership, "withdraw", &bank_stack.item_id, quantity);
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! Consumable item effects
+//!
+//! `use_item` looks up each effect listed in a consumable's properties in `ITEM_EFFECTS`
+//! and runs its handler. New effects are added to that table; nothing in `use_item`
+//! needs to change.
+
+use spacetimedb::{ReducerContext, Identity};
+use shared_module::*;
+use server_module::*;
+
+/// Signature every effect handler implements
+pub type ItemEffectHandler = fn(&ReducerContext, &Identity, &ItemEffect) -> Result<(), String>;
+
+/// Every effect a consumable can list, by name
+const ITEM_EFFECTS: &[(&str, ItemEffectHandler)] = &[
+    ("heal", effect_heal),
+    ("restore_full_health", effect_restore_full_health),
+];
+
+/// Find the handler for an effect name
+pub fn find_item_effect(name: &str) -> Option<ItemEffectHandler> {
+    ITEM_EFFECTS.iter()
+        .find(|(existing, _)| *existing == name)
+        .map(|(_, handler)| *handler)
+}
+
+/// Check every effect a consumable lists exists
+pub fn validate_item_effects(properties: &ItemProperties) -> Result<(), String> {
+    if let ItemProperties::Consumable(consumable) = properties {
+        if let Some(unknown) = consumable.effects.iter().find(|effect| find_item_effect(&effect.effect).is_none()) {
+            return Err(format!("Unknown item effect '{}'", unknown.effect));
+        }
+    }
+    Ok(())
+}
+
+/// Run one effect for the player using the item
+pub fn apply_item_effect(ctx: &ReducerContext, identity: &Identity, effect: &ItemEffect) -> Result<(), String> {
+    let handler = find_item_effect(&effect.effect)
+        .ok_or_else(|| format!("Unknown item effect '{}'", effect.effect))?;
+    handler(ctx, identity, effect)
+}
+
+/// Restore `amount` health, up to the player's maximum
+fn effect_heal(ctx: &ReducerContext, identity: &Identity, effect: &ItemEffect) -> Result<(), String> {
+    let mut player = ctx.db.game_players().identity().find(identity)
+        .ok_or("Player not found")?;
+
+    player.health = (player.health + effect.amount.max(0.0)).min(player.max_health);
+    log::info!("Player {} healed for {} HP", player.username, effect.amount);
+
+    ctx.db.game_players().identity().update(player);
+    Ok(())
+}
+
+/// Restore the player to full health
+fn effect_restore_full_health(ctx: &ReducerContext, identity: &Identity, _effect: &ItemEffect) -> Result<(), String> {
+    let mut player = ctx.db.game_players().identity().find(identity)
+        .ok_or("Player not found")?;
+
+    player.health = player.max_health;
+    ctx.db.game_players().identity().update(player);
+    Ok(())
+}
@@ -0,0 +1,317 @@
+//! Unique item instances: non-stackable weapons and armor with their own durability,
+//! rolled quality and affixes, creator and binding
+//!
+//! Each instance sits alone in an inventory slot; its PlayerInventory row carries the
+//! instance_id. Equipped instances stay in their slot and wear down in combat.
+
+use spacetimedb::{table, reducer, ReducerContext, Identity, Timestamp, Table};
+use spacetimedb::rand::Rng;
+use shared_module::*;
+use server_module::*;
+use server_module::utils::validation::validate_player_in_game;
+use crate::mechanics::*;
+use crate::vendor::find_vendor_in_range;
+use crate::wallet::PlayerWallet;
+
+/// One copy of a weapon or armor item
+#[derive(Clone, Debug)]
+#[table(name = item_instances, public)]
+pub struct ItemInstance {
+    #[primary_key]
+    #[auto_inc]
+    pub instance_id: u64,
+    #[index(btree)]
+    pub owner_identity: Identity,
+    pub item_id: String,
+    pub quality: String, // A name from ITEM_QUALITY_TIERS
+    pub durability: u32,
+    pub max_durability: u32,
+    pub creator_identity: Option<Identity>,
+    pub created_at: Timestamp,
+    pub bind_on_equip: bool,
+    pub soulbound: bool,
+    pub equipped_slot: Option<String>, // "weapon" or "armor" while equipped
+}
+
+/// A bonus stat rolled onto an instance
+#[derive(Clone, Debug)]
+#[table(name = item_instance_affixes, public)]
+pub struct ItemInstanceAffix {
+    #[primary_key]
+    #[auto_inc]
+    pub affix_id: u64,
+    #[index(btree)]
+    pub instance_id: u64,
+    pub stat: String,
+    pub value: u32,
+}
+
+impl ItemInstance {
+    pub fn find_owned(ctx: &ReducerContext, identity: &Identity, instance_id: u64) -> Result<ItemInstance, String> {
+        ctx.db.item_instances().instance_id().find(&instance_id)
+            .filter(|instance| instance.owner_identity == *identity)
+            .ok_or_else(|| "Item not found in your inventory".to_string())
+    }
+
+    /// The instance a player has equipped in a slot
+    pub fn find_equipped(ctx: &ReducerContext, identity: &Identity, slot: &str) -> Option<ItemInstance> {
+        ctx.db.item_instances().owner_identity().filter(identity)
+            .find(|instance| instance.equipped_slot.as_deref() == Some(slot))
+    }
+
+    pub fn get_affixes(ctx: &ReducerContext, instance_id: u64) -> Vec<ItemInstanceAffix> {
+        ctx.db.item_instance_affixes().instance_id().filter(&instance_id).collect()
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.durability == 0
+    }
+
+    /// Whether the instance may leave its owner's inventory
+    pub fn validate_tradeable(&self) -> Result<(), String> {
+        if self.soulbound {
+            return Err("Soulbound items cannot be traded".to_string());
+        }
+        if self.equipped_slot.is_some() {
+            return Err("Unequip the item first".to_string());
+        }
+        Ok(())
+    }
+
+    /// Delete an instance and its affixes
+    pub fn destroy(ctx: &ReducerContext, instance_id: u64) {
+        for affix in Self::get_affixes(ctx, instance_id) {
+            ctx.db.item_instance_affixes().affix_id().delete(&affix.affix_id);
+        }
+        ctx.db.item_instances().instance_id().delete(&instance_id);
+    }
+}
+
+/// Equipment slot an item goes in; None for items that are not unique
+pub fn equipment_slot(properties: &ItemProperties) -> Option<&'static str> {
+    match properties {
+        ItemProperties::Weapon(_) => Some("weapon"),
+        ItemProperties::Armor(_) => Some("armor"),
+        _ => None,
+    }
+}
+
+/// Roll a new instance of a weapon or armor item for a player (internal)
+/// The caller places it in an inventory slot
+pub fn create_item_instance(
+    ctx: &ReducerContext,
+    owner: &Identity,
+    item: &GameItem,
+    creator: Option<Identity>
+) -> Result<ItemInstance, String> {
+    let (base_durability, binding) = match item.properties()? {
+        ItemProperties::Weapon(weapon) => (weapon.durability, weapon.binding),
+        ItemProperties::Armor(armor) => (armor.durability, armor.binding),
+        _ => return Err(format!("'{}' is not a unique item", item.item_id)),
+    };
+
+    let total_weight: u32 = ITEM_QUALITY_TIERS.iter().map(|(_, weight, _, _)| weight).sum();
+    let (quality, multiplier, affix_count) = item_quality_for_roll(ctx.rng().gen_range(0..total_weight));
+    let max_durability = ((base_durability as f32 * multiplier).round() as u32).max(1);
+
+    let instance = ctx.db.item_instances().insert(ItemInstance {
+        instance_id: 0, // auto_inc
+        owner_identity: *owner,
+        item_id: item.item_id.clone(),
+        quality: quality.to_string(),
+        durability: max_durability,
+        max_durability,
+        creator_identity: creator,
+        created_at: ctx.timestamp,
+        bind_on_equip: binding == ItemBinding::OnEquip,
+        soulbound: binding == ItemBinding::OnPickup,
+        equipped_slot: None,
+    });
+
+    // Each affix rolls a different stat
+    let mut stats: Vec<(&str, u32, u32)> = ITEM_AFFIX_STATS.to_vec();
+    for _ in 0..affix_count.min(stats.len()) {
+        let (stat, min, max) = stats.swap_remove(ctx.rng().gen_range(0..stats.len()));
+        ctx.db.item_instance_affixes().insert(ItemInstanceAffix {
+            affix_id: 0, // auto_inc
+            instance_id: instance.instance_id,
+            stat: stat.to_string(),
+            value: ctx.rng().gen_range(min..=max),
+        });
+    }
+
+    Ok(instance)
+}
+
+/// Hand an existing instance to another player, in their lowest free slot (internal)
+/// The caller has already removed it from the old owner's inventory
+pub fn give_item_instance(ctx: &ReducerContext, instance_id: u64, new_owner: &Identity) -> Result<(), String> {
+    let mut instance = ctx.db.item_instances().instance_id().find(&instance_id)
+        .ok_or("Item not found")?;
+    instance.validate_tradeable()?;
+
+    let item = ctx.db.game_items().item_id().find(&instance.item_id)
+        .ok_or("Item not found")?;
+
+    let slot_index = *free_inventory_slots(ctx, new_owner).first()
+        .ok_or("Inventory is full")?;
+
+    ctx.db.player_inventory().insert(PlayerInventory {
+        inventory_id: 0, // auto_inc
+        player_identity: *new_owner,
+        item_type: item.item_type,
+        item_id: item.item_id,
+        quantity: 1,
+        slot_index,
+        instance_id: Some(instance_id),
+    });
+
+    instance.owner_identity = *new_owner;
+    ctx.db.item_instances().instance_id().update(instance);
+    Ok(())
+}
+
+/// Hold an instance out of any player's hands while mail, an auction or a guild bank has it (internal)
+/// The caller has already removed it from the owner's inventory; give_item_instance hands it on
+pub fn escrow_item_instance(ctx: &ReducerContext, instance_id: u64) -> Result<(), String> {
+    let mut instance = ctx.db.item_instances().instance_id().find(&instance_id)
+        .ok_or("Item not found")?;
+    instance.validate_tradeable()?;
+
+    instance.owner_identity = ctx.identity();
+    ctx.db.item_instances().instance_id().update(instance);
+    Ok(())
+}
+
+/// Wear down what a player has equipped in a slot (internal; called from combat)
+pub fn wear_equipped_item(ctx: &ReducerContext, identity: &Identity, slot: &str, amount: u32) {
+    let Some(mut instance) = ItemInstance::find_equipped(ctx, identity, slot) else {
+        return;
+    };
+
+    if instance.is_broken() {
+        return;
+    }
+
+    instance.durability = instance.durability.saturating_sub(amount);
+    if instance.is_broken() {
+        PlayerNotification::send(ctx, *identity, "item", format!("Your {} has broken and needs repair", instance.item_id));
+    }
+    ctx.db.item_instances().instance_id().update(instance);
+}
+
+/// Equip a weapon or armor instance, replacing whatever is in that slot
+/// Bind-on-equip items become soulbound
+#[reducer]
+pub fn equip_item_instance(ctx: &ReducerContext, instance_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    let mut instance = ItemInstance::find_owned(ctx, &player.identity, instance_id)?;
+
+    if instance.is_broken() {
+        return Err("That item is broken and needs repair".to_string());
+    }
+
+    let item = ctx.db.game_items().item_id().find(&instance.item_id)
+        .ok_or("Item not found")?;
+    let slot = equipment_slot(&item.properties()?)
+        .ok_or("That item cannot be equipped")?;
+
+    if let Some(mut current) = ItemInstance::find_equipped(ctx, &player.identity, slot) {
+        if current.instance_id == instance_id {
+            return Ok(());
+        }
+        current.equipped_slot = None;
+        ctx.db.item_instances().instance_id().update(current);
+    }
+
+    if instance.bind_on_equip {
+        instance.soulbound = true;
+    }
+    instance.equipped_slot = Some(slot.to_string());
+    ctx.db.item_instances().instance_id().update(instance);
+
+    log::info!("Player {} equipped {} ({})", player.username, item.item_id, instance_id);
+    Ok(())
+}
+
+#[reducer]
+pub fn unequip_item_instance(ctx: &ReducerContext, instance_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    let mut instance = ItemInstance::find_owned(ctx, &player.identity, instance_id)?;
+
+    if instance.equipped_slot.is_none() {
+        return Err("That item is not equipped".to_string());
+    }
+
+    instance.equipped_slot = None;
+    ctx.db.item_instances().instance_id().update(instance);
+    Ok(())
+}
+
+/// Destroy one of your item instances
+#[reducer]
+pub fn destroy_item_instance(ctx: &ReducerContext, instance_id: u64) -> Result<(), String> {
+    let player = ctx.db.game_players().identity().find(&ctx.sender)
+        .ok_or("Player not found")?;
+    ItemInstance::find_owned(ctx, &player.identity, instance_id)?;
+
+    if let Some(stack) = get_inventory(ctx, &player.identity).into_iter().find(|stack| stack.instance_id == Some(instance_id)) {
+        ctx.db.player_inventory().inventory_id().delete(&stack.inventory_id);
+    }
+    ItemInstance::destroy(ctx, instance_id);
+    Ok(())
+}
+
+/// Repair an item instance at a merchant you are standing next to
+#[reducer]
+pub fn repair_item_instance(ctx: &ReducerContext, npc_id: u64, instance_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    find_vendor_in_range(ctx, &player, npc_id)?;
+    let instance = ItemInstance::find_owned(ctx, &player.identity, instance_id)?;
+
+    if instance.durability == instance.max_durability {
+        return Err("That item does not need repair".to_string());
+    }
+
+    let cost = repair_instance(ctx, &player.identity, instance)?;
+    log::info!("{} repaired item {} for {}", player.username, instance_id, cost);
+    Ok(())
+}
+
+/// Repair every damaged item instance you own at a merchant you are standing next to
+#[reducer]
+pub fn repair_all_items(ctx: &ReducerContext, npc_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    find_vendor_in_range(ctx, &player, npc_id)?;
+
+    let damaged: Vec<ItemInstance> = ctx.db.item_instances().owner_identity().filter(&player.identity)
+        .filter(|instance| instance.durability < instance.max_durability)
+        .collect();
+
+    if damaged.is_empty() {
+        return Err("Nothing needs repair".to_string());
+    }
+
+    // Any debit failing rolls back every repair made before it
+    let mut total = 0u64;
+    for instance in damaged {
+        total += repair_instance(ctx, &player.identity, instance)?;
+    }
+
+    log::info!("{} repaired all items for {}", player.username, total);
+    Ok(())
+}
+
+/// Charge for and restore an instance to full durability, returning the cost
+fn repair_instance(ctx: &ReducerContext, identity: &Identity, mut instance: ItemInstance) -> Result<u64, String> {
+    let item = ctx.db.game_items().item_id().find(&instance.item_id)
+        .ok_or("Item not found")?;
+
+    let cost = item_repair_cost(item.value, instance.durability, instance.max_durability);
+    PlayerWallet::debit(ctx, identity, cost)
+        .map_err(|_| format!("You need {} to repair that", cost))?;
+
+    instance.durability = instance.max_durability;
+    ctx.db.item_instances().instance_id().update(instance);
+    Ok(cost)
+}
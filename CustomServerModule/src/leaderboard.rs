@@ -0,0 +1,304 @@
+//! Ranked leaderboards computed into snapshot tables on a schedule, with seasonal archives
+
+use spacetimedb::{table, reducer, ReducerContext, Identity, Timestamp, ScheduleAt, Table};
+use shared_module::*;
+use server_module::*;
+use server_module::utils::validation::validate_admin_permissions;
+use crate::mechanics::*;
+
+/// Board for player level and experience
+pub const LEVEL_BOARD: &str = "level";
+/// Seasonal board for NPC kills
+pub const NPC_KILLS_BOARD: &str = "npc_kills";
+/// Seasonal board for PvP rating, earned in duels
+pub const PVP_RATING_BOARD: &str = "pvp_rating";
+/// Prefix for per-skill boards, e.g. "skill:mining"
+pub const SKILL_BOARD_PREFIX: &str = "skill:";
+
+/// Boards whose stats start over each season
+const SEASONAL_BOARDS: [&str; 2] = [NPC_KILLS_BOARD, PVP_RATING_BOARD];
+
+/// Combat statistics tracked for the leaderboards
+#[derive(Clone, Debug)]
+#[table(name = player_combat_stats, public)]
+pub struct PlayerCombatStats {
+    #[primary_key]
+    pub player_identity: Identity,
+
+    /// NPC kills this season
+    pub npc_kills: u64,
+
+    /// PvP rating this season
+    pub pvp_rating: u32,
+    pub pvp_wins: u32,
+    pub pvp_losses: u32,
+}
+
+/// One ranked row of the most recent leaderboard snapshot
+/// Clients read a board through the board_rank index, e.g. the top 100 of "level" with
+/// `SELECT * FROM leaderboard_entry WHERE board = 'level' AND rank <= 100`, and their own
+/// standing with `WHERE player_identity = :sender`
+#[derive(Clone, Debug)]
+#[table(
+    name = leaderboard_entry,
+    public,
+    index(name = board_rank, btree(columns = [board, rank]))
+)]
+pub struct LeaderboardEntry {
+    #[primary_key]
+    #[auto_inc]
+    pub entry_id: u64,
+
+    pub board: String,
+    /// 1 is the top of the board
+    pub rank: u32,
+
+    #[index(btree)]
+    pub player_identity: Identity,
+    pub username: String,
+
+    pub score: u64,
+    /// Breaks ties in score, e.g. experience within a level
+    pub tiebreak: u64,
+
+    pub snapshot_at: Timestamp,
+}
+
+/// A leaderboard season; the current one has no end time
+#[derive(Clone, Debug)]
+#[table(name = leaderboard_season, public)]
+pub struct LeaderboardSeason {
+    #[primary_key]
+    #[auto_inc]
+    pub season_id: u64,
+    pub started_at: Timestamp,
+    pub ended_at: Option<Timestamp>,
+}
+
+/// Final standings of a seasonal board, kept when the season ends
+/// Subscribe by season_id, e.g. `SELECT * FROM leaderboard_archive WHERE season_id = 3`
+#[derive(Clone, Debug)]
+#[table(name = leaderboard_archive, public)]
+pub struct LeaderboardArchive {
+    #[primary_key]
+    #[auto_inc]
+    pub archive_id: u64,
+
+    #[index(btree)]
+    pub season_id: u64,
+
+    pub board: String,
+    pub rank: u32,
+    pub player_identity: Identity,
+    pub username: String,
+    pub score: u64,
+}
+
+/// Periodic job that recomputes the leaderboard snapshot
+#[table(name = leaderboard_schedule, scheduled(refresh_leaderboards))]
+pub struct LeaderboardSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub scheduled_id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+impl PlayerCombatStats {
+    /// Get a player's stats, or fresh ones if they have none yet
+    pub fn get(ctx: &ReducerContext, identity: &Identity) -> PlayerCombatStats {
+        ctx.db.player_combat_stats().player_identity().find(identity)
+            .unwrap_or(PlayerCombatStats {
+                player_identity: *identity,
+                npc_kills: 0,
+                pvp_rating: DEFAULT_PVP_RATING,
+                pvp_wins: 0,
+                pvp_losses: 0,
+            })
+    }
+
+    fn save(ctx: &ReducerContext, stats: PlayerCombatStats) {
+        if ctx.db.player_combat_stats().player_identity().find(&stats.player_identity).is_some() {
+            ctx.db.player_combat_stats().player_identity().update(stats);
+        } else {
+            ctx.db.player_combat_stats().insert(stats);
+        }
+    }
+
+    /// Count an NPC kill
+    pub fn record_npc_kill(ctx: &ReducerContext, identity: &Identity) {
+        let mut stats = Self::get(ctx, identity);
+        stats.npc_kills = stats.npc_kills.saturating_add(1);
+        Self::save(ctx, stats);
+    }
+
+    /// Apply the rating change from a finished duel
+    pub fn record_pvp_result(ctx: &ReducerContext, winner: &Identity, loser: &Identity) {
+        let mut winner_stats = Self::get(ctx, winner);
+        let mut loser_stats = Self::get(ctx, loser);
+
+        let (winner_rating, loser_rating) = elo_ratings_after_match(winner_stats.pvp_rating, loser_stats.pvp_rating);
+        winner_stats.pvp_rating = winner_rating;
+        winner_stats.pvp_wins = winner_stats.pvp_wins.saturating_add(1);
+        loser_stats.pvp_rating = loser_rating;
+        loser_stats.pvp_losses = loser_stats.pvp_losses.saturating_add(1);
+
+        Self::save(ctx, winner_stats);
+        Self::save(ctx, loser_stats);
+    }
+}
+
+impl LeaderboardEntry {
+    /// The top of a board from the latest snapshot
+    pub fn get_top(ctx: &ReducerContext, board: &str, limit: u32) -> Vec<LeaderboardEntry> {
+        let mut entries: Vec<LeaderboardEntry> = ctx.db.leaderboard_entry().board_rank()
+            .filter((board, 1..=limit))
+            .collect();
+        entries.sort_by_key(|entry| entry.rank);
+        entries
+    }
+}
+
+impl LeaderboardSeason {
+    /// The season in progress, starting the first one if needed
+    pub fn current(ctx: &ReducerContext) -> LeaderboardSeason {
+        ctx.db.leaderboard_season().iter()
+            .find(|season| season.ended_at.is_none())
+            .unwrap_or_else(|| ctx.db.leaderboard_season().insert(LeaderboardSeason {
+                season_id: 0, // auto_inc
+                started_at: ctx.timestamp,
+                ended_at: None,
+            }))
+    }
+}
+
+/// Start the leaderboard refresh job and the first season
+pub fn initialize_leaderboards(ctx: &ReducerContext) -> Result<(), String> {
+    LeaderboardSeason::current(ctx);
+
+    if ctx.db.leaderboard_schedule().count() == 0 {
+        let interval = std::time::Duration::from_secs(LEADERBOARD_REFRESH_INTERVAL_SECONDS);
+        ctx.db.leaderboard_schedule().insert(LeaderboardSchedule {
+            scheduled_id: 0, // auto_inc
+            scheduled_at: ScheduleAt::Interval(interval.into()),
+        });
+    }
+
+    log::info!("Leaderboards initialized");
+    Ok(())
+}
+
+/// Replace one board's snapshot with freshly ranked rows of (identity, username, score, tiebreak)
+fn write_board(ctx: &ReducerContext, board: &str, mut rows: Vec<(Identity, String, u64, u64)>) {
+    let stale: Vec<u64> = ctx.db.leaderboard_entry().board_rank()
+        .filter(board)
+        .map(|entry| entry.entry_id)
+        .collect();
+    for entry_id in stale {
+        ctx.db.leaderboard_entry().entry_id().delete(&entry_id);
+    }
+
+    rows.sort_by(|a, b| b.2.cmp(&a.2).then(b.3.cmp(&a.3)).then_with(|| a.1.cmp(&b.1)));
+
+    for (index, (player_identity, username, score, tiebreak)) in rows.into_iter().enumerate() {
+        ctx.db.leaderboard_entry().insert(LeaderboardEntry {
+            entry_id: 0, // auto_inc
+            board: board.to_string(),
+            rank: index as u32 + 1,
+            player_identity,
+            username,
+            score,
+            tiebreak,
+            snapshot_at: ctx.timestamp,
+        });
+    }
+}
+
+/// Recompute every board from the live tables
+fn rebuild_leaderboards(ctx: &ReducerContext) {
+    let usernames: std::collections::HashMap<Identity, String> = ctx.db.game_players().iter()
+        .map(|player| (player.identity, player.username.clone()))
+        .collect();
+
+    write_board(ctx, LEVEL_BOARD, ctx.db.game_players().iter()
+        .map(|player| (player.identity, player.username.clone(), player.level as u64, player.experience))
+        .collect());
+
+    let stats: Vec<PlayerCombatStats> = ctx.db.player_combat_stats().iter().collect();
+    let with_name = |stats: &PlayerCombatStats| usernames.get(&stats.player_identity).cloned();
+
+    write_board(ctx, NPC_KILLS_BOARD, stats.iter()
+        .filter(|stats| stats.npc_kills > 0)
+        .filter_map(|stats| Some((stats.player_identity, with_name(stats)?, stats.npc_kills, 0)))
+        .collect());
+
+    write_board(ctx, PVP_RATING_BOARD, stats.iter()
+        .filter(|stats| stats.pvp_wins + stats.pvp_losses > 0)
+        .filter_map(|stats| Some((stats.player_identity, with_name(stats)?, stats.pvp_rating as u64, stats.pvp_wins as u64)))
+        .collect());
+
+    let mut skill_boards: std::collections::HashMap<String, Vec<(Identity, String, u64, u64)>> = std::collections::HashMap::new();
+    for skill in ctx.db.player_skills().iter() {
+        if let Some(username) = usernames.get(&skill.player_identity) {
+            skill_boards.entry(format!("{}{}", SKILL_BOARD_PREFIX, skill.skill_name))
+                .or_default()
+                .push((skill.player_identity, username.clone(), skill.skill_level as u64, skill.experience));
+        }
+    }
+
+    for (board, rows) in skill_boards {
+        write_board(ctx, &board, rows);
+    }
+}
+
+/// Recompute the leaderboard snapshot
+/// Runs on the leaderboard_schedule interval
+#[reducer]
+pub fn refresh_leaderboards(ctx: &ReducerContext, _schedule: LeaderboardSchedule) -> Result<(), String> {
+    // Only the scheduler may run this
+    if ctx.sender != ctx.identity() {
+        return Err("refresh_leaderboards may only be invoked by the scheduler".to_string());
+    }
+
+    rebuild_leaderboards(ctx);
+    Ok(())
+}
+
+/// End the current season: archive the seasonal boards, reset their stats and start a new season (admin function)
+#[reducer]
+pub fn reset_leaderboard_season(ctx: &ReducerContext) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+
+    // Archive standings as they are right now, not as of the last scheduled refresh
+    rebuild_leaderboards(ctx);
+
+    let mut season = LeaderboardSeason::current(ctx);
+
+    for board in SEASONAL_BOARDS {
+        for entry in LeaderboardEntry::get_top(ctx, board, LEADERBOARD_ARCHIVE_SIZE as u32) {
+            ctx.db.leaderboard_archive().insert(LeaderboardArchive {
+                archive_id: 0, // auto_inc
+                season_id: season.season_id,
+                board: entry.board,
+                rank: entry.rank,
+                player_identity: entry.player_identity,
+                username: entry.username,
+                score: entry.score,
+            });
+        }
+    }
+
+    let stats: Vec<Identity> = ctx.db.player_combat_stats().iter().map(|stats| stats.player_identity).collect();
+    for identity in stats {
+        ctx.db.player_combat_stats().player_identity().delete(&identity);
+    }
+
+    season.ended_at = Some(ctx.timestamp);
+    let ended_season_id = season.season_id;
+    ctx.db.leaderboard_season().season_id().update(season);
+    LeaderboardSeason::current(ctx);
+
+    rebuild_leaderboards(ctx);
+
+    log::info!("Leaderboard season {} ended and archived", ended_season_id);
+    Ok(())
+}
@@ -11,11 +11,41 @@ use server_module::*;
 pub mod world;
 pub mod ai;
 pub mod mechanics;
+pub mod item_effects;
+pub mod item_instance;
+pub mod content;
+pub mod commands;
+pub mod mail;
+pub mod guild_bank;
+pub mod wallet;
+pub mod trade;
+pub mod vendor;
+pub mod auction;
+pub mod leaderboard;
+pub mod pvp;
+pub mod achievement;
+pub mod quest;
+pub mod dialogue;
 
 // Re-export custom functionality
 pub use world::*;
 pub use ai::*;
 pub use mechanics::*;
+pub use item_effects::*;
+pub use item_instance::*;
+pub use content::*;
+pub use commands::*;
+pub use mail::*;
+pub use guild_bank::*;
+pub use wallet::*;
+pub use trade::*;
+pub use vendor::*;
+pub use auction::*;
+pub use leaderboard::*;
+pub use pvp::*;
+pub use achievement::*;
+pub use quest::*;
+pub use dialogue::*;
 
 /// Initialize custom server features
 #[reducer]
@@ -25,19 +55,47 @@ pub fn initialize_custom_features(ctx: &ReducerContext) -> Result<(), String> {
     // Initialize world generation
     world::initialize_world_generator(ctx)?;
     
+    // Seed NPC templates before anything spawns NPCs
+    content::initialize_content(ctx)?;
+    
     // Initialize AI systems
     ai::initialize_ai_systems(ctx)?;
     
     // Initialize custom mechanics
     mechanics::initialize_game_mechanics(ctx)?;
     
+    // Start the mail expiry job
+    mail::initialize_mail_system(ctx)?;
+    
+    // Start the vendor restock job
+    vendor::initialize_vendor_system(ctx)?;
+    
+    // Start the auction expiry job
+    auction::initialize_auction_house(ctx)?;
+    
+    // Start the leaderboard refresh job
+    leaderboard::initialize_leaderboards(ctx)?;
+    
+    // Seed achievement definitions
+    achievement::initialize_achievements(ctx)?;
+    
+    // Seed quest definitions and start the daily quest reset job
+    quest::initialize_quests(ctx)?;
+    
+    // Seed NPC dialogue
+    dialogue::initialize_dialogue(ctx)?;
+    
     log::info!("Custom MMO features initialized successfully!");
     Ok(())
 }
 
 /// Custom table for world chunks
 #[derive(Clone, Debug)]
-#[table(name = world_chunks, public)]
+#[table(
+    name = world_chunks,
+    public,
+    index(name = chunk_coords, btree(columns = [x, y, z]))
+)]
 pub struct WorldChunk {
     #[primary_key]
     pub chunk_id: u64,
@@ -0,0 +1,365 @@
+//! In-game mail with item and currency attachments, expiry and return-to-sender
+
+use spacetimedb::{table, reducer, client_visibility_filter, Filter, ReducerContext, Identity, Timestamp, ScheduleAt, Table};
+use shared_module::*;
+use server_module::*;
+use server_module::utils::validation::validate_player_in_game;
+use crate::mechanics::*;
+use crate::item_instance::{escrow_item_instance, give_item_instance};
+use crate::wallet::PlayerWallet;
+
+/// A mail message waiting in a player's mailbox
+#[derive(Clone, Debug)]
+#[table(name = mail, public)]
+pub struct Mail {
+    #[primary_key]
+    #[auto_inc]
+    pub mail_id: u64,
+
+    /// Sender; the module identity for system mail
+    pub sender_identity: Identity,
+    pub sender_username: String,
+
+    #[index(btree)]
+    pub recipient_identity: Identity,
+    pub recipient_username: String,
+
+    pub subject: String,
+    pub body: String,
+
+    pub sent_at: Timestamp,
+
+    /// When unclaimed attachments go back to the sender (or, for mail that cannot be
+    /// returned, when the expiry is pushed back)
+    #[index(btree)]
+    pub expires_at: Timestamp,
+
+    pub is_read: bool,
+
+    /// Currency attached to the mail, held here until claimed
+    pub currency: u64,
+
+    /// Whether this mail is bounced mail coming back to its original sender
+    pub is_returned: bool,
+}
+
+/// An item stack attached to a mail, held here until claimed
+/// A unique item is attached as its instance, owned by the module while in the mail
+#[derive(Clone, Debug)]
+#[table(name = mail_attachment, public)]
+pub struct MailAttachment {
+    #[primary_key]
+    #[auto_inc]
+    pub attachment_id: u64,
+
+    #[index(btree)]
+    pub mail_id: u64,
+
+    pub item_id: String,
+    pub quantity: u32,
+    pub instance_id: Option<u64>,
+}
+
+/// Periodic job that returns or deletes expired mail
+#[table(name = mail_expiry_schedule, scheduled(process_expired_mail))]
+pub struct MailExpirySchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub scheduled_id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+/// Players only see their own mailbox
+#[client_visibility_filter]
+const MAIL_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM mail WHERE recipient_identity = :sender"
+);
+
+/// Attachments are visible to whoever can see the mail they belong to
+#[client_visibility_filter]
+const MAIL_ATTACHMENT_VISIBILITY: Filter = Filter::Sql(
+    "SELECT mail_attachment.* FROM mail_attachment \
+     JOIN mail ON mail_attachment.mail_id = mail.mail_id \
+     WHERE mail.recipient_identity = :sender"
+);
+
+impl Mail {
+    /// Find mail by ID
+    pub fn filter_by_mail_id(ctx: &ReducerContext, mail_id: u64) -> Option<Mail> {
+        ctx.db.mail().mail_id().find(&mail_id)
+    }
+
+    /// Count the mail in a player's mailbox
+    pub fn count_for_recipient(ctx: &ReducerContext, recipient_identity: &Identity) -> usize {
+        ctx.db.mail().recipient_identity().filter(recipient_identity).count()
+    }
+
+    /// Get the attachments still held by this mail
+    pub fn attachments(&self, ctx: &ReducerContext) -> Vec<MailAttachment> {
+        ctx.db.mail_attachment().mail_id().filter(&self.mail_id).collect()
+    }
+
+    /// Whether the mail still holds items or currency
+    pub fn has_unclaimed(&self, ctx: &ReducerContext) -> bool {
+        self.currency > 0 || !self.attachments(ctx).is_empty()
+    }
+
+    /// Delete a mail together with any attachments it still holds
+    pub fn delete_mail(ctx: &ReducerContext, mail_id: u64) {
+        ctx.db.mail_attachment().mail_id().delete(&mail_id);
+        ctx.db.mail().mail_id().delete(&mail_id);
+    }
+}
+
+/// Start the mail expiry job
+pub fn initialize_mail_system(ctx: &ReducerContext) -> Result<(), String> {
+    if ctx.db.mail_expiry_schedule().count() == 0 {
+        let interval = std::time::Duration::from_secs(MAIL_EXPIRY_CHECK_INTERVAL_SECONDS);
+        ctx.db.mail_expiry_schedule().insert(MailExpirySchedule {
+            scheduled_id: 0, // auto_inc
+            scheduled_at: ScheduleAt::Interval(interval.into()),
+        });
+    }
+
+    log::info!("Mail system initialized");
+    Ok(())
+}
+
+/// Put a mail in a mailbox; other systems (auction house, quests) use this to deliver items
+/// Attachments are (item_id, quantity, instance_id) and currency that have already been taken from wherever they were
+/// An attached instance must already be escrowed with escrow_item_instance
+pub fn deliver_mail(
+    ctx: &ReducerContext,
+    sender_identity: Identity,
+    sender_username: String,
+    recipient_identity: Identity,
+    recipient_username: String,
+    subject: String,
+    body: String,
+    attachments: Vec<(String, u32, Option<u64>)>,
+    currency: u64
+) -> Mail {
+    let mail = ctx.db.mail().insert(Mail {
+        mail_id: 0, // auto_inc
+        sender_identity,
+        sender_username,
+        recipient_identity,
+        recipient_username,
+        subject,
+        body,
+        sent_at: ctx.timestamp,
+        expires_at: ctx.timestamp + std::time::Duration::from_secs(MAIL_EXPIRY_SECONDS),
+        is_read: false,
+        currency,
+        is_returned: false,
+    });
+
+    for (item_id, quantity, instance_id) in attachments {
+        ctx.db.mail_attachment().insert(MailAttachment {
+            attachment_id: 0, // auto_inc
+            mail_id: mail.mail_id,
+            item_id,
+            quantity,
+            instance_id,
+        });
+    }
+
+    mail
+}
+
+/// Look up a mail in the caller's own mailbox
+fn find_own_mail(ctx: &ReducerContext, mail_id: u64) -> Result<Mail, String> {
+    Mail::filter_by_mail_id(ctx, mail_id)
+        .filter(|mail| mail.recipient_identity == ctx.sender)
+        .ok_or_else(|| "Mail not found".to_string())
+}
+
+/// Send mail to any player, online or not
+/// Each listed inventory stack is moved out of the sender's inventory in full
+#[reducer]
+pub fn send_mail(
+    ctx: &ReducerContext,
+    recipient_username: String,
+    subject: String,
+    body: String,
+    attachment_inventory_ids: Vec<u64>,
+    currency: u64
+) -> Result<(), String> {
+    let sender = validate_player_in_game(ctx)?;
+
+    let recipient = User::filter_by_username(ctx, &recipient_username)
+        .ok_or("No player with that name exists")?;
+
+    if recipient.identity == sender.identity {
+        return Err("You cannot mail yourself".to_string());
+    }
+
+    if Mail::count_for_recipient(ctx, &recipient.identity) >= MAX_MAILBOX_SIZE {
+        return Err("That player's mailbox is full".to_string());
+    }
+
+    if attachment_inventory_ids.len() > MAX_MAIL_ATTACHMENTS {
+        return Err(format!("A mail can carry at most {} attachments", MAX_MAIL_ATTACHMENTS));
+    }
+
+    // Mail goes through the same sanitizer and word filters as chat
+    let rules = ChatWordFilter::get_rules(ctx);
+    let subject = apply_word_filters(&sanitize_text(&subject, MAX_MAIL_SUBJECT_LENGTH)?, &rules)?.message;
+    let body = match body.trim().is_empty() {
+        true => String::new(),
+        false => apply_word_filters(&sanitize_text(&body, MAX_MAIL_BODY_LENGTH)?, &rules)?.message,
+    };
+
+    // Take every stack before anything is delivered; any failure rolls the whole send back
+    let mut attachments = Vec::with_capacity(attachment_inventory_ids.len());
+    for inventory_id in attachment_inventory_ids {
+        let stack = ctx.db.player_inventory().inventory_id().find(&inventory_id)
+            .filter(|stack| stack.player_identity == sender.identity)
+            .ok_or("Attachment not found in your inventory")?;
+
+        if let Some(instance_id) = stack.instance_id {
+            escrow_item_instance(ctx, instance_id)?;
+        }
+
+        ctx.db.player_inventory().inventory_id().delete(&inventory_id);
+        attachments.push((stack.item_id, stack.quantity, stack.instance_id));
+    }
+
+    PlayerWallet::debit(ctx, &sender.identity, currency)?;
+
+    if !RateLimitBucket::try_consume(ctx, &ctx.sender, "mail", MAIL_BURST_CAPACITY, MAIL_REFILL_PER_SECOND) {
+        return Err("You are sending mail too quickly".to_string());
+    }
+
+    let mail = deliver_mail(
+        ctx,
+        sender.identity,
+        sender.username.clone(),
+        recipient.identity,
+        recipient.username.clone(),
+        subject,
+        body,
+        attachments,
+        currency
+    );
+
+    PlayerNotification::send(ctx, recipient.identity, "mail", format!("New mail from {}", sender.username));
+
+    log::info!("Mail {} sent from {} to {}", mail.mail_id, sender.username, recipient.username);
+    Ok(())
+}
+
+/// Mark a mail as read
+#[reducer]
+pub fn read_mail(ctx: &ReducerContext, mail_id: u64) -> Result<(), String> {
+    let mut mail = find_own_mail(ctx, mail_id)?;
+
+    if !mail.is_read {
+        mail.is_read = true;
+        ctx.db.mail().mail_id().update(mail);
+    }
+
+    Ok(())
+}
+
+/// Move a mail's attachments into your inventory and its currency into your wallet
+/// Everything is claimed together; if the items do not fit, nothing is taken
+#[reducer]
+pub fn claim_mail_attachments(ctx: &ReducerContext, mail_id: u64) -> Result<(), String> {
+    let mut mail = find_own_mail(ctx, mail_id)?;
+
+    if !mail.has_unclaimed(ctx) {
+        return Err("This mail has no attachments".to_string());
+    }
+
+    for attachment in &mail.attachments(ctx) {
+        match attachment.instance_id {
+            Some(instance_id) => give_item_instance(ctx, instance_id, &ctx.sender)?,
+            None => add_item_to_inventory(ctx, &ctx.sender, &attachment.item_id, attachment.quantity)?,
+        }
+        ctx.db.mail_attachment().attachment_id().delete(&attachment.attachment_id);
+    }
+
+    PlayerWallet::credit(ctx, &ctx.sender, mail.currency)?;
+    mail.currency = 0;
+    mail.is_read = true;
+    ctx.db.mail().mail_id().update(mail);
+
+    Ok(())
+}
+
+/// Delete a mail; mail still holding attachments must be claimed first
+#[reducer]
+pub fn delete_mail(ctx: &ReducerContext, mail_id: u64) -> Result<(), String> {
+    let mail = find_own_mail(ctx, mail_id)?;
+
+    if mail.has_unclaimed(ctx) {
+        return Err("Claim the attachments before deleting this mail".to_string());
+    }
+
+    Mail::delete_mail(ctx, mail.mail_id);
+    Ok(())
+}
+
+/// Return expired mail with attachments to its sender and delete the rest
+/// System mail and bounced mail have no sender to go back to, so while they still
+/// hold items or currency they stay in the mailbox for another expiry period
+/// Runs on the mail_expiry_schedule interval
+#[reducer]
+pub fn process_expired_mail(ctx: &ReducerContext, _schedule: MailExpirySchedule) -> Result<(), String> {
+    // Only the scheduler may run this
+    if ctx.sender != ctx.identity() {
+        return Err("process_expired_mail may only be invoked by the scheduler".to_string());
+    }
+
+    let expired: Vec<Mail> = ctx.db.mail().expires_at().filter(..ctx.timestamp).collect();
+    let mut returned = 0;
+
+    for mut mail in expired {
+        if !mail.has_unclaimed(ctx) {
+            Mail::delete_mail(ctx, mail.mail_id);
+            continue;
+        }
+
+        if mail.is_returned || mail.sender_identity == ctx.identity() {
+            log::info!(
+                "Keeping unclaimed mail {} for {} ({} currency, {} attachment(s))",
+                mail.mail_id,
+                mail.recipient_username,
+                mail.currency,
+                mail.attachments(ctx).len()
+            );
+            mail.expires_at = ctx.timestamp + std::time::Duration::from_secs(MAIL_EXPIRY_SECONDS);
+            ctx.db.mail().mail_id().update(mail);
+            continue;
+        }
+
+        let attachments: Vec<(String, u32, Option<u64>)> = mail.attachments(ctx)
+            .into_iter()
+            .map(|attachment| (attachment.item_id, attachment.quantity, attachment.instance_id))
+            .collect();
+
+        Mail::delete_mail(ctx, mail.mail_id);
+
+        let mut bounced = deliver_mail(
+            ctx,
+            mail.recipient_identity,
+            mail.recipient_username,
+            mail.sender_identity,
+            mail.sender_username,
+            format!("Returned: {}", mail.subject),
+            mail.body,
+            attachments,
+            mail.currency
+        );
+        bounced.is_returned = true;
+        ctx.db.mail().mail_id().update(bounced);
+        returned += 1;
+    }
+
+    if returned > 0 {
+        log::info!("Returned {} expired mail(s) to their senders", returned);
+    }
+
+    Ok(())
+}
@@ -1,20 +1,36 @@
 //! Game mechanics like inventory, skills, and progression
 
-use spacetimedb::{table, reducer, ReducerContext, Identity, Timestamp};
+use spacetimedb::{table, reducer, ReducerContext, Identity, Timestamp, Table};
 use shared_module::*;
 use server_module::*;
+use server_module::utils::validation::{validate_admin_permissions, validate_player_in_game};
+use crate::achievement::{record_achievement_event, AchievementEvent};
+use crate::quest::{record_quest_event, QuestEvent};
+use crate::item_effects::{apply_item_effect, validate_item_effects};
+use crate::item_instance::{create_item_instance, equipment_slot, ItemInstance, item_instances};
 
 /// Player inventory system
+/// Each row is one stack in one slot; a slot never holds more than the item's max_stack_size
+/// Weapons and armor are unique: each copy is an ItemInstance alone in its slot
 #[derive(Clone, Debug)]
 #[table(name = player_inventory, public)]
 pub struct PlayerInventory {
     #[primary_key]
+    #[auto_inc]
     pub inventory_id: u64,
+    #[index(btree)]
     pub player_identity: Identity,
     pub item_type: String,
     pub item_id: String,
     pub quantity: u32,
     pub slot_index: u32,
+    pub instance_id: Option<u64>, // Set when the slot holds a unique item instance
+}
+
+impl PlayerInventory {
+    pub fn is_unique(&self) -> bool {
+        self.instance_id.is_some()
+    }
 }
 
 /// Player skills and progression
@@ -41,7 +57,41 @@ pub struct GameItem {
     pub description: String,
     pub max_stack_size: u32,
     pub value: u32,
-    pub properties_json: String, // JSON string for flexible item properties
+    pub properties_json: String, // JSON matching the ItemProperties schema for item_type
+}
+
+impl GameItem {
+    /// The item's properties, parsed with the schema for its type
+    pub fn properties(&self) -> Result<ItemProperties, String> {
+        parse_item_properties(&self.item_type, &self.properties_json)
+    }
+    
+    /// Check an item definition before it goes into game_items
+    pub fn validate(&self) -> Result<(), String> {
+        if self.item_id.is_empty() || self.item_name.is_empty() {
+            return Err("Items need an id and a name".to_string());
+        }
+        if self.max_stack_size == 0 {
+            return Err(format!("Item '{}' needs a stack size of at least 1", self.item_id));
+        }
+        
+        let properties = self.properties()
+            .map_err(|e| format!("Item '{}': {}", self.item_id, e))?;
+        validate_item_effects(&properties)
+            .map_err(|e| format!("Item '{}': {}", self.item_id, e))
+    }
+    
+    /// Validate an item definition and insert it, replacing any existing definition
+    pub fn upsert(ctx: &ReducerContext, item: GameItem) -> Result<(), String> {
+        item.validate()?;
+        
+        if ctx.db.game_items().item_id().find(&item.item_id).is_some() {
+            ctx.db.game_items().item_id().update(item);
+        } else {
+            ctx.db.game_items().insert(item);
+        }
+        Ok(())
+    }
 }
 
 /// Initialize game mechanics
@@ -49,26 +99,26 @@ pub fn initialize_game_mechanics(ctx: &ReducerContext) -> Result<(), String> {
     log::info!("Initializing game mechanics...");
     
     // Create default items if they don't exist
-    create_default_items(ctx);
+    create_default_items(ctx)?;
     
     log::info!("Game mechanics initialized");
     Ok(())
 }
 
 /// Create default game items
-fn create_default_items(ctx: &ReducerContext) {
+fn create_default_items(ctx: &ReducerContext) -> Result<(), String> {
     let default_items = vec![
         ("sword_iron", "Iron Sword", "weapon", "A sturdy iron sword", 1, 100, r#"{"damage": 25, "durability": 100}"#),
-        ("potion_health", "Health Potion", "consumable", "Restores 50 health", 10, 25, r#"{"heal_amount": 50}"#),
+        ("potion_health", "Health Potion", "consumable", "Restores 50 health", 10, 25, r#"{"effects": [{"effect": "heal", "amount": 50}]}"#),
         ("armor_leather", "Leather Armor", "armor", "Basic leather protection", 1, 50, r#"{"defense": 10, "durability": 50}"#),
         ("ore_iron", "Iron Ore", "material", "Raw iron ore for crafting", 50, 10, r#"{"crafting_material": true}"#),
-        ("food_bread", "Bread", "consumable", "Restores 20 health and reduces hunger", 20, 5, r#"{"heal_amount": 20, "hunger_reduction": 30}"#),
+        ("food_bread", "Bread", "consumable", "Restores 20 health", 20, 5, r#"{"effects": [{"effect": "heal", "amount": 20}]}"#),
     ];
     
     for (id, name, item_type, desc, stack_size, value, properties) in default_items {
         // Check if item already exists
         if ctx.db.game_items().item_id().find(&id.to_string()).is_none() {
-            ctx.db.game_items().insert(GameItem {
+            GameItem::upsert(ctx, GameItem {
                 item_id: id.to_string(),
                 item_name: name.to_string(),
                 item_type: item_type.to_string(),
@@ -76,12 +126,15 @@ fn create_default_items(ctx: &ReducerContext) {
                 max_stack_size: stack_size,
                 value,
                 properties_json: properties.to_string(),
-            });
+            })?;
         }
     }
+    
+    Ok(())
 }
 
-/// Give item to player
+/// Give item to player (admin function)
+/// Players move items between each other through trades
 #[reducer]
 pub fn give_item_to_player(
     ctx: &ReducerContext,
@@ -89,6 +142,8 @@ pub fn give_item_to_player(
     item_id: String,
     quantity: u32
 ) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+    
     // Verify the item exists
     let _item = ctx.db.game_items().item_id().find(&item_id)
         .ok_or("Item not found")?;
@@ -98,53 +153,112 @@ pub fn give_item_to_player(
         .find(|p| p.username == target_username)
         .ok_or("Player not found")?;
     
-    // Add item to player's inventory
-    add_item_to_inventory(ctx, &target_player.identity, &item_id, quantity)?;
+    // Add item to player's inventory; unique items record the admin as their creator
+    add_item_to_inventory_from(ctx, &target_player.identity, &item_id, quantity, Some(ctx.sender))?;
     
     log::info!("Gave {} x{} to player {}", item_id, quantity, target_username);
     Ok(())
 }
 
-/// Add item to player inventory
-#[reducer]
+/// Add items to a player's inventory (internal; clients cannot call this)
+/// Tops up every partial stack first, then opens new full-size stacks in free slots.
+/// Weapons and armor get a freshly rolled instance per copy instead.
+/// Nothing changes unless everything fits.
 pub fn add_item_to_inventory(
     ctx: &ReducerContext,
     player_identity: &Identity,
     item_id: &str,
     quantity: u32
+) -> Result<(), String> {
+    add_item_to_inventory_from(ctx, player_identity, item_id, quantity, None)
+}
+
+/// Add items to a player's inventory, recording who created any unique instances (internal)
+pub fn add_item_to_inventory_from(
+    ctx: &ReducerContext,
+    player_identity: &Identity,
+    item_id: &str,
+    quantity: u32,
+    creator: Option<Identity>
 ) -> Result<(), String> {
     let item = ctx.db.game_items().item_id().find(&item_id.to_string())
         .ok_or("Item not found")?;
     
-    // Find existing stack of this item
-    if let Some(existing_item) = find_inventory_item(ctx, player_identity, item_id) {
-        let mut updated_item = existing_item.clone();
-        let new_quantity = updated_item.quantity + quantity;
+    if quantity == 0 {
+        return Ok(());
+    }
+    
+    if equipment_slot(&item.properties()?).is_some() {
+        let free_slots = free_inventory_slots(ctx, player_identity);
+        if quantity as usize > free_slots.len() {
+            return Err("Inventory is full".to_string());
+        }
         
-        if new_quantity <= item.max_stack_size {
-            // Add to existing stack
-            updated_item.quantity = new_quantity;
-            ctx.db.player_inventory().inventory_id().update(updated_item);
-        } else {
-            // Split into multiple stacks or create new stack
-            let remaining = new_quantity - item.max_stack_size;
-            updated_item.quantity = item.max_stack_size;
-            ctx.db.player_inventory().inventory_id().update(updated_item);
-            
-            // Create new stack for remaining items
-            if remaining > 0 {
-                create_new_inventory_slot(ctx, player_identity, item_id, remaining)?;
-            }
+        for slot_index in free_slots.into_iter().take(quantity as usize) {
+            let instance = create_item_instance(ctx, player_identity, &item, creator)?;
+            ctx.db.player_inventory().insert(PlayerInventory {
+                inventory_id: 0, // auto_inc
+                player_identity: *player_identity,
+                item_type: item.item_type.clone(),
+                item_id: item.item_id.clone(),
+                quantity: 1,
+                slot_index,
+                instance_id: Some(instance.instance_id),
+            });
         }
-    } else {
-        // Create new inventory slot
-        create_new_inventory_slot(ctx, player_identity, item_id, quantity)?;
+        
+        record_quest_event(ctx, player_identity, QuestEvent::ItemCollected { item_id: item_id.to_string() });
+        return Ok(());
+    }
+    
+    let max_stack = item.max_stack_size.max(1);
+    let mut partial_stacks: Vec<PlayerInventory> = get_inventory(ctx, player_identity).into_iter()
+        .filter(|stack| stack.item_id == item_id && !stack.is_unique() && stack.quantity < max_stack)
+        .collect();
+    partial_stacks.sort_by_key(|stack| stack.slot_index);
+    
+    // Work out where everything goes before touching any row
+    let partial_space = partial_stacks.iter()
+        .fold(0u64, |space, stack| space + (max_stack - stack.quantity) as u64);
+    let overflow = (quantity as u64).saturating_sub(partial_space);
+    let new_stacks_needed = overflow.div_ceil(max_stack as u64) as usize;
+    
+    let free_slots = free_inventory_slots(ctx, player_identity);
+    if new_stacks_needed > free_slots.len() {
+        return Err("Inventory is full".to_string());
+    }
+    
+    let mut remaining = quantity;
+    for mut stack in partial_stacks {
+        if remaining == 0 {
+            break;
+        }
+        
+        let added = remaining.min(max_stack - stack.quantity);
+        stack.quantity += added;
+        remaining -= added;
+        ctx.db.player_inventory().inventory_id().update(stack);
+    }
+    
+    for slot_index in free_slots.into_iter().take(new_stacks_needed) {
+        let added = remaining.min(max_stack);
+        remaining -= added;
+        ctx.db.player_inventory().insert(PlayerInventory {
+            inventory_id: 0, // auto_inc
+            player_identity: *player_identity,
+            item_type: item.item_type.clone(),
+            item_id: item.item_id.clone(),
+            quantity: added,
+            slot_index,
+            instance_id: None,
+        });
     }
     
+    record_quest_event(ctx, player_identity, QuestEvent::ItemCollected { item_id: item_id.to_string() });
     Ok(())
 }
 
-/// Remove item from player inventory
+/// Destroy items from your inventory
 #[reducer]
 pub fn remove_item_from_inventory(
     ctx: &ReducerContext,
@@ -152,75 +266,51 @@ pub fn remove_item_from_inventory(
     quantity: u32
 ) -> Result<(), String> {
     let player = ctx.db.game_players().identity().find(&ctx.sender)
-        .ok_or("Player not found")?
-        .clone();
-    
-    let inventory_item = find_inventory_item(ctx, &player.identity, &item_id)
-        .ok_or("Item not found in inventory")?;
+        .ok_or("Player not found")?;
     
-    if inventory_item.quantity < quantity {
-        return Err("Not enough items in inventory".to_string());
+    if quantity == 0 {
+        return Err("Quantity must be at least 1".to_string());
     }
     
-    if inventory_item.quantity == quantity {
-        // Remove entire stack
-        ctx.db.player_inventory().inventory_id().delete(&inventory_item.inventory_id);
-    } else {
-        // Reduce quantity
-        let mut updated_item = inventory_item.clone();
-        updated_item.quantity -= quantity;
-        ctx.db.player_inventory().inventory_id().update(updated_item);
+    if count_inventory_item(ctx, &player.identity, &item_id) == 0 {
+        return Err("Item not found in inventory".to_string());
     }
     
-    Ok(())
+    take_inventory_items(ctx, &player.identity, &item_id, quantity)
 }
 
 /// Use consumable item
+/// Each effect in the item's properties runs through the item effect registry
 #[reducer]
 pub fn use_item(
     ctx: &ReducerContext,
     item_id: String
 ) -> Result<(), String> {
-    let mut player = ctx.db.game_players().identity().find(&ctx.sender)
-        .ok_or("Player not found")?
-        .clone();
+    let player = ctx.db.game_players().identity().find(&ctx.sender)
+        .ok_or("Player not found")?;
     
     let item = ctx.db.game_items().item_id().find(&item_id)
         .ok_or("Item not found")?;
     
     // Check if player has the item
-    let _inventory_item = find_inventory_item(ctx, &player.identity, &item_id)
-        .ok_or("Item not found in inventory")?;
-    
-    // Apply item effects based on type
-    match item.item_type.as_str() {
-        "consumable" => {
-            // Simple parsing for heal_amount property
-            if item.properties_json.contains("heal_amount") {
-                // Extract heal amount from the JSON string (simple parsing)
-                if let Some(start) = item.properties_json.find("\"heal_amount\":") {
-                    let rest = &item.properties_json[start + 14..];
-                    if let Some(end) = rest.find(&[',', '}'][..]) {
-                        let heal_str = &rest[..end].trim();
-                        if let Ok(heal_amount) = heal_str.parse::<f32>() {
-                            player.health = (player.health + heal_amount).min(player.max_health);
-                            log::info!("Player {} used {} and healed for {} HP", player.username, item.item_name, heal_amount);
-                        }
-                    }
-                }
-            }
-            
-            // Remove one item from inventory
-            remove_item_from_inventory(ctx, item_id, 1)?;
-            
-            // Update player health
-            ctx.db.game_players().identity().update(player);
-        },
-        _ => {
-            return Err("This item cannot be used".to_string());
-        }
+    if count_inventory_item(ctx, &player.identity, &item_id) == 0 {
+        return Err("Item not found in inventory".to_string());
+    }
+    
+    let ItemProperties::Consumable(consumable) = item.properties()? else {
+        return Err("This item cannot be used".to_string());
+    };
+    
+    for effect in &consumable.effects {
+        apply_item_effect(ctx, &player.identity, effect)?;
     }
     
+    // Remove one item from inventory
+    take_inventory_items(ctx, &player.identity, &item_id, 1)?;
+    
+    log::info!("Player {} used {}", player.username, item.item_name);
+    record_achievement_event(ctx, &player.identity, AchievementEvent::ItemUsed { item_id: item.item_id.clone() });
+    
     Ok(())
 }
 
@@ -230,26 +320,168 @@ pub fn get_player_inventory(ctx: &ReducerContext) -> Result<Vec<PlayerInventory>
     let player = ctx.db.game_players().identity().find(&ctx.sender)
         .ok_or("Player not found")?;
     
-    let inventory: Vec<PlayerInventory> = ctx.db.player_inventory().iter()
-        .filter(|item| item.player_identity == player.identity)
-        .cloned()
-        .collect();
+    Ok(get_inventory(ctx, &player.identity))
+}
+
+/// Move a stack into an empty slot
+#[reducer]
+pub fn move_inventory_item(ctx: &ReducerContext, inventory_id: u64, to_slot: u32) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    let mut stack = find_own_stack(ctx, &player.identity, inventory_id)?;
+    validate_inventory_slot(to_slot)?;
+    
+    if stack.slot_index == to_slot {
+        return Ok(());
+    }
+    
+    if find_stack_in_slot(ctx, &player.identity, to_slot).is_some() {
+        return Err("That slot is not empty".to_string());
+    }
     
-    Ok(inventory)
+    stack.slot_index = to_slot;
+    ctx.db.player_inventory().inventory_id().update(stack);
+    Ok(())
 }
 
-/// Level up player skill
+/// Swap the contents of two slots; either may be empty
 #[reducer]
-pub fn gain_skill_experience(
+pub fn swap_inventory_slots(ctx: &ReducerContext, slot_a: u32, slot_b: u32) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    validate_inventory_slot(slot_a)?;
+    validate_inventory_slot(slot_b)?;
+    
+    if slot_a == slot_b {
+        return Ok(());
+    }
+    
+    let stack_a = find_stack_in_slot(ctx, &player.identity, slot_a);
+    let stack_b = find_stack_in_slot(ctx, &player.identity, slot_b);
+    
+    if stack_a.is_none() && stack_b.is_none() {
+        return Err("Both slots are empty".to_string());
+    }
+    
+    if let Some(mut stack) = stack_a {
+        stack.slot_index = slot_b;
+        ctx.db.player_inventory().inventory_id().update(stack);
+    }
+    if let Some(mut stack) = stack_b {
+        stack.slot_index = slot_a;
+        ctx.db.player_inventory().inventory_id().update(stack);
+    }
+    
+    Ok(())
+}
+
+/// Split part of a stack off into an empty slot
+#[reducer]
+pub fn split_inventory_stack(ctx: &ReducerContext, inventory_id: u64, quantity: u32, to_slot: u32) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    let mut stack = find_own_stack(ctx, &player.identity, inventory_id)?;
+    validate_inventory_slot(to_slot)?;
+    
+    if quantity == 0 || quantity >= stack.quantity {
+        return Err("You can only split off part of a stack".to_string());
+    }
+    
+    if find_stack_in_slot(ctx, &player.identity, to_slot).is_some() {
+        return Err("That slot is not empty".to_string());
+    }
+    
+    stack.quantity -= quantity;
+    ctx.db.player_inventory().insert(PlayerInventory {
+        inventory_id: 0, // auto_inc
+        player_identity: player.identity,
+        item_type: stack.item_type.clone(),
+        item_id: stack.item_id.clone(),
+        quantity,
+        slot_index: to_slot,
+        instance_id: None,
+    });
+    ctx.db.player_inventory().inventory_id().update(stack);
+    
+    Ok(())
+}
+
+/// Move as much of one stack onto another stack of the same item as fits
+#[reducer]
+pub fn merge_inventory_stacks(ctx: &ReducerContext, from_inventory_id: u64, into_inventory_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    
+    if from_inventory_id == into_inventory_id {
+        return Err("Cannot merge a stack with itself".to_string());
+    }
+    
+    let mut from = find_own_stack(ctx, &player.identity, from_inventory_id)?;
+    let mut into = find_own_stack(ctx, &player.identity, into_inventory_id)?;
+    
+    if from.item_id != into.item_id {
+        return Err("Only stacks of the same item can be merged".to_string());
+    }
+    
+    if from.is_unique() || into.is_unique() {
+        return Err("Unique items do not stack".to_string());
+    }
+    
+    let item = ctx.db.game_items().item_id().find(&into.item_id)
+        .ok_or("Item not found")?;
+    
+    let moved = from.quantity.min(item.max_stack_size.saturating_sub(into.quantity));
+    if moved == 0 {
+        return Err("That stack is already full".to_string());
+    }
+    
+    into.quantity += moved;
+    from.quantity -= moved;
+    ctx.db.player_inventory().inventory_id().update(into);
+    
+    if from.quantity == 0 {
+        ctx.db.player_inventory().inventory_id().delete(&from.inventory_id);
+    } else {
+        ctx.db.player_inventory().inventory_id().update(from);
+    }
+    
+    Ok(())
+}
+
+/// Grant skill experience to a player (admin function)
+/// Skills only advance through the server; clients cannot report their own progress
+#[reducer]
+pub fn grant_skill_experience(
     ctx: &ReducerContext,
+    target_username: String,
     skill_name: String,
     experience_gained: u64
 ) -> Result<(), String> {
-    let player = ctx.db.game_players().identity().find(&ctx.sender)
+    validate_admin_permissions(ctx)?;
+    
+    if skill_name.trim().is_empty() {
+        return Err("Skill name cannot be empty".to_string());
+    }
+    
+    let target_player = ctx.db.game_players().iter()
+        .find(|p| p.username == target_username)
+        .ok_or("Player not found")?;
+    
+    gain_skill_experience(ctx, &target_player.identity, skill_name.trim(), experience_gained)?;
+    
+    log::info!("Granted {} {} experience to player {}", experience_gained, skill_name, target_username);
+    Ok(())
+}
+
+/// Level up player skill (internal; clients cannot call this)
+pub fn gain_skill_experience(
+    ctx: &ReducerContext,
+    player_identity: &Identity,
+    skill_name: &str,
+    experience_gained: u64
+) -> Result<(), String> {
+    let player = ctx.db.game_players().identity().find(player_identity)
         .ok_or("Player not found")?;
     
     // Find or create skill
-    if let Some(mut skill) = find_player_skill(ctx, &player.identity, &skill_name) {
+    if let Some(mut skill) = find_player_skill(ctx, &player.identity, skill_name) {
+        let starting_level = skill.skill_level;
         skill.experience += experience_gained;
         
         // Check for level up
@@ -260,15 +492,21 @@ pub fn gain_skill_experience(
             log::info!("Player {} leveled up {} to level {}", player.username, skill_name, skill.skill_level);
         }
         
+        let levelled_up = skill.skill_level > starting_level;
+        let skill_level = skill.skill_level;
         skill.last_updated = ctx.timestamp;
         ctx.db.player_skills().skill_id().update(skill);
+        
+        if levelled_up {
+            record_achievement_event(ctx, &player.identity, AchievementEvent::SkillLevelReached { skill_name: skill_name.to_string(), level: skill_level });
+        }
     } else {
         // Create new skill
-        let skill_id = generate_unique_id(&ctx.sender, ctx.timestamp);
+        let skill_id = generate_unique_id(&player.identity, ctx.timestamp);
         ctx.db.player_skills().insert(PlayerSkill {
             skill_id,
             player_identity: player.identity,
-            skill_name: skill_name.clone(),
+            skill_name: skill_name.to_string(),
             skill_level: 1,
             experience: experience_gained,
             last_updated: ctx.timestamp,
@@ -279,51 +517,96 @@ pub fn gain_skill_experience(
     Ok(())
 }
 
-/// Helper functions
-fn find_inventory_item(
-    ctx: &ReducerContext,
-    player_identity: &Identity,
-    item_id: &str
-) -> Option<PlayerInventory> {
-    ctx.db.player_inventory().iter()
-        .find(|item| item.player_identity == *player_identity && item.item_id == item_id)
-        .cloned()
+// Inventory helpers
+
+/// All of a player's stacks
+pub fn get_inventory(ctx: &ReducerContext, player_identity: &Identity) -> Vec<PlayerInventory> {
+    ctx.db.player_inventory().player_identity().filter(player_identity).collect()
+}
+
+/// Total quantity of an item a player holds, unequipped instances included
+pub fn count_inventory_item(ctx: &ReducerContext, player_identity: &Identity, item_id: &str) -> u32 {
+    takeable_stacks(ctx, player_identity, item_id).iter()
+        .fold(0u32, |total, stack| total.saturating_add(stack.quantity))
 }
 
-fn create_new_inventory_slot(
+/// Stacks of an item that count towards it; unique instances count unless equipped
+fn takeable_stacks(ctx: &ReducerContext, player_identity: &Identity, item_id: &str) -> Vec<PlayerInventory> {
+    get_inventory(ctx, player_identity).into_iter()
+        .filter(|stack| stack.item_id == item_id)
+        .filter(|stack| match stack.instance_id {
+            Some(instance_id) => ctx.db.item_instances().instance_id().find(&instance_id)
+                .is_some_and(|instance| instance.equipped_slot.is_none()),
+            None => true,
+        })
+        .collect()
+}
+
+/// Remove a quantity of an item from a player, emptying the smallest plain stacks first
+pub fn take_inventory_items(
     ctx: &ReducerContext,
     player_identity: &Identity,
     item_id: &str,
     quantity: u32
 ) -> Result<(), String> {
-    // Find next available slot
-    let used_slots: std::collections::HashSet<u32> = ctx.db.player_inventory().iter()
-        .filter(|item| item.player_identity == *player_identity)
-        .map(|item| item.slot_index)
-        .collect();
+    if count_inventory_item(ctx, player_identity, item_id) < quantity {
+        return Err("Not enough items in inventory".to_string());
+    }
     
-    let mut next_slot = 0;
-    while used_slots.contains(&next_slot) {
-        next_slot += 1;
-        if next_slot > 100 { // Max 100 inventory slots
-            return Err("Inventory is full".to_string());
+    // Plain stacks go before unique instances
+    let mut stacks = takeable_stacks(ctx, player_identity, item_id);
+    stacks.sort_by_key(|stack| (stack.is_unique(), stack.quantity));
+    
+    let mut remaining = quantity;
+    for mut stack in stacks {
+        if remaining == 0 {
+            break;
+        }
+        
+        if stack.quantity <= remaining {
+            remaining -= stack.quantity;
+            ctx.db.player_inventory().inventory_id().delete(&stack.inventory_id);
+            if let Some(instance_id) = stack.instance_id {
+                ItemInstance::destroy(ctx, instance_id);
+            }
+        } else {
+            stack.quantity -= remaining;
+            remaining = 0;
+            ctx.db.player_inventory().inventory_id().update(stack);
         }
     }
     
-    let inventory_id = generate_unique_id(player_identity, ctx.timestamp);
-    
-    ctx.db.player_inventory().insert(PlayerInventory {
-        inventory_id,
-        player_identity: *player_identity,
-        item_type: "item".to_string(), // TODO: Get from item definition
-        item_id: item_id.to_string(),
-        quantity,
-        slot_index: next_slot,
-    });
+    Ok(())
+}
+
+/// Empty slots in a player's inventory, lowest first
+pub fn free_inventory_slots(ctx: &ReducerContext, player_identity: &Identity) -> Vec<u32> {
+    let used_slots: std::collections::HashSet<u32> = get_inventory(ctx, player_identity)
+        .iter()
+        .map(|stack| stack.slot_index)
+        .collect();
     
+    (0..INVENTORY_SLOTS as u32).filter(|slot| !used_slots.contains(slot)).collect()
+}
+
+fn validate_inventory_slot(slot_index: u32) -> Result<(), String> {
+    if slot_index >= INVENTORY_SLOTS as u32 {
+        return Err("Invalid inventory slot".to_string());
+    }
     Ok(())
 }
 
+fn find_own_stack(ctx: &ReducerContext, player_identity: &Identity, inventory_id: u64) -> Result<PlayerInventory, String> {
+    ctx.db.player_inventory().inventory_id().find(&inventory_id)
+        .filter(|stack| stack.player_identity == *player_identity)
+        .ok_or_else(|| "Item not found in your inventory".to_string())
+}
+
+fn find_stack_in_slot(ctx: &ReducerContext, player_identity: &Identity, slot_index: u32) -> Option<PlayerInventory> {
+    get_inventory(ctx, player_identity).into_iter()
+        .find(|stack| stack.slot_index == slot_index)
+}
+
 fn find_player_skill(
     ctx: &ReducerContext,
     player_identity: &Identity,
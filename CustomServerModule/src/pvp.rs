@@ -0,0 +1,258 @@
+//! Duels: agreed one-on-one fights between players, fought with equipped gear
+//!
+//! The server works out every hit, so a duel's result can feed the PvP rating board
+
+use spacetimedb::{table, reducer, client_visibility_filter, Filter, ReducerContext, Identity, Timestamp, Table};
+use shared_module::*;
+use server_module::*;
+use server_module::utils::validation::{validate_player_in_game, validate_rate_limit, validate_target_player};
+use crate::item_instance::{wear_equipped_item, ItemInstance};
+use crate::leaderboard::PlayerCombatStats;
+
+/// A duel between two players; unaccepted challenges expire
+#[derive(Clone, Debug)]
+#[table(name = duel, public)]
+pub struct Duel {
+    #[primary_key]
+    #[auto_inc]
+    pub duel_id: u64,
+
+    #[index(btree)]
+    pub challenger_identity: Identity,
+    pub challenger_username: String,
+
+    #[index(btree)]
+    pub opponent_identity: Identity,
+    pub opponent_username: String,
+
+    /// False while the challenge waits for the opponent
+    pub accepted: bool,
+
+    pub created_at: Timestamp,
+}
+
+/// Players see duels they take part in
+#[client_visibility_filter]
+const DUEL_CHALLENGER_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM duel WHERE challenger_identity = :sender"
+);
+
+#[client_visibility_filter]
+const DUEL_OPPONENT_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM duel WHERE opponent_identity = :sender"
+);
+
+impl Duel {
+    /// The duel a player is currently part of, on either side
+    pub fn find_for_player(ctx: &ReducerContext, identity: &Identity) -> Option<Duel> {
+        ctx.db.duel().challenger_identity().filter(identity).next()
+            .or_else(|| ctx.db.duel().opponent_identity().filter(identity).next())
+    }
+
+    /// Whether an unanswered challenge has timed out
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        !self.accepted
+            && now.duration_since(self.created_at).unwrap_or_default().as_secs() > DUEL_CHALLENGE_EXPIRY_SECONDS
+    }
+
+    /// The other participant
+    pub fn other_side(&self, identity: &Identity) -> Identity {
+        if self.challenger_identity == *identity { self.opponent_identity } else { self.challenger_identity }
+    }
+}
+
+/// Look up a duel the caller takes part in
+fn find_own_duel(ctx: &ReducerContext, duel_id: u64) -> Result<Duel, String> {
+    ctx.db.duel().duel_id().find(&duel_id)
+        .filter(|duel| duel.challenger_identity == ctx.sender || duel.opponent_identity == ctx.sender)
+        .ok_or_else(|| "Duel not found".to_string())
+}
+
+/// Two players must be in the same zone and within reach of each other to fight
+fn check_duel_range(a: &Player, b: &Player) -> Result<(), String> {
+    let distance = calculate_distance(
+        a.position_x, a.position_y, a.position_z,
+        b.position_x, b.position_y, b.position_z
+    );
+
+    if a.current_zone != b.current_zone || distance > MAX_DUEL_DISTANCE {
+        return Err("You are too far away from your opponent".to_string());
+    }
+
+    Ok(())
+}
+
+/// Damage of a player's equipped weapon, or unarmed damage without a working one
+fn weapon_damage(ctx: &ReducerContext, identity: &Identity) -> u32 {
+    ItemInstance::find_equipped(ctx, identity, "weapon")
+        .filter(|instance| !instance.is_broken())
+        .and_then(|instance| ctx.db.game_items().item_id().find(&instance.item_id))
+        .and_then(|item| match item.properties() {
+            Ok(ItemProperties::Weapon(weapon)) => Some(weapon.damage),
+            _ => None,
+        })
+        .unwrap_or(DUEL_UNARMED_DAMAGE)
+}
+
+/// Defense of a player's equipped armor, or none without a working piece
+fn armor_defense(ctx: &ReducerContext, identity: &Identity) -> u32 {
+    ItemInstance::find_equipped(ctx, identity, "armor")
+        .filter(|instance| !instance.is_broken())
+        .and_then(|instance| ctx.db.game_items().item_id().find(&instance.item_id))
+        .and_then(|item| match item.properties() {
+            Ok(ItemProperties::Armor(armor)) => Some(armor.defense),
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+/// Put both duelists back on full health
+fn restore_health(ctx: &ReducerContext, duel: &Duel) {
+    for identity in [duel.challenger_identity, duel.opponent_identity] {
+        if let Some(mut player) = Player::filter_by_identity(ctx, &identity) {
+            player.health = player.max_health;
+            ctx.db.game_players().identity().update(player);
+        }
+    }
+}
+
+/// End an accepted duel, rate both sides and heal them
+fn finish_duel(ctx: &ReducerContext, duel: &Duel, winner: &Identity) {
+    let loser = duel.other_side(winner);
+
+    ctx.db.duel().duel_id().delete(&duel.duel_id);
+    restore_health(ctx, duel);
+    PlayerCombatStats::record_pvp_result(ctx, winner, &loser);
+
+    PlayerNotification::send(ctx, *winner, "duel", "You won the duel".to_string());
+    PlayerNotification::send(ctx, loser, "duel", "You lost the duel".to_string());
+    log::info!("Duel {} won by {:?}", duel.duel_id, winner);
+}
+
+/// Challenge a nearby player to a duel
+#[reducer]
+pub fn challenge_to_duel(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    let target = validate_target_player(ctx, &target_username)?;
+
+    if target.identity == player.identity {
+        return Err("You cannot duel yourself".to_string());
+    }
+
+    // Clear out unanswered challenges that have run out
+    for identity in [player.identity, target.identity] {
+        if let Some(duel) = Duel::find_for_player(ctx, &identity) {
+            if duel.is_expired(ctx.timestamp) {
+                ctx.db.duel().duel_id().delete(&duel.duel_id);
+            }
+        }
+    }
+
+    if Duel::find_for_player(ctx, &player.identity).is_some() {
+        return Err("You are already in a duel".to_string());
+    }
+
+    if Duel::find_for_player(ctx, &target.identity).is_some() {
+        return Err("That player is already in a duel".to_string());
+    }
+
+    check_duel_range(&player, &target)?;
+
+    validate_rate_limit(ctx, "duel_challenge")?;
+
+    // Challenges to someone ignoring the sender are dropped silently
+    if IgnoredPlayer::is_ignoring(ctx, &target.identity, &player.identity) {
+        return Ok(());
+    }
+
+    ctx.db.duel().insert(Duel {
+        duel_id: 0, // auto_inc
+        challenger_identity: player.identity,
+        challenger_username: player.username.clone(),
+        opponent_identity: target.identity,
+        opponent_username: target.username.clone(),
+        accepted: false,
+        created_at: ctx.timestamp,
+    });
+
+    PlayerNotification::send(ctx, target.identity, "duel", format!("{} challenges you to a duel", player.username));
+    Ok(())
+}
+
+/// Accept a duel challenge; both duelists start on full health
+#[reducer]
+pub fn accept_duel(ctx: &ReducerContext, duel_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+
+    let mut duel = ctx.db.duel().duel_id().find(&duel_id)
+        .filter(|duel| duel.opponent_identity == player.identity && !duel.accepted)
+        .ok_or("Duel challenge not found")?;
+
+    if duel.is_expired(ctx.timestamp) {
+        ctx.db.duel().duel_id().delete(&duel_id);
+        PlayerNotification::send(ctx, player.identity, "duel", "That duel challenge has expired".to_string());
+        return Ok(());
+    }
+
+    let challenger = Player::filter_by_identity(ctx, &duel.challenger_identity)
+        .filter(|challenger| challenger.is_online)
+        .ok_or("Your challenger is no longer online")?;
+    check_duel_range(&player, &challenger)?;
+
+    duel.accepted = true;
+    restore_health(ctx, &duel);
+    ctx.db.duel().duel_id().update(duel);
+
+    PlayerNotification::send(ctx, challenger.identity, "duel", format!("{} accepted your duel", player.username));
+    Ok(())
+}
+
+/// Decline or withdraw a challenge, or give up a duel in progress (which counts as a loss)
+#[reducer]
+pub fn forfeit_duel(ctx: &ReducerContext, duel_id: u64) -> Result<(), String> {
+    let duel = find_own_duel(ctx, duel_id)?;
+
+    if !duel.accepted {
+        ctx.db.duel().duel_id().delete(&duel_id);
+        PlayerNotification::send(ctx, duel.other_side(&ctx.sender), "duel", "The duel challenge was called off".to_string());
+        return Ok(());
+    }
+
+    finish_duel(ctx, &duel, &duel.other_side(&ctx.sender));
+    Ok(())
+}
+
+/// Strike your duel opponent with your equipped weapon
+/// The server works out the damage from both players' gear
+#[reducer]
+pub fn attack_in_duel(ctx: &ReducerContext, duel_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+
+    let duel = find_own_duel(ctx, duel_id)?;
+    if !duel.accepted {
+        return Err("That duel has not started yet".to_string());
+    }
+
+    let mut target = Player::filter_by_identity(ctx, &duel.other_side(&player.identity))
+        .filter(|target| target.is_online)
+        .ok_or("Your opponent is no longer online")?;
+    check_duel_range(&player, &target)?;
+
+    if !RateLimitBucket::try_consume(ctx, &ctx.sender, "duel_attack", 1.0, DUEL_ATTACKS_PER_SECOND) {
+        return Err("You are attacking too quickly".to_string());
+    }
+
+    let damage = duel_hit_damage(weapon_damage(ctx, &player.identity), armor_defense(ctx, &target.identity));
+    wear_equipped_item(ctx, &player.identity, "weapon", ITEM_WEAR_PER_ATTACK);
+    wear_equipped_item(ctx, &target.identity, "armor", ITEM_WEAR_PER_HIT_TAKEN);
+
+    target.health = (target.health - damage as f32).max(0.0);
+    let defeated = target.health <= 0.0;
+    ctx.db.game_players().identity().update(target);
+
+    if defeated {
+        finish_duel(ctx, &duel, &player.identity);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,635 @@
+//! Data-defined quests with prerequisites, objectives, rewards and daily resets
+
+use spacetimedb::{table, reducer, client_visibility_filter, Filter, ReducerContext, Identity, Timestamp, ScheduleAt, Table};
+use shared_module::*;
+use server_module::*;
+use server_module::utils::validation::validate_player_in_game;
+use crate::*;
+use crate::mechanics::*;
+use crate::wallet::PlayerWallet;
+use crate::achievement::{record_achievement_event, AchievementEvent};
+
+/// What a quest objective asks for
+#[derive(Clone, Debug, PartialEq)]
+pub enum QuestObjectiveKind {
+    /// Kill NPCs of the target type
+    KillNpc,
+    /// Hold the target item; the items are handed in with the quest
+    CollectItem,
+    /// Go to the target zone, or a spot within it
+    ReachLocation,
+    /// Talk to the NPC with the target name
+    TalkToNpc,
+}
+
+impl std::fmt::Display for QuestObjectiveKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            QuestObjectiveKind::KillNpc => "kill_npc",
+            QuestObjectiveKind::CollectItem => "collect_item",
+            QuestObjectiveKind::ReachLocation => "reach_location",
+            QuestObjectiveKind::TalkToNpc => "talk_to_npc",
+        })
+    }
+}
+
+impl QuestObjectiveKind {
+    pub fn from_string(s: &str) -> Option<QuestObjectiveKind> {
+        match s {
+            "kill_npc" => Some(QuestObjectiveKind::KillNpc),
+            "collect_item" => Some(QuestObjectiveKind::CollectItem),
+            "reach_location" => Some(QuestObjectiveKind::ReachLocation),
+            "talk_to_npc" => Some(QuestObjectiveKind::TalkToNpc),
+            _ => None,
+        }
+    }
+}
+
+/// A gameplay event that can advance quest objectives
+#[derive(Clone, Debug)]
+pub enum QuestEvent {
+    NpcKilled { npc_type: String },
+    ItemCollected { item_id: String },
+    PlayerMoved { zone: String, x: f32, y: f32, z: f32 },
+    NpcTalkedTo { npc_name: String },
+}
+
+/// Quest definition
+#[derive(Clone, Debug)]
+#[table(name = quest_definitions, public)]
+pub struct QuestDefinition {
+    #[primary_key]
+    pub quest_id: String,
+    pub name: String,
+    pub description: String,
+
+    /// Name of the NPC who offers the quest and takes it back; empty if it needs no giver
+    pub giver_npc_name: String,
+    pub min_level: u32,
+    /// Quests that must be turned in before this one is offered
+    pub prerequisite_quest_ids: Vec<String>,
+    /// Daily quests can be done again after each daily reset
+    pub daily: bool,
+
+    pub reward_experience: u64,
+    pub reward_currency: u64,
+}
+
+/// One objective of a quest
+#[derive(Clone, Debug)]
+#[table(name = quest_objectives, public)]
+pub struct QuestObjective {
+    #[primary_key]
+    #[auto_inc]
+    pub objective_id: u64,
+
+    #[index(btree)]
+    pub quest_id: String,
+    /// Position of this objective's counter in PlayerQuest.progress
+    pub objective_index: u32,
+
+    /// QuestObjectiveKind as a string
+    pub kind: String,
+    /// NPC type, item, zone or NPC name the objective applies to
+    pub target: String,
+    pub required_count: u32,
+
+    /// For reach objectives, the spot to reach; a radius of 0 accepts anywhere in the zone
+    pub position_x: f32,
+    pub position_y: f32,
+    pub position_z: f32,
+    pub radius: f32,
+
+    pub description: String,
+}
+
+/// An item handed out when a quest is turned in
+#[derive(Clone, Debug)]
+#[table(name = quest_reward_items, public)]
+pub struct QuestRewardItem {
+    #[primary_key]
+    #[auto_inc]
+    pub reward_id: u64,
+
+    #[index(btree)]
+    pub quest_id: String,
+    pub item_id: String,
+    pub quantity: u32,
+}
+
+/// A quest a player is working on
+#[derive(Clone, Debug)]
+#[table(name = player_quest, public)]
+pub struct PlayerQuest {
+    #[primary_key]
+    #[auto_inc]
+    pub player_quest_id: u64,
+
+    #[index(btree)]
+    pub player_identity: Identity,
+    pub quest_id: String,
+
+    /// Progress per objective, indexed by QuestObjective.objective_index
+    pub progress: Vec<u32>,
+    pub accepted_at: Timestamp,
+}
+
+/// A quest a player has turned in
+#[derive(Clone, Debug)]
+#[table(name = quest_completion, public)]
+pub struct QuestCompletion {
+    #[primary_key]
+    #[auto_inc]
+    pub completion_id: u64,
+
+    #[index(btree)]
+    pub player_identity: Identity,
+    pub quest_id: String,
+
+    pub times_completed: u32,
+    pub last_completed_at: Timestamp,
+    /// Set when a daily quest is turned in, cleared by the next daily reset
+    pub on_cooldown: bool,
+}
+
+/// Job that makes daily quests available again; it reschedules itself after each run
+#[table(name = daily_quest_reset_schedule, scheduled(reset_daily_quests))]
+pub struct DailyQuestResetSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub scheduled_id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+/// Players only see their own quest log
+#[client_visibility_filter]
+const PLAYER_QUEST_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM player_quest WHERE player_identity = :sender"
+);
+
+/// Quest history is private to the player
+#[client_visibility_filter]
+const QUEST_COMPLETION_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM quest_completion WHERE player_identity = :sender"
+);
+
+impl QuestDefinition {
+    /// The quest's objectives in progress order
+    pub fn objectives(&self, ctx: &ReducerContext) -> Vec<QuestObjective> {
+        let mut objectives: Vec<QuestObjective> = ctx.db.quest_objectives().quest_id().filter(&self.quest_id).collect();
+        objectives.sort_by_key(|objective| objective.objective_index);
+        objectives
+    }
+
+    /// Items handed out when the quest is turned in
+    pub fn reward_items(&self, ctx: &ReducerContext) -> Vec<QuestRewardItem> {
+        ctx.db.quest_reward_items().quest_id().filter(&self.quest_id).collect()
+    }
+}
+
+impl QuestObjective {
+    /// Progress an event makes towards this objective, if it applies
+    /// Counted events add to progress; collect and reach objectives are set from the player's state
+    fn progress_from(&self, ctx: &ReducerContext, identity: &Identity, event: &QuestEvent, current: u32) -> Option<u32> {
+        let kind = QuestObjectiveKind::from_string(&self.kind)?;
+
+        match (kind, event) {
+            (QuestObjectiveKind::KillNpc, QuestEvent::NpcKilled { npc_type }) if self.target == *npc_type => {
+                Some(current.saturating_add(1))
+            },
+            (QuestObjectiveKind::CollectItem, QuestEvent::ItemCollected { item_id }) if self.target == *item_id => {
+                Some(count_inventory_item(ctx, identity, item_id))
+            },
+            (QuestObjectiveKind::ReachLocation, QuestEvent::PlayerMoved { zone, x, y, z }) => {
+                self.is_reached(zone, *x, *y, *z).then_some(self.required_count)
+            },
+            (QuestObjectiveKind::TalkToNpc, QuestEvent::NpcTalkedTo { npc_name }) if self.target == *npc_name => {
+                Some(current.saturating_add(1))
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether a position satisfies a reach objective
+    fn is_reached(&self, zone: &str, x: f32, y: f32, z: f32) -> bool {
+        if self.target != zone {
+            return false;
+        }
+
+        self.radius <= 0.0
+            || calculate_distance(x, y, z, self.position_x, self.position_y, self.position_z) <= self.radius
+    }
+
+    /// Progress a player already has when accepting the quest
+    fn starting_progress(&self, ctx: &ReducerContext, player: &Player) -> u32 {
+        match QuestObjectiveKind::from_string(&self.kind) {
+            Some(QuestObjectiveKind::CollectItem) => {
+                count_inventory_item(ctx, &player.identity, &self.target).min(self.required_count)
+            },
+            Some(QuestObjectiveKind::ReachLocation)
+                if self.is_reached(&player.current_zone, player.position_x, player.position_y, player.position_z) => {
+                self.required_count
+            },
+            _ => 0,
+        }
+    }
+}
+
+impl PlayerQuest {
+    /// A player's active quests
+    pub fn get_for_player(ctx: &ReducerContext, identity: &Identity) -> Vec<PlayerQuest> {
+        ctx.db.player_quest().player_identity().filter(identity).collect()
+    }
+
+    /// Find one of a player's active quests
+    pub fn find(ctx: &ReducerContext, identity: &Identity, quest_id: &str) -> Option<PlayerQuest> {
+        ctx.db.player_quest().player_identity()
+            .filter(identity)
+            .find(|entry| entry.quest_id == quest_id)
+    }
+
+    /// Whether every objective has reached its required count
+    pub fn is_complete(&self, objectives: &[QuestObjective]) -> bool {
+        objectives.iter().all(|objective| {
+            self.progress.get(objective.objective_index as usize).copied().unwrap_or(0) >= objective.required_count
+        })
+    }
+}
+
+impl QuestCompletion {
+    /// Find a player's record of a quest
+    pub fn find(ctx: &ReducerContext, identity: &Identity, quest_id: &str) -> Option<QuestCompletion> {
+        ctx.db.quest_completion().player_identity()
+            .filter(identity)
+            .find(|entry| entry.quest_id == quest_id)
+    }
+
+    /// Count a turn-in, putting daily quests on cooldown until the next reset
+    fn record(ctx: &ReducerContext, identity: &Identity, quest: &QuestDefinition) {
+        match Self::find(ctx, identity, &quest.quest_id) {
+            Some(existing) => {
+                ctx.db.quest_completion().completion_id().update(QuestCompletion {
+                    times_completed: existing.times_completed.saturating_add(1),
+                    last_completed_at: ctx.timestamp,
+                    on_cooldown: quest.daily,
+                    ..existing
+                });
+            },
+            None => {
+                ctx.db.quest_completion().insert(QuestCompletion {
+                    completion_id: 0, // auto_inc
+                    player_identity: *identity,
+                    quest_id: quest.quest_id.clone(),
+                    times_completed: 1,
+                    last_completed_at: ctx.timestamp,
+                    on_cooldown: quest.daily,
+                });
+            },
+        }
+    }
+}
+
+/// Set up quest definitions and the daily reset job
+pub fn initialize_quests(ctx: &ReducerContext) -> Result<(), String> {
+    if ctx.db.quest_definitions().count() == 0 {
+        create_default_quests(ctx);
+    }
+
+    if ctx.db.daily_quest_reset_schedule().count() == 0 {
+        schedule_daily_reset(ctx);
+    }
+
+    log::info!("Quest system initialized");
+    Ok(())
+}
+
+/// When the next daily reset happens, at DAILY_QUEST_RESET_HOUR_UTC
+fn next_daily_reset(now: Timestamp) -> Timestamp {
+    const DAY_MICROS: i64 = 24 * 60 * 60 * 1_000_000;
+    let offset_micros = (DAILY_QUEST_RESET_HOUR_UTC as i64 % 24) * 60 * 60 * 1_000_000;
+
+    let now_micros = now.to_micros_since_unix_epoch();
+    let day_start = (now_micros - offset_micros).div_euclid(DAY_MICROS) * DAY_MICROS + offset_micros;
+    Timestamp::from_micros_since_unix_epoch(day_start + DAY_MICROS)
+}
+
+fn schedule_daily_reset(ctx: &ReducerContext) {
+    ctx.db.daily_quest_reset_schedule().insert(DailyQuestResetSchedule {
+        scheduled_id: 0, // auto_inc
+        scheduled_at: ScheduleAt::Time(next_daily_reset(ctx.timestamp)),
+    });
+}
+
+/// Create default quest definitions
+fn create_default_quests(ctx: &ReducerContext) {
+    let default_quests = vec![
+        ("goblin_menace", "Goblin Menace", "Captain Aldric wants the goblins near town thinned out", "Captain Aldric", 1, vec![], false, 200, 50),
+        ("iron_for_the_forge", "Iron for the Forge", "Bring iron ore to the smith so the guard can be armed", "Smith Bera", 3, vec!["goblin_menace"], false, 300, 75),
+        ("scouting_report", "Scouting Report", "Scout the ridge east of town and report back to the captain", "Captain Aldric", 3, vec!["goblin_menace"], false, 250, 0),
+        ("daily_goblin_cull", "Daily Goblin Cull", "The goblins keep coming back; keep them in check", "Captain Aldric", 5, vec!["goblin_menace"], true, 150, 40),
+    ];
+
+    for (id, name, desc, giver, min_level, prerequisites, daily, experience, currency) in default_quests {
+        ctx.db.quest_definitions().insert(QuestDefinition {
+            quest_id: id.to_string(),
+            name: name.to_string(),
+            description: desc.to_string(),
+            giver_npc_name: giver.to_string(),
+            min_level,
+            prerequisite_quest_ids: prerequisites.into_iter().map(|quest_id: &str| quest_id.to_string()).collect(),
+            daily,
+            reward_experience: experience,
+            reward_currency: currency,
+        });
+    }
+
+    // (quest, kind, target, count, position and radius, description)
+    let default_objectives = vec![
+        ("goblin_menace", QuestObjectiveKind::KillNpc, "goblin", 10, (0.0, 0.0, 0.0, 0.0), "Kill 10 goblins"),
+        ("iron_for_the_forge", QuestObjectiveKind::CollectItem, "ore_iron", 20, (0.0, 0.0, 0.0, 0.0), "Collect 20 iron ore"),
+        ("scouting_report", QuestObjectiveKind::ReachLocation, DEFAULT_STARTING_ZONE, 1, (500.0, 0.0, 0.0, 25.0), "Reach the eastern ridge"),
+        ("scouting_report", QuestObjectiveKind::TalkToNpc, "Captain Aldric", 1, (0.0, 0.0, 0.0, 0.0), "Report to Captain Aldric"),
+        ("daily_goblin_cull", QuestObjectiveKind::KillNpc, "goblin", 20, (0.0, 0.0, 0.0, 0.0), "Kill 20 goblins"),
+    ];
+
+    let mut next_index: std::collections::HashMap<&str, u32> = std::collections::HashMap::new();
+    for (quest_id, kind, target, count, (x, y, z, radius), desc) in default_objectives {
+        let index = next_index.entry(quest_id).or_insert(0);
+        ctx.db.quest_objectives().insert(QuestObjective {
+            objective_id: 0, // auto_inc
+            quest_id: quest_id.to_string(),
+            objective_index: *index,
+            kind: kind.to_string(),
+            target: target.to_string(),
+            required_count: count,
+            position_x: x,
+            position_y: y,
+            position_z: z,
+            radius,
+            description: desc.to_string(),
+        });
+        *index += 1;
+    }
+
+    let default_rewards = vec![
+        ("goblin_menace", "potion_health", 3),
+        ("iron_for_the_forge", "sword_iron", 1),
+        ("scouting_report", "armor_leather", 1),
+        ("daily_goblin_cull", "potion_health", 2),
+    ];
+
+    for (quest_id, item_id, quantity) in default_rewards {
+        ctx.db.quest_reward_items().insert(QuestRewardItem {
+            reward_id: 0, // auto_inc
+            quest_id: quest_id.to_string(),
+            item_id: item_id.to_string(),
+            quantity,
+        });
+    }
+}
+
+/// Advance a player's active quests from a gameplay event
+/// Never fails, so hooks cannot abort the action that triggered them
+pub fn record_quest_event(ctx: &ReducerContext, identity: &Identity, event: QuestEvent) {
+    for mut entry in PlayerQuest::get_for_player(ctx, identity) {
+        let Some(quest) = ctx.db.quest_definitions().quest_id().find(&entry.quest_id) else {
+            continue;
+        };
+
+        let objectives = quest.objectives(ctx);
+        let was_complete = entry.is_complete(&objectives);
+        let mut changed = false;
+
+        for objective in &objectives {
+            let index = objective.objective_index as usize;
+            if entry.progress.len() <= index {
+                entry.progress.resize(index + 1, 0);
+            }
+
+            let current = entry.progress[index];
+            let Some(progress) = objective.progress_from(ctx, identity, &event, current) else {
+                continue;
+            };
+
+            let progress = progress.min(objective.required_count);
+            if progress != current {
+                entry.progress[index] = progress;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            continue;
+        }
+
+        if !was_complete && entry.is_complete(&objectives) {
+            PlayerNotification::send(ctx, *identity, "quest", format!("{} is ready to turn in", quest.name));
+        }
+
+        ctx.db.player_quest().player_quest_id().update(entry);
+    }
+}
+
+/// Check an NPC is alive and the player is standing next to it
+pub fn validate_npc_in_range(player: &Player, npc: &NPC) -> Result<(), String> {
+    if npc.ai_state == AIState::Dead.to_string() {
+        return Err(format!("{} cannot talk right now", npc.name));
+    }
+
+    let distance = calculate_distance(
+        player.position_x, player.position_y, player.position_z,
+        npc.position_x, npc.position_y, npc.position_z
+    );
+
+    if distance > QUEST_INTERACTION_RANGE {
+        return Err(format!("Too far away from {}", npc.name));
+    }
+
+    Ok(())
+}
+
+/// Check the player is next to the quest's giver, if it has one
+/// Several NPCs can share a name; any of them will do
+fn validate_quest_giver(ctx: &ReducerContext, player: &Player, quest: &QuestDefinition) -> Result<(), String> {
+    if quest.giver_npc_name.is_empty() {
+        return Ok(());
+    }
+
+    let giver_in_range = ctx.db.npcs().iter()
+        .filter(|npc| npc.name == quest.giver_npc_name)
+        .any(|npc| validate_npc_in_range(player, &npc).is_ok());
+
+    if !giver_in_range {
+        return Err(format!("You need to be next to {}", quest.giver_npc_name));
+    }
+
+    Ok(())
+}
+
+/// Accept a quest from its giver
+#[reducer]
+pub fn accept_quest(ctx: &ReducerContext, quest_id: String) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+
+    let quest = ctx.db.quest_definitions().quest_id().find(&quest_id)
+        .ok_or("Quest not found")?;
+
+    if PlayerQuest::find(ctx, &player.identity, &quest_id).is_some() {
+        return Err("You are already on that quest".to_string());
+    }
+
+    if PlayerQuest::get_for_player(ctx, &player.identity).len() >= MAX_ACTIVE_QUESTS {
+        return Err(format!("You can only have {} quests at a time", MAX_ACTIVE_QUESTS));
+    }
+
+    if let Some(completion) = QuestCompletion::find(ctx, &player.identity, &quest_id) {
+        if !quest.daily {
+            return Err("You have already completed that quest".to_string());
+        }
+        if completion.on_cooldown {
+            return Err("You have already done that quest today".to_string());
+        }
+    }
+
+    if player.level < quest.min_level {
+        return Err(format!("You must be level {} to accept that quest", quest.min_level));
+    }
+
+    let missing_prerequisite = quest.prerequisite_quest_ids.iter()
+        .any(|prerequisite| QuestCompletion::find(ctx, &player.identity, prerequisite).is_none());
+    if missing_prerequisite {
+        return Err("You are not ready for that quest yet".to_string());
+    }
+
+    validate_quest_giver(ctx, &player, &quest)?;
+
+    let objectives = quest.objectives(ctx);
+    let mut progress = vec![0; objectives.iter().map(|objective| objective.objective_index as usize + 1).max().unwrap_or(0)];
+    for objective in &objectives {
+        progress[objective.objective_index as usize] = objective.starting_progress(ctx, &player);
+    }
+
+    ctx.db.player_quest().insert(PlayerQuest {
+        player_quest_id: 0, // auto_inc
+        player_identity: player.identity,
+        quest_id: quest.quest_id.clone(),
+        progress,
+        accepted_at: ctx.timestamp,
+    });
+
+    PlayerNotification::send(ctx, player.identity, "quest", format!("Quest accepted: {}", quest.name));
+    log::info!("Player {} accepted quest {}", player.username, quest.quest_id);
+    Ok(())
+}
+
+/// Drop a quest, losing its progress
+#[reducer]
+pub fn abandon_quest(ctx: &ReducerContext, quest_id: String) -> Result<(), String> {
+    let entry = PlayerQuest::find(ctx, &ctx.sender, &quest_id)
+        .ok_or("You are not on that quest")?;
+
+    ctx.db.player_quest().player_quest_id().delete(&entry.player_quest_id);
+    Ok(())
+}
+
+/// Hand in a finished quest to its giver and collect the rewards
+/// Collected items are taken first, so the rewards can use the space they free
+#[reducer]
+pub fn turn_in_quest(ctx: &ReducerContext, quest_id: String) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+
+    let entry = PlayerQuest::find(ctx, &player.identity, &quest_id)
+        .ok_or("You are not on that quest")?;
+    let quest = ctx.db.quest_definitions().quest_id().find(&quest_id)
+        .ok_or("Quest not found")?;
+
+    validate_quest_giver(ctx, &player, &quest)?;
+
+    // Collected items may have been sold or traded away since they were counted
+    let objectives = quest.objectives(ctx);
+    let mut progress = entry.progress.clone();
+    for objective in &objectives {
+        if QuestObjectiveKind::from_string(&objective.kind) == Some(QuestObjectiveKind::CollectItem) {
+            let index = objective.objective_index as usize;
+            if progress.len() <= index {
+                progress.resize(index + 1, 0);
+            }
+            progress[index] = count_inventory_item(ctx, &player.identity, &objective.target).min(objective.required_count);
+        }
+    }
+
+    let entry = PlayerQuest { progress, ..entry };
+    if !entry.is_complete(&objectives) {
+        return Err("That quest is not finished yet".to_string());
+    }
+
+    for objective in &objectives {
+        if QuestObjectiveKind::from_string(&objective.kind) == Some(QuestObjectiveKind::CollectItem) {
+            take_inventory_items(ctx, &player.identity, &objective.target, objective.required_count)?;
+        }
+    }
+
+    ctx.db.player_quest().player_quest_id().delete(&entry.player_quest_id);
+    QuestCompletion::record(ctx, &player.identity, &quest);
+
+    for reward in quest.reward_items(ctx) {
+        add_item_to_inventory(ctx, &player.identity, &reward.item_id, reward.quantity)
+            .map_err(|e| format!("Could not hand out {}: {}", reward.item_id, e))?;
+    }
+
+    if quest.reward_currency > 0 {
+        PlayerWallet::credit(ctx, &player.identity, quest.reward_currency)?;
+    }
+
+    PlayerNotification::send(ctx, player.identity, "quest", format!("Quest completed: {}", quest.name));
+    log::info!("Player {} turned in quest {}", player.username, quest.quest_id);
+
+    if quest.reward_experience > 0 {
+        if let Some(new_level) = Player::grant_experience(ctx, &player.identity, quest.reward_experience) {
+            PlayerNotification::send(ctx, player.identity, "progression", format!("You reached level {}", new_level));
+            record_achievement_event(ctx, &player.identity, AchievementEvent::LevelReached { level: new_level });
+        }
+    }
+
+    Ok(())
+}
+
+/// Check reach-location objectives against a player's new position
+/// ServerModule runs this after every move and zone change
+fn on_player_moved(ctx: &ReducerContext, player: &Player) {
+    record_quest_event(ctx, &player.identity, QuestEvent::PlayerMoved {
+        zone: player.current_zone.clone(),
+        x: player.position_x,
+        y: player.position_y,
+        z: player.position_z,
+    });
+}
+
+server_module::register_player_moved_hook!(on_player_moved);
+
+/// Make daily quests available again and schedule the next reset
+/// Runs from daily_quest_reset_schedule
+#[reducer]
+pub fn reset_daily_quests(ctx: &ReducerContext, _schedule: DailyQuestResetSchedule) -> Result<(), String> {
+    // Only the scheduler may run this
+    if ctx.sender != ctx.identity() {
+        return Err("reset_daily_quests may only be invoked by the scheduler".to_string());
+    }
+
+    let cooling_down: Vec<QuestCompletion> = ctx.db.quest_completion().iter()
+        .filter(|completion| completion.on_cooldown)
+        .collect();
+
+    let reset = cooling_down.len();
+    for completion in cooling_down {
+        ctx.db.quest_completion().completion_id().update(QuestCompletion {
+            on_cooldown: false,
+            ..completion
+        });
+    }
+
+    schedule_daily_reset(ctx);
+
+    log::info!("Daily quest reset made {} quests available again", reset);
+    Ok(())
+}
@@ -0,0 +1,548 @@
+//! Two-party trade windows: request, offer, lock, confirm, then an atomic swap
+
+use spacetimedb::{table, reducer, client_visibility_filter, Filter, ReducerContext, Identity, Timestamp, Table};
+use shared_module::*;
+use server_module::*;
+use server_module::utils::validation::{validate_player_in_game, validate_rate_limit, validate_target_player};
+use crate::mechanics::*;
+use crate::item_instance::{give_item_instance, ItemInstance};
+use crate::wallet::PlayerWallet;
+
+/// Trade session status
+#[derive(Clone, Debug, PartialEq)]
+pub enum TradeStatus {
+    /// Waiting for the partner to accept
+    Requested,
+    /// Both sides are building their offers
+    Open,
+}
+
+impl std::fmt::Display for TradeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TradeStatus::Requested => "requested",
+            TradeStatus::Open => "open",
+        })
+    }
+}
+
+impl TradeStatus {
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "requested" => Some(TradeStatus::Requested),
+            "open" => Some(TradeStatus::Open),
+            _ => None,
+        }
+    }
+}
+
+/// A trade between two players
+/// Each side locks its offer, then confirms once both are locked; changing
+/// anything after a lock unlocks both sides again
+#[derive(Clone, Debug)]
+#[table(name = trade_session, public)]
+pub struct TradeSession {
+    #[primary_key]
+    #[auto_inc]
+    pub trade_id: u64,
+
+    #[index(btree)]
+    pub initiator_identity: Identity,
+    pub initiator_username: String,
+
+    #[index(btree)]
+    pub partner_identity: Identity,
+    pub partner_username: String,
+
+    pub status: String,
+
+    pub initiator_currency: u64,
+    pub partner_currency: u64,
+
+    pub initiator_locked: bool,
+    pub partner_locked: bool,
+    pub initiator_confirmed: bool,
+    pub partner_confirmed: bool,
+
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+}
+
+/// An inventory stack (or part of one) offered in a trade
+/// The items stay in the owner's inventory until the swap
+#[derive(Clone, Debug)]
+#[table(name = trade_offer, public)]
+pub struct TradeOffer {
+    #[primary_key]
+    #[auto_inc]
+    pub offer_id: u64,
+
+    #[index(btree)]
+    pub trade_id: u64,
+
+    pub owner_identity: Identity,
+    pub inventory_id: u64,
+    pub item_id: String,
+    pub quantity: u32,
+}
+
+/// Record of a completed trade
+#[derive(Clone, Debug)]
+#[table(name = trade_log)]
+pub struct TradeLog {
+    #[primary_key]
+    #[auto_inc]
+    pub log_id: u64,
+
+    pub trade_id: u64,
+
+    #[index(btree)]
+    pub initiator_identity: Identity,
+    pub initiator_username: String,
+
+    #[index(btree)]
+    pub partner_identity: Identity,
+    pub partner_username: String,
+
+    /// What each side gave, e.g. "ore_iron x20, potion_health x3"
+    pub initiator_items: String,
+    pub partner_items: String,
+    pub initiator_currency: u64,
+    pub partner_currency: u64,
+
+    pub completed_at: Timestamp,
+}
+
+/// Players see trades they take part in
+#[client_visibility_filter]
+const TRADE_SESSION_INITIATOR_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM trade_session WHERE initiator_identity = :sender"
+);
+
+#[client_visibility_filter]
+const TRADE_SESSION_PARTNER_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM trade_session WHERE partner_identity = :sender"
+);
+
+/// Both sides see every offer in their trade
+#[client_visibility_filter]
+const TRADE_OFFER_INITIATOR_VISIBILITY: Filter = Filter::Sql(
+    "SELECT trade_offer.* FROM trade_offer \
+     JOIN trade_session ON trade_offer.trade_id = trade_session.trade_id \
+     WHERE trade_session.initiator_identity = :sender"
+);
+
+#[client_visibility_filter]
+const TRADE_OFFER_PARTNER_VISIBILITY: Filter = Filter::Sql(
+    "SELECT trade_offer.* FROM trade_offer \
+     JOIN trade_session ON trade_offer.trade_id = trade_session.trade_id \
+     WHERE trade_session.partner_identity = :sender"
+);
+
+impl TradeSession {
+    /// Find trade by ID
+    pub fn filter_by_trade_id(ctx: &ReducerContext, trade_id: u64) -> Option<TradeSession> {
+        ctx.db.trade_session().trade_id().find(&trade_id)
+    }
+
+    /// The trade a player is currently part of, on either side
+    pub fn find_for_player(ctx: &ReducerContext, identity: &Identity) -> Option<TradeSession> {
+        ctx.db.trade_session().initiator_identity().filter(identity).next()
+            .or_else(|| ctx.db.trade_session().partner_identity().filter(identity).next())
+    }
+
+    pub fn status(&self) -> Option<TradeStatus> {
+        TradeStatus::from_string(&self.status)
+    }
+
+    /// Whether an unanswered request has timed out
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.status() == Some(TradeStatus::Requested)
+            && now.duration_since(self.created_at).unwrap_or_default().as_secs() > TRADE_REQUEST_EXPIRY_SECONDS
+    }
+
+    pub fn is_initiator(&self, identity: &Identity) -> bool {
+        self.initiator_identity == *identity
+    }
+
+    /// The other participant
+    pub fn other_side(&self, identity: &Identity) -> Identity {
+        if self.is_initiator(identity) { self.partner_identity } else { self.initiator_identity }
+    }
+
+    /// Drop both locks and confirmations after either offer changed
+    pub fn unlock(&mut self) {
+        self.initiator_locked = false;
+        self.partner_locked = false;
+        self.initiator_confirmed = false;
+        self.partner_confirmed = false;
+    }
+
+    pub fn offers(&self, ctx: &ReducerContext) -> Vec<TradeOffer> {
+        ctx.db.trade_offer().trade_id().filter(&self.trade_id).collect()
+    }
+
+    pub fn save(ctx: &ReducerContext, mut trade: TradeSession) {
+        trade.updated_at = ctx.timestamp;
+        ctx.db.trade_session().trade_id().update(trade);
+    }
+
+    /// Remove a trade and its offers
+    pub fn close(ctx: &ReducerContext, trade_id: u64) {
+        ctx.db.trade_offer().trade_id().delete(&trade_id);
+        ctx.db.trade_session().trade_id().delete(&trade_id);
+    }
+}
+
+/// Two players must be in the same zone and close to each other to trade
+fn check_trade_range(a: &Player, b: &Player) -> Result<(), String> {
+    let distance = calculate_distance(
+        a.position_x, a.position_y, a.position_z,
+        b.position_x, b.position_y, b.position_z
+    );
+
+    if a.current_zone != b.current_zone || distance > MAX_TRADE_DISTANCE {
+        return Err("You are too far away from your trade partner".to_string());
+    }
+
+    Ok(())
+}
+
+/// Both participants must still be online and in range
+fn validate_trade_distance(ctx: &ReducerContext, trade: &TradeSession) -> Result<(), String> {
+    let initiator = Player::filter_by_identity(ctx, &trade.initiator_identity)
+        .filter(|player| player.is_online)
+        .ok_or("Your trade partner is no longer online")?;
+    let partner = Player::filter_by_identity(ctx, &trade.partner_identity)
+        .filter(|player| player.is_online)
+        .ok_or("Your trade partner is no longer online")?;
+
+    check_trade_range(&initiator, &partner)
+}
+
+/// Look up an open trade the caller takes part in
+fn find_open_trade(ctx: &ReducerContext, trade_id: u64) -> Result<TradeSession, String> {
+    let trade = TradeSession::filter_by_trade_id(ctx, trade_id)
+        .filter(|trade| trade.initiator_identity == ctx.sender || trade.partner_identity == ctx.sender)
+        .ok_or("Trade not found")?;
+
+    if trade.status() != Some(TradeStatus::Open) {
+        return Err("That trade has not been accepted yet".to_string());
+    }
+
+    Ok(trade)
+}
+
+/// Summarize one side's offers for the trade log
+fn describe_offers(offers: &[TradeOffer]) -> String {
+    offers.iter()
+        .map(|offer| format!("{} x{}", offer.item_id, offer.quantity))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Ask a nearby player to trade
+#[reducer]
+pub fn request_trade(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    let target = validate_target_player(ctx, &target_username)?;
+
+    if target.identity == player.identity {
+        return Err("You cannot trade with yourself".to_string());
+    }
+
+    // Clear out unanswered requests that have run out
+    for identity in [player.identity, target.identity] {
+        if let Some(trade) = TradeSession::find_for_player(ctx, &identity) {
+            if trade.is_expired(ctx.timestamp) {
+                TradeSession::close(ctx, trade.trade_id);
+            }
+        }
+    }
+
+    if TradeSession::find_for_player(ctx, &player.identity).is_some() {
+        return Err("You are already trading".to_string());
+    }
+
+    if TradeSession::find_for_player(ctx, &target.identity).is_some() {
+        return Err("That player is busy trading".to_string());
+    }
+
+    check_trade_range(&player, &target)?;
+
+    validate_rate_limit(ctx, "trade_request")?;
+
+    // Requests to someone ignoring the sender are dropped silently
+    if IgnoredPlayer::is_ignoring(ctx, &target.identity, &player.identity) {
+        return Ok(());
+    }
+
+    ctx.db.trade_session().insert(TradeSession {
+        trade_id: 0, // auto_inc
+        initiator_identity: player.identity,
+        initiator_username: player.username.clone(),
+        partner_identity: target.identity,
+        partner_username: target.username.clone(),
+        status: TradeStatus::Requested.to_string(),
+        initiator_currency: 0,
+        partner_currency: 0,
+        initiator_locked: false,
+        partner_locked: false,
+        initiator_confirmed: false,
+        partner_confirmed: false,
+        created_at: ctx.timestamp,
+        updated_at: ctx.timestamp,
+    });
+
+    PlayerNotification::send(ctx, target.identity, "trade", format!("{} wants to trade with you", player.username));
+    Ok(())
+}
+
+/// Accept a trade request and open the trade window
+#[reducer]
+pub fn accept_trade(ctx: &ReducerContext, trade_id: u64) -> Result<(), String> {
+    validate_player_in_game(ctx)?;
+
+    let mut trade = TradeSession::filter_by_trade_id(ctx, trade_id)
+        .filter(|trade| trade.partner_identity == ctx.sender)
+        .ok_or("Trade not found")?;
+
+    if trade.status() != Some(TradeStatus::Requested) {
+        return Err("That trade is already open".to_string());
+    }
+
+    if trade.is_expired(ctx.timestamp) {
+        return Err("That trade request has expired".to_string());
+    }
+
+    validate_trade_distance(ctx, &trade)?;
+
+    trade.status = TradeStatus::Open.to_string();
+    let initiator = trade.initiator_identity;
+    let partner_username = trade.partner_username.clone();
+    TradeSession::save(ctx, trade);
+
+    PlayerNotification::send(ctx, initiator, "trade", format!("{} accepted your trade request", partner_username));
+    Ok(())
+}
+
+/// Decline a trade request or cancel a trade in progress
+#[reducer]
+pub fn cancel_trade(ctx: &ReducerContext, trade_id: u64) -> Result<(), String> {
+    let trade = TradeSession::filter_by_trade_id(ctx, trade_id)
+        .filter(|trade| trade.initiator_identity == ctx.sender || trade.partner_identity == ctx.sender)
+        .ok_or("Trade not found")?;
+
+    TradeSession::close(ctx, trade_id);
+
+    PlayerNotification::send(ctx, trade.other_side(&ctx.sender), "trade", "The trade was cancelled".to_string());
+    Ok(())
+}
+
+/// Offer items from one of your inventory stacks
+/// Offering the same stack again replaces the quantity
+#[reducer]
+pub fn add_trade_item(ctx: &ReducerContext, trade_id: u64, inventory_id: u64, quantity: u32) -> Result<(), String> {
+    let mut trade = find_open_trade(ctx, trade_id)?;
+
+    if quantity == 0 {
+        return Err("Quantity must be at least 1".to_string());
+    }
+
+    let stack = ctx.db.player_inventory().inventory_id().find(&inventory_id)
+        .filter(|stack| stack.player_identity == ctx.sender)
+        .ok_or("Item not found in your inventory")?;
+
+    if stack.quantity < quantity {
+        return Err("Not enough items in inventory".to_string());
+    }
+
+    if let Some(instance_id) = stack.instance_id {
+        ItemInstance::find_owned(ctx, &ctx.sender, instance_id)?.validate_tradeable()?;
+    }
+
+    let own_offers: Vec<TradeOffer> = trade.offers(ctx)
+        .into_iter()
+        .filter(|offer| offer.owner_identity == ctx.sender)
+        .collect();
+
+    match own_offers.iter().find(|offer| offer.inventory_id == inventory_id) {
+        Some(existing) => {
+            let mut updated = existing.clone();
+            updated.quantity = quantity;
+            ctx.db.trade_offer().offer_id().update(updated);
+        },
+        None => {
+            if own_offers.len() >= MAX_TRADE_ITEMS {
+                return Err(format!("You can offer at most {} stacks", MAX_TRADE_ITEMS));
+            }
+            ctx.db.trade_offer().insert(TradeOffer {
+                offer_id: 0, // auto_inc
+                trade_id,
+                owner_identity: ctx.sender,
+                inventory_id,
+                item_id: stack.item_id,
+                quantity,
+            });
+        },
+    }
+
+    trade.unlock();
+    TradeSession::save(ctx, trade);
+    Ok(())
+}
+
+/// Take an item back out of your offer
+#[reducer]
+pub fn remove_trade_item(ctx: &ReducerContext, trade_id: u64, offer_id: u64) -> Result<(), String> {
+    let mut trade = find_open_trade(ctx, trade_id)?;
+
+    ctx.db.trade_offer().offer_id().find(&offer_id)
+        .filter(|offer| offer.trade_id == trade_id && offer.owner_identity == ctx.sender)
+        .ok_or("Offer not found")?;
+
+    ctx.db.trade_offer().offer_id().delete(&offer_id);
+
+    trade.unlock();
+    TradeSession::save(ctx, trade);
+    Ok(())
+}
+
+/// Set how much currency you are offering
+#[reducer]
+pub fn set_trade_currency(ctx: &ReducerContext, trade_id: u64, amount: u64) -> Result<(), String> {
+    let mut trade = find_open_trade(ctx, trade_id)?;
+
+    if PlayerWallet::balance(ctx, &ctx.sender) < amount {
+        return Err("Not enough currency".to_string());
+    }
+
+    if trade.is_initiator(&ctx.sender) {
+        trade.initiator_currency = amount;
+    } else {
+        trade.partner_currency = amount;
+    }
+
+    trade.unlock();
+    TradeSession::save(ctx, trade);
+    Ok(())
+}
+
+/// Lock your side of the trade; nothing can change without unlocking both sides
+#[reducer]
+pub fn lock_trade(ctx: &ReducerContext, trade_id: u64) -> Result<(), String> {
+    let mut trade = find_open_trade(ctx, trade_id)?;
+
+    if trade.is_initiator(&ctx.sender) {
+        trade.initiator_locked = true;
+    } else {
+        trade.partner_locked = true;
+    }
+
+    TradeSession::save(ctx, trade);
+    Ok(())
+}
+
+/// Confirm a trade both sides have locked; the swap happens once both confirm
+#[reducer]
+pub fn confirm_trade(ctx: &ReducerContext, trade_id: u64) -> Result<(), String> {
+    let mut trade = find_open_trade(ctx, trade_id)?;
+
+    if !trade.initiator_locked || !trade.partner_locked {
+        return Err("Both sides must lock the trade before confirming".to_string());
+    }
+
+    if trade.is_initiator(&ctx.sender) {
+        trade.initiator_confirmed = true;
+    } else {
+        trade.partner_confirmed = true;
+    }
+
+    if !(trade.initiator_confirmed && trade.partner_confirmed) {
+        TradeSession::save(ctx, trade);
+        return Ok(());
+    }
+
+    validate_trade_distance(ctx, &trade)?;
+    execute_trade(ctx, &trade)
+}
+
+/// Swap both offers; any failure returns an error and rolls the whole swap back
+fn execute_trade(ctx: &ReducerContext, trade: &TradeSession) -> Result<(), String> {
+    let offers = trade.offers(ctx);
+    let (initiator_offers, partner_offers): (Vec<TradeOffer>, Vec<TradeOffer>) = offers
+        .into_iter()
+        .partition(|offer| offer.owner_identity == trade.initiator_identity);
+
+    // Take everything out of both inventories first, so capacity is checked
+    // against the inventories as they will be after the swap
+    let mut offered_instances = Vec::new();
+    for offer in initiator_offers.iter().chain(partner_offers.iter()) {
+        let mut stack = ctx.db.player_inventory().inventory_id().find(&offer.inventory_id)
+            .filter(|stack| stack.player_identity == offer.owner_identity && stack.item_id == offer.item_id)
+            .ok_or("An offered item is no longer in the inventory")?;
+
+        if stack.quantity < offer.quantity {
+            return Err("An offered item is no longer in the inventory".to_string());
+        }
+
+        // Instances change hands as they are instead of being rolled again
+        if let Some(instance_id) = stack.instance_id {
+            offered_instances.push((offer.offer_id, instance_id));
+        }
+
+        if stack.quantity == offer.quantity {
+            ctx.db.player_inventory().inventory_id().delete(&offer.inventory_id);
+        } else {
+            stack.quantity -= offer.quantity;
+            ctx.db.player_inventory().inventory_id().update(stack);
+        }
+    }
+
+    PlayerWallet::debit(ctx, &trade.initiator_identity, trade.initiator_currency)
+        .map_err(|_| format!("{} no longer has the offered currency", trade.initiator_username))?;
+    PlayerWallet::debit(ctx, &trade.partner_identity, trade.partner_currency)
+        .map_err(|_| format!("{} no longer has the offered currency", trade.partner_username))?;
+
+    let deliver = |offer: &TradeOffer, to: &Identity| match offered_instances.iter().find(|(offer_id, _)| *offer_id == offer.offer_id) {
+        Some((_, instance_id)) => give_item_instance(ctx, *instance_id, to),
+        None => add_item_to_inventory(ctx, to, &offer.item_id, offer.quantity),
+    };
+
+    for offer in &initiator_offers {
+        deliver(offer, &trade.partner_identity)
+            .map_err(|e| format!("{} cannot take the items: {}", trade.partner_username, e))?;
+    }
+    for offer in &partner_offers {
+        deliver(offer, &trade.initiator_identity)
+            .map_err(|e| format!("{} cannot take the items: {}", trade.initiator_username, e))?;
+    }
+
+    PlayerWallet::credit(ctx, &trade.partner_identity, trade.initiator_currency)
+        .map_err(|e| format!("{}: {}", trade.partner_username, e))?;
+    PlayerWallet::credit(ctx, &trade.initiator_identity, trade.partner_currency)
+        .map_err(|e| format!("{}: {}", trade.initiator_username, e))?;
+
+    ctx.db.trade_log().insert(TradeLog {
+        log_id: 0, // auto_inc
+        trade_id: trade.trade_id,
+        initiator_identity: trade.initiator_identity,
+        initiator_username: trade.initiator_username.clone(),
+        partner_identity: trade.partner_identity,
+        partner_username: trade.partner_username.clone(),
+        initiator_items: describe_offers(&initiator_offers),
+        partner_items: describe_offers(&partner_offers),
+        initiator_currency: trade.initiator_currency,
+        partner_currency: trade.partner_currency,
+        completed_at: ctx.timestamp,
+    });
+
+    TradeSession::close(ctx, trade.trade_id);
+
+    for identity in [trade.initiator_identity, trade.partner_identity] {
+        PlayerNotification::send(ctx, identity, "trade", "Trade complete".to_string());
+    }
+
+    log::info!("Trade {} completed between {} and {}", trade.trade_id, trade.initiator_username, trade.partner_username);
+    Ok(())
+}
@@ -0,0 +1,401 @@
+//! Merchant NPC vendors: stock, pricing from GameItem.value, restocking and buyback
+
+use spacetimedb::{table, reducer, client_visibility_filter, Filter, ReducerContext, Identity, Timestamp, ScheduleAt, Table};
+use shared_module::*;
+use server_module::*;
+use server_module::utils::validation::{validate_admin_permissions, validate_player_in_game};
+use crate::*;
+use crate::mechanics::*;
+use crate::wallet::PlayerWallet;
+
+/// Vendor settings for a merchant NPC
+#[derive(Clone, Debug)]
+#[table(name = vendor, public)]
+pub struct Vendor {
+    #[primary_key]
+    pub npc_id: u64,
+
+    /// Players pay GameItem.value times this
+    pub buy_multiplier: f32,
+
+    /// Players receive GameItem.value times this
+    pub sell_multiplier: f32,
+}
+
+/// An item a vendor sells
+/// Unlimited entries never run out; limited ones refill by restock_amount every interval
+#[derive(Clone, Debug)]
+#[table(name = vendor_stock, public)]
+pub struct VendorStock {
+    #[primary_key]
+    #[auto_inc]
+    pub stock_id: u64,
+
+    #[index(btree)]
+    pub npc_id: u64,
+
+    pub item_id: String,
+
+    pub limited: bool,
+    pub quantity: u32,
+    pub max_quantity: u32,
+    pub restock_amount: u32,
+    pub restock_interval_seconds: u64,
+    pub last_restock: Timestamp,
+}
+
+/// An item a player sold recently, which they can buy back for what they got for it
+#[derive(Clone, Debug)]
+#[table(name = vendor_buyback, public)]
+pub struct VendorBuyback {
+    #[primary_key]
+    #[auto_inc]
+    pub buyback_id: u64,
+
+    #[index(btree)]
+    pub player_identity: Identity,
+
+    pub item_id: String,
+    pub quantity: u32,
+    pub price: u64,
+    pub sold_at: Timestamp,
+}
+
+/// Periodic job that refills limited vendor stock
+#[table(name = vendor_restock_schedule, scheduled(restock_vendors))]
+pub struct VendorRestockSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub scheduled_id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+/// Players only see their own buyback list
+#[client_visibility_filter]
+const VENDOR_BUYBACK_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM vendor_buyback WHERE player_identity = :sender"
+);
+
+impl VendorStock {
+    /// Find a vendor's stock entry for an item
+    pub fn find(ctx: &ReducerContext, npc_id: u64, item_id: &str) -> Option<VendorStock> {
+        ctx.db.vendor_stock().npc_id()
+            .filter(&npc_id)
+            .find(|stock| stock.item_id == item_id)
+    }
+
+    /// Refill a limited entry for every full interval that has passed
+    /// Returns whether anything changed
+    fn restock(&mut self, now: Timestamp) -> bool {
+        if !self.limited || self.restock_interval_seconds == 0 {
+            return false;
+        }
+
+        // A full shelf starts its refill timer when the first item leaves it
+        if self.quantity >= self.max_quantity {
+            self.last_restock = now;
+            return false;
+        }
+
+        let elapsed = now.duration_since(self.last_restock).unwrap_or_default().as_secs();
+        let intervals = elapsed / self.restock_interval_seconds;
+        if intervals == 0 {
+            return false;
+        }
+
+        self.last_restock = self.last_restock
+            + std::time::Duration::from_secs(intervals * self.restock_interval_seconds);
+
+        let refill = (self.restock_amount as u64).saturating_mul(intervals).min(u32::MAX as u64) as u32;
+        self.quantity = self.quantity.saturating_add(refill).min(self.max_quantity);
+        true
+    }
+}
+
+impl VendorBuyback {
+    /// Get a player's buyback list, newest first
+    pub fn get_for_player(ctx: &ReducerContext, identity: &Identity) -> Vec<VendorBuyback> {
+        let mut entries: Vec<VendorBuyback> = ctx.db.vendor_buyback().player_identity().filter(identity).collect();
+        entries.sort_by(|a, b| b.sold_at.cmp(&a.sold_at));
+        entries
+    }
+
+    /// Remember a sale, dropping the oldest entries beyond the list size
+    fn record(ctx: &ReducerContext, identity: &Identity, item_id: &str, quantity: u32, price: u64) {
+        ctx.db.vendor_buyback().insert(VendorBuyback {
+            buyback_id: 0, // auto_inc
+            player_identity: *identity,
+            item_id: item_id.to_string(),
+            quantity,
+            price,
+            sold_at: ctx.timestamp,
+        });
+
+        for stale in Self::get_for_player(ctx, identity).into_iter().skip(MAX_BUYBACK_ENTRIES) {
+            ctx.db.vendor_buyback().buyback_id().delete(&stale.buyback_id);
+        }
+    }
+}
+
+/// Start the vendor restock job
+pub fn initialize_vendor_system(ctx: &ReducerContext) -> Result<(), String> {
+    if ctx.db.vendor_restock_schedule().count() == 0 {
+        let interval = std::time::Duration::from_secs(VENDOR_RESTOCK_CHECK_INTERVAL_SECONDS);
+        ctx.db.vendor_restock_schedule().insert(VendorRestockSchedule {
+            scheduled_id: 0, // auto_inc
+            scheduled_at: ScheduleAt::Interval(interval.into()),
+        });
+    }
+
+    log::info!("Vendor system initialized");
+    Ok(())
+}
+
+/// Find a living NPC with vendor settings that the calling player is standing next to
+pub(crate) fn find_vendor_in_range(ctx: &ReducerContext, player: &Player, npc_id: u64) -> Result<Vendor, String> {
+    let npc = ctx.db.npcs().npc_id().find(&npc_id)
+        .ok_or("Merchant not found")?;
+
+    if npc.ai_state == AIState::Dead.to_string() {
+        return Err("That merchant is not trading right now".to_string());
+    }
+
+    let distance = calculate_distance(
+        player.position_x, player.position_y, player.position_z,
+        npc.position_x, npc.position_y, npc.position_z
+    );
+
+    if distance > VENDOR_INTERACTION_RANGE {
+        return Err("Too far away from the merchant".to_string());
+    }
+
+    ctx.db.vendor().npc_id().find(&npc_id)
+        .ok_or_else(|| "That merchant has nothing to trade".to_string())
+}
+
+fn validate_price_multiplier(multiplier: f32) -> Result<(), String> {
+    if !multiplier.is_finite() || multiplier < 0.0 || multiplier > MAX_VENDOR_PRICE_MULTIPLIER {
+        return Err(format!("Price multipliers must be between 0 and {}", MAX_VENDOR_PRICE_MULTIPLIER));
+    }
+    Ok(())
+}
+
+/// Make a merchant NPC a vendor, or change its prices (admin function)
+#[reducer]
+pub fn set_vendor(
+    ctx: &ReducerContext,
+    npc_id: u64,
+    buy_multiplier: f32,
+    sell_multiplier: f32
+) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+
+    ctx.db.npcs().npc_id().find(&npc_id)
+        .filter(|npc| NpcTemplate::find_active(ctx, &npc.npc_type).is_some_and(|template| template.vendor))
+        .ok_or("Only merchant NPCs can be vendors")?;
+
+    validate_price_multiplier(buy_multiplier)?;
+    validate_price_multiplier(sell_multiplier)?;
+
+    // Selling for more than buying would let players print money
+    if sell_multiplier > buy_multiplier {
+        return Err("A vendor cannot pay more for items than it charges".to_string());
+    }
+
+    let vendor = Vendor { npc_id, buy_multiplier, sell_multiplier };
+    if ctx.db.vendor().npc_id().find(&npc_id).is_some() {
+        ctx.db.vendor().npc_id().update(vendor);
+    } else {
+        ctx.db.vendor().insert(vendor);
+    }
+
+    Ok(())
+}
+
+/// Add an item to a vendor's stock or change it (admin function)
+/// Limited stock starts full and refills by restock_amount every restock_interval_seconds
+#[reducer]
+pub fn set_vendor_stock(
+    ctx: &ReducerContext,
+    npc_id: u64,
+    item_id: String,
+    limited: bool,
+    max_quantity: u32,
+    restock_amount: u32,
+    restock_interval_seconds: u64
+) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+
+    ctx.db.vendor().npc_id().find(&npc_id)
+        .ok_or("That NPC is not a vendor")?;
+    ctx.db.game_items().item_id().find(&item_id)
+        .ok_or("Item not found")?;
+
+    if ContentRecord::is_retired(ctx, CONTENT_KIND_ITEM, &item_id) {
+        return Err("That item has been retired".to_string());
+    }
+
+    if limited && (max_quantity == 0 || (restock_amount > 0 && restock_interval_seconds == 0)) {
+        return Err("Limited stock needs a maximum and a restock interval".to_string());
+    }
+
+    let stock = VendorStock {
+        stock_id: 0,
+        npc_id,
+        item_id: item_id.clone(),
+        limited,
+        quantity: max_quantity,
+        max_quantity,
+        restock_amount,
+        restock_interval_seconds,
+        last_restock: ctx.timestamp,
+    };
+
+    match VendorStock::find(ctx, npc_id, &item_id) {
+        Some(existing) => {
+            ctx.db.vendor_stock().stock_id().update(VendorStock {
+                stock_id: existing.stock_id,
+                quantity: existing.quantity.min(max_quantity),
+                ..stock
+            });
+        },
+        None => {
+            ctx.db.vendor_stock().insert(stock);
+        },
+    }
+
+    Ok(())
+}
+
+/// Stop a vendor selling an item (admin function)
+#[reducer]
+pub fn remove_vendor_stock(ctx: &ReducerContext, npc_id: u64, item_id: String) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+
+    let stock = VendorStock::find(ctx, npc_id, &item_id)
+        .ok_or("That vendor does not sell that item")?;
+
+    ctx.db.vendor_stock().stock_id().delete(&stock.stock_id);
+    Ok(())
+}
+
+/// Buy items from a merchant you are standing next to
+#[reducer]
+pub fn buy_from_vendor(ctx: &ReducerContext, npc_id: u64, item_id: String, quantity: u32) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    let vendor = find_vendor_in_range(ctx, &player, npc_id)?;
+
+    if quantity == 0 {
+        return Err("Quantity must be at least 1".to_string());
+    }
+
+    let mut stock = VendorStock::find(ctx, npc_id, &item_id)
+        .ok_or("That merchant does not sell that item")?;
+    let item = ctx.db.game_items().item_id().find(&item_id)
+        .ok_or("Item not found")?;
+
+    if stock.limited {
+        stock.restock(ctx.timestamp);
+        if stock.quantity < quantity {
+            return Err(format!("The merchant only has {} left", stock.quantity));
+        }
+        stock.quantity -= quantity;
+        ctx.db.vendor_stock().stock_id().update(stock);
+    }
+
+    let price = vendor_price(item.value, vendor.buy_multiplier, quantity)?;
+    PlayerWallet::debit(ctx, &player.identity, price)?;
+    add_item_to_inventory(ctx, &player.identity, &item_id, quantity)?;
+
+    log::info!("{} bought {}x {} from vendor {} for {}", player.username, quantity, item_id, npc_id, price);
+    Ok(())
+}
+
+/// Sell items from an inventory stack to a merchant you are standing next to
+#[reducer]
+pub fn sell_to_vendor(ctx: &ReducerContext, npc_id: u64, inventory_id: u64, quantity: u32) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    let vendor = find_vendor_in_range(ctx, &player, npc_id)?;
+
+    if quantity == 0 {
+        return Err("Quantity must be at least 1".to_string());
+    }
+
+    let mut stack = ctx.db.player_inventory().inventory_id().find(&inventory_id)
+        .filter(|stack| stack.player_identity == player.identity)
+        .ok_or("Item not found in your inventory")?;
+
+    if stack.quantity < quantity {
+        return Err("Not enough items in inventory".to_string());
+    }
+
+    let item = ctx.db.game_items().item_id().find(&stack.item_id)
+        .ok_or("Item not found")?;
+
+    let price = vendor_price(item.value, vendor.sell_multiplier, quantity)?;
+    if price == 0 {
+        return Err("The merchant is not interested in that".to_string());
+    }
+
+    // Sold instances are destroyed and never offered for buyback, which would reroll them
+    if let Some(instance_id) = stack.instance_id {
+        let instance = ItemInstance::find_owned(ctx, &player.identity, instance_id)?;
+        if instance.equipped_slot.is_some() {
+            return Err("Unequip the item first".to_string());
+        }
+        ctx.db.player_inventory().inventory_id().delete(&inventory_id);
+        ItemInstance::destroy(ctx, instance_id);
+    } else if stack.quantity == quantity {
+        ctx.db.player_inventory().inventory_id().delete(&inventory_id);
+    } else {
+        stack.quantity -= quantity;
+        ctx.db.player_inventory().inventory_id().update(stack.clone());
+    }
+
+    PlayerWallet::credit(ctx, &player.identity, price)?;
+    if !stack.is_unique() {
+        VendorBuyback::record(ctx, &player.identity, &item.item_id, quantity, price);
+    }
+
+    log::info!("{} sold {}x {} to vendor {} for {}", player.username, quantity, item.item_id, npc_id, price);
+    Ok(())
+}
+
+/// Buy back something you sold, for what the merchant paid you
+#[reducer]
+pub fn buyback_item(ctx: &ReducerContext, npc_id: u64, buyback_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    find_vendor_in_range(ctx, &player, npc_id)?;
+
+    let entry = ctx.db.vendor_buyback().buyback_id().find(&buyback_id)
+        .filter(|entry| entry.player_identity == player.identity)
+        .ok_or("That item is no longer available to buy back")?;
+
+    ctx.db.vendor_buyback().buyback_id().delete(&buyback_id);
+    PlayerWallet::debit(ctx, &player.identity, entry.price)?;
+    add_item_to_inventory(ctx, &player.identity, &entry.item_id, entry.quantity)?;
+
+    Ok(())
+}
+
+/// Refill limited vendor stock
+/// Runs on the vendor_restock_schedule interval
+#[reducer]
+pub fn restock_vendors(ctx: &ReducerContext, _schedule: VendorRestockSchedule) -> Result<(), String> {
+    // Only the scheduler may run this
+    if ctx.sender != ctx.identity() {
+        return Err("restock_vendors may only be invoked by the scheduler".to_string());
+    }
+
+    let due: Vec<VendorStock> = ctx.db.vendor_stock().iter()
+        .filter(|stock| stock.limited && stock.quantity < stock.max_quantity)
+        .collect();
+
+    for mut stock in due {
+        if stock.restock(ctx.timestamp) {
+            ctx.db.vendor_stock().stock_id().update(stock);
+        }
+    }
+
+    Ok(())
+}
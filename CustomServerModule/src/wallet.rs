@@ -0,0 +1,73 @@
+//! Per-character currency wallet
+
+use spacetimedb::{table, reducer, client_visibility_filter, Filter, ReducerContext, Identity, Timestamp, Table};
+use shared_module::*;
+use server_module::*;
+use server_module::utils::validation::validate_admin_permissions;
+
+/// A character's currency balance
+#[derive(Clone, Debug)]
+#[table(name = player_wallet, public)]
+pub struct PlayerWallet {
+    #[primary_key]
+    pub player_identity: Identity,
+    pub balance: u64,
+    pub updated_at: Timestamp,
+}
+
+/// Players only see their own wallet
+#[client_visibility_filter]
+const PLAYER_WALLET_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM player_wallet WHERE player_identity = :sender"
+);
+
+impl PlayerWallet {
+    /// Current balance; players without a wallet row have nothing
+    pub fn balance(ctx: &ReducerContext, identity: &Identity) -> u64 {
+        ctx.db.player_wallet().player_identity().find(identity)
+            .map(|wallet| wallet.balance)
+            .unwrap_or(0)
+    }
+
+    /// Add currency to a wallet, returning the new balance
+    pub fn credit(ctx: &ReducerContext, identity: &Identity, amount: u64) -> Result<u64, String> {
+        let balance = add_currency(Self::balance(ctx, identity), amount)?;
+        Self::save(ctx, identity, balance);
+        Ok(balance)
+    }
+
+    /// Take currency from a wallet, returning the new balance
+    pub fn debit(ctx: &ReducerContext, identity: &Identity, amount: u64) -> Result<u64, String> {
+        let balance = subtract_currency(Self::balance(ctx, identity), amount)?;
+        Self::save(ctx, identity, balance);
+        Ok(balance)
+    }
+
+    fn save(ctx: &ReducerContext, identity: &Identity, balance: u64) {
+        let wallet = PlayerWallet {
+            player_identity: *identity,
+            balance,
+            updated_at: ctx.timestamp,
+        };
+
+        if ctx.db.player_wallet().player_identity().find(identity).is_some() {
+            ctx.db.player_wallet().player_identity().update(wallet);
+        } else {
+            ctx.db.player_wallet().insert(wallet);
+        }
+    }
+}
+
+/// Give currency to a player (admin function)
+#[reducer]
+pub fn grant_currency(ctx: &ReducerContext, target_username: String, amount: u64) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+
+    let target = User::filter_by_username(ctx, &target_username)
+        .ok_or("User not found")?;
+
+    let balance = PlayerWallet::credit(ctx, &target.identity, amount)?;
+
+    log::info!("Granted {} currency to {} (balance {})", amount, target.username, balance);
+    Ok(())
+}
@@ -2,8 +2,12 @@
 
 use spacetimedb::{reducer, ReducerContext};
 use shared_module::*;
+use server_module::Player;
 use crate::*;
 
+/// Every biome determine_biome_type can produce
+pub const BIOME_TYPES: [&str; 4] = ["grasslands", "forest", "mountains", "desert"];
+
 /// Initialize the world generation system
 pub fn initialize_world_generator(_ctx: &ReducerContext) -> Result<(), String> {
     log::info!("World generation system initialized");
@@ -19,10 +23,7 @@ pub fn generate_world_chunk(
     z: i32
 ) -> Result<u64, String> {
     // Check if chunk already exists
-    let existing_chunk = ctx.db.world_chunks().iter()
-        .find(|chunk| chunk.x == x && chunk.y == y && chunk.z == z);
-    
-    if existing_chunk.is_some() {
+    if WorldChunk::find_at(ctx, x, y, z).is_some() {
         return Err("Chunk already exists".to_string());
     }
     
@@ -41,15 +42,43 @@ pub fn generate_world_chunk(
         x,
         y,
         z,
-        biome_type,
+        biome_type: biome_type.clone(),
         generated: true,
         data_compressed: chunk_data,
     });
     
     log::info!("Generated world chunk at ({}, {}, {})", x, y, z);
+    
     Ok(chunk_id)
 }
 
+impl WorldChunk {
+    /// The chunk at the given chunk coordinates, if it has been generated
+    pub fn find_at(ctx: &ReducerContext, x: i32, y: i32, z: i32) -> Option<WorldChunk> {
+        ctx.db.world_chunks().chunk_coords().filter((x, y, z)).next()
+    }
+
+    /// The chunk containing a world position, if it has been generated
+    pub fn containing(ctx: &ReducerContext, x: f32, y: f32, z: f32) -> Option<WorldChunk> {
+        let to_chunk = |coordinate: f32| (coordinate / WORLD_CHUNK_SIZE).floor() as i32;
+        Self::find_at(ctx, to_chunk(x), to_chunk(y), to_chunk(z))
+    }
+}
+
+/// Record a biome discovery when a player steps into a chunk of a biome they have not seen
+/// ServerModule runs this after every move and zone change
+fn discover_biome_on_move(ctx: &ReducerContext, player: &Player) {
+    let Some(chunk) = WorldChunk::containing(ctx, player.position_x, player.position_y, player.position_z) else {
+        return;
+    };
+
+    if !PlayerBiomeDiscovery::has_discovered(ctx, &player.identity, &chunk.biome_type) {
+        record_achievement_event(ctx, &player.identity, AchievementEvent::BiomeDiscovered { biome_type: chunk.biome_type });
+    }
+}
+
+server_module::register_player_moved_hook!(discover_biome_on_move);
+
 /// Determine biome type based on coordinates
 fn determine_biome_type(x: i32, y: i32, _z: i32) -> String {
     // Simple biome generation based on distance from origin
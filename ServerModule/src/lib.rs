@@ -18,8 +18,17 @@ pub use reducers::*;
 
 /// Initialize the server when the SpacetimeDB module starts
 #[reducer(init)]
-pub fn initialize_server(_ctx: &ReducerContext) {
+pub fn initialize_server(ctx: &ReducerContext) {
     log::info!("MMO Server initializing...");
+    
+    // Whoever publishes the module becomes the first admin
+    StaffMember::grant(ctx, ctx.sender, StaffRole::Admin, ctx.sender);
+    
+    // Default chat retention policies and the job that applies them
+    // Both are idempotent and also run on client_connected for upgraded databases
+    ChatRetentionPolicy::seed_defaults(ctx);
+    ChatRetentionSchedule::ensure_scheduled(ctx);
+    
     log::info!("MMO Server initialized successfully!");
 }
 
@@ -6,7 +6,7 @@ use crate::tables::*;
 use sha2::{Sha256, Digest};
 
 /// WASM-compatible password hashing using SHA256
-fn hash_password(password: &str, salt: &str) -> String {
+pub(crate) fn hash_password(password: &str, salt: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(password.as_bytes());
     hasher.update(salt.as_bytes());
@@ -17,13 +17,13 @@ fn hash_password(password: &str, salt: &str) -> String {
 }
 
 /// Verify password against stored hash and salt
-fn verify_password(password: &str, stored_hash: &str, salt: &str) -> bool {
+pub(crate) fn verify_password(password: &str, stored_hash: &str, salt: &str) -> bool {
     let computed_hash = hash_password(password, salt);
     computed_hash == stored_hash
 }
 
 /// Generate a salt using the user's identity and current timestamp
-fn generate_salt(identity: &spacetimedb::Identity, timestamp: spacetimedb::Timestamp) -> String {
+pub(crate) fn generate_salt(identity: &spacetimedb::Identity, timestamp: spacetimedb::Timestamp) -> String {
     let mut hasher = Sha256::new();
     
     // Use string representations for hashing since direct byte access isn't available
@@ -3,68 +3,102 @@
 use spacetimedb::{reducer, ReducerContext};
 use shared_module::*;
 use crate::tables::*;
+use crate::utils::validation::{validate_admin_permissions, validate_chat_channel_access};
+use crate::utils::cleanup::cleanup_chat_spam_state;
+use crate::utils::anti_spam::{check_chat_channel_restrictions, check_chat_mute, check_chat_spam, consume_chat_slow_mode, SpamVerdict};
+use crate::reducers::chat_commands::execute_chat_command;
+
+/// Sanitize a message and run it through the configured word filters
+fn screen_chat_message(ctx: &ReducerContext, message: &str) -> Result<WordFilterOutcome, String> {
+    let sanitized_message = sanitize_chat_message(message)?;
+    apply_word_filters(&sanitized_message, &ChatWordFilter::get_rules(ctx))
+}
 
 /// Send a chat message
+/// Input starting with '/' is run as a chat command; "//" posts a literal slash
 #[reducer]
 pub fn send_chat_message(
     ctx: &ReducerContext,
     message: String,
     channel: String
 ) -> Result<(), String> {
-    // Verify player is online
-    let player = Player::filter_by_identity(ctx, &ctx.sender)
-        .ok_or("Must be in game to send messages")?;
+    let trimmed = message.trim_start();
     
-    if !player.is_online {
-        return Err("Must be online to send messages".to_string());
+    if let Some(escaped) = trimmed.strip_prefix("//") {
+        return post_chat_message(ctx, &format!("/{}", escaped), &channel);
     }
     
-    // Sanitize and validate message content using shared utility
-    let sanitized_message = sanitize_chat_message(&message)?;
+    if let Some(command_line) = trimmed.strip_prefix('/') {
+        return execute_chat_command(ctx, command_line, &channel);
+    }
     
-    // Validate channel
-    let valid_channels = ["global", "zone", "guild", "party", "whisper"];
-    if !valid_channels.contains(&channel.as_str()) {
-        return Err("Invalid chat channel".to_string());
+    post_chat_message(ctx, &message, &channel)
+}
+
+/// Post a message to a channel as the calling player
+/// Shared by send_chat_message and the channel shortcut commands (/p, /g, ...)
+pub fn post_chat_message(
+    ctx: &ReducerContext,
+    message: &str,
+    channel: &str
+) -> Result<(), String> {
+    // Channel names are case-insensitive
+    let channel = channel.trim().to_lowercase();
+    
+    // Verify player is online and allowed to use this channel
+    let (player, scope) = validate_chat_channel_access(ctx, &channel)?;
+    if channel == "system" {
+        return Err("Only the server can post to the system channel".to_string());
+    }
+    check_chat_mute(ctx, &ctx.sender, &channel)?;
+    check_chat_channel_restrictions(ctx, &ctx.sender, &channel)?;
+    
+    // Sanitize and filter message content
+    let screened = screen_chat_message(ctx, message)?;
+    
+    // Spam is dropped rather than failed so the violation record is kept
+    if let SpamVerdict::Rejected(reason) = check_chat_spam(ctx, &ctx.sender, &channel, &screened.message) {
+        PlayerNotification::send(ctx, ctx.sender, "chat", reason);
+        return Ok(());
     }
+    consume_chat_slow_mode(ctx, &ctx.sender, &channel);
     
-    // Generate unique message ID
-    let message_id = generate_unique_id(&ctx.sender, ctx.timestamp);
+    // Group-scoped channels carry the party or guild they are addressed to
+    let group_id = match scope {
+        ChatScope::Group(group_id) => group_id,
+        _ => 0,
+    };
     
     // Create the message
-    ChatMessage::create_message(
+    let chat_message = ChatMessage::create_message(
         ctx,
-        message_id,
         ctx.sender,
         player.username,
-        sanitized_message,
-        channel,
+        screened.message.clone(),
+        channel.clone(),
+        player.current_zone,
+        group_id,
         ctx.timestamp
     );
     
+    if !screened.flagged_patterns.is_empty() {
+        ChatFlag::record(
+            ctx,
+            ctx.sender,
+            channel,
+            chat_message.message_id,
+            screened.message,
+            &screened.flagged_patterns,
+            ctx.timestamp
+        );
+    }
+    
     // Update session activity
     GameSession::update_activity(ctx, &ctx.sender, ctx.timestamp);
     
     Ok(())
 }
 
-/// Get recent messages from a channel
-#[reducer]
-pub fn get_recent_messages(
-    ctx: &ReducerContext,
-    channel: String,
-    limit: u32
-) -> Result<Vec<ChatMessage>, String> {
-    // Verify player exists
-    Player::filter_by_identity(ctx, &ctx.sender)
-        .ok_or("Player not found")?;
-    
-    let limit = (limit as usize).min(MAX_CHAT_HISTORY); // Cap at max history
-    
-    let messages = ChatMessage::get_recent_messages(ctx, &channel, limit);
-    Ok(messages)
-}
-
 /// Send a whisper (private message) to another player
 #[reducer]
 pub fn send_whisper(
@@ -81,70 +115,154 @@ pub fn send_whisper(
     }
     
     // Find target player
-    let _target = ctx.db.game_players().iter()
+    let target = ctx.db.game_players().iter()
         .find(|p| p.username == target_username && p.is_online)
         .ok_or("Target player not found or offline")?;
     
-    // Sanitize message
-    let sanitized_message = sanitize_chat_message(&message)?;
+    if target.identity == sender.identity {
+        return Err("Cannot whisper yourself".to_string());
+    }
+    
+    check_chat_mute(ctx, &ctx.sender, "whisper")?;
+    check_chat_channel_restrictions(ctx, &ctx.sender, "whisper")?;
+    
+    // Sanitize and filter message content
+    let screened = screen_chat_message(ctx, &message)?;
+    
+    // Spam is dropped rather than failed so the violation record is kept
+    if let SpamVerdict::Rejected(reason) = check_chat_spam(ctx, &ctx.sender, "whisper", &screened.message) {
+        PlayerNotification::send(ctx, ctx.sender, "chat", reason);
+        return Ok(());
+    }
+    consume_chat_slow_mode(ctx, &ctx.sender, "whisper");
     
-    // Create the whisper message with special channel format
-    let whisper_channel = format!("whisper:{}:{}", sender.username, target_username);
-    let message_id = generate_unique_id(&ctx.sender, ctx.timestamp);
+    // Whispers to someone ignoring the sender vanish without telling either side
+    if IgnoredPlayer::is_ignoring(ctx, &target.identity, &sender.identity) {
+        return Ok(());
+    }
     
-    ChatMessage::create_message(
+    // Store privately and deliver only to the two participants
+    let whisper = DirectMessage::send(
         ctx,
-        message_id,
-        ctx.sender,
+        sender.identity,
         sender.username,
-        sanitized_message,
-        whisper_channel,
+        target.identity,
+        target.username,
+        screened.message,
         ctx.timestamp
     );
     
+    if !screened.flagged_patterns.is_empty() {
+        ChatFlag::record(
+            ctx,
+            ctx.sender,
+            "whisper".to_string(),
+            whisper.message_id,
+            whisper.message,
+            &screened.flagged_patterns,
+            ctx.timestamp
+        );
+    }
+    
     // Update session activity
     GameSession::update_activity(ctx, &ctx.sender, ctx.timestamp);
     
     Ok(())
 }
 
-/// Clean up old chat messages
+/// Apply the chat retention policies now (admin function)
 #[reducer]
 pub fn cleanup_chat_messages(ctx: &ReducerContext) -> Result<(), String> {
-    // Only allow this to be called by system/admin
-    // In a real implementation, you'd check for admin privileges
+    validate_admin_permissions(ctx)?;
+    
+    let archived = ChatMessage::enforce_retention(ctx);
+    
+    log::info!("Chat message cleanup completed: {} archived", archived);
+    Ok(())
+}
+
+/// Apply the chat retention policies
+/// Runs on the chat_retention_schedule interval
+#[reducer]
+pub fn run_chat_retention(ctx: &ReducerContext, _schedule: ChatRetentionSchedule) -> Result<(), String> {
+    // Only the scheduler may run this
+    if ctx.sender != ctx.identity() {
+        return Err("run_chat_retention may only be invoked by the scheduler".to_string());
+    }
     
-    ChatMessage::cleanup_old_messages(ctx, MAX_CHAT_HISTORY);
+    let archived = ChatMessage::enforce_retention(ctx);
+    if archived > 0 {
+        log::info!("Archived {} chat messages past retention", archived);
+    }
+    
+    // Anti-spam state is swept on the same schedule
+    cleanup_chat_spam_state(ctx);
     
-    log::info!("Chat message cleanup completed");
     Ok(())
 }
 
-/// Get whisper conversation between two players
+/// Set how long and how many messages a channel type keeps (admin function)
+/// Channel types: global, zone, system, party, guild, custom; 0 disables a limit
 #[reducer]
-pub fn get_whisper_conversation(
+pub fn set_chat_retention_policy(
     ctx: &ReducerContext,
-    other_username: String,
-    limit: u32
-) -> Result<Vec<ChatMessage>, String> {
-    // Verify requesting player exists
-    let player = Player::filter_by_identity(ctx, &ctx.sender)
-        .ok_or("Player not found")?;
-    
-    let limit = (limit as usize).min(50); // Cap whisper history
-    
-    // Find messages in both directions
-    let channel1 = format!("whisper:{}:{}", player.username, other_username);
-    let channel2 = format!("whisper:{}:{}", other_username, player.username);
-    
-    let mut messages: Vec<ChatMessage> = ctx.db.chatmessage().iter()
-        .filter(|msg| msg.channel == channel1 || msg.channel == channel2)
-        .cloned()
-        .collect();
-    
-    // Sort by timestamp (newest first)
-    messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-    messages.truncate(limit);
-    
-    Ok(messages)
-}
\ No newline at end of file
+    channel_type: String,
+    max_age_seconds: u64,
+    max_count: u32
+) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+    
+    let channel_type = ChatChannelType::from_string(&channel_type.trim().to_lowercase())
+        .ok_or("Channel type must be one of: global, zone, system, party, guild, custom")?;
+    
+    ChatRetentionPolicy::set(ctx, &channel_type, max_age_seconds, max_count);
+    
+    log::info!(
+        "Chat retention for {}: {}s, {} messages",
+        channel_type.to_string(),
+        max_age_seconds,
+        max_count
+    );
+    Ok(())
+}
+
+/// Add or update a chat word filter rule (admin function)
+#[reducer]
+pub fn set_chat_word_filter(
+    ctx: &ReducerContext,
+    pattern: String,
+    action: String
+) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+    
+    let action = WordFilterAction::from_string(&action)
+        .ok_or("Filter action must be one of: mask, block, flag")?;
+    
+    // Store patterns in the same form messages are compared in
+    let pattern = sanitize_chat_message(&pattern)?.to_lowercase();
+    if pattern.chars().count() > MAX_WORD_FILTER_PATTERN_LENGTH {
+        return Err("Filter pattern too long".to_string());
+    }
+    
+    ChatWordFilter::upsert_rule(ctx, pattern.clone(), action, ctx.sender, ctx.timestamp);
+    
+    log::info!("Chat word filter set: '{}' -> {}", pattern, action.to_string());
+    Ok(())
+}
+
+/// Remove a chat word filter rule (admin function)
+#[reducer]
+pub fn remove_chat_word_filter(
+    ctx: &ReducerContext,
+    pattern: String
+) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+    
+    let pattern = sanitize_chat_message(&pattern)?.to_lowercase();
+    if !ChatWordFilter::remove_rule(ctx, &pattern) {
+        return Err("Filter rule not found".to_string());
+    }
+    
+    log::info!("Chat word filter removed: '{}'", pattern);
+    Ok(())
+}
@@ -0,0 +1,293 @@
+//! User-created chat channel reducers
+
+use spacetimedb::{reducer, ReducerContext, Table};
+use shared_module::*;
+use crate::tables::*;
+use crate::reducers::auth::{generate_salt, hash_password, verify_password};
+use crate::utils::validation::{validate_player_in_game, validate_rate_limit};
+
+/// Longest mute a channel moderator can hand out (7 days)
+const MAX_CHANNEL_MUTE_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Look up a channel and the calling player's membership in it
+fn find_channel_membership(
+    ctx: &ReducerContext,
+    name: &str
+) -> Result<(ChatChannel, ChatChannelMember), String> {
+    let channel = ChatChannel::filter_by_name(ctx, &name.trim().to_lowercase())
+        .ok_or("Channel not found")?;
+    
+    let membership = ChatChannelMember::find(ctx, channel.channel_id, &ctx.sender)
+        .ok_or("You are not a member of this channel")?;
+    
+    Ok((channel, membership))
+}
+
+/// Look up another member of a channel by username
+fn find_target_membership(
+    ctx: &ReducerContext,
+    channel: &ChatChannel,
+    target_username: &str
+) -> Result<ChatChannelMember, String> {
+    let target = User::filter_by_username(ctx, target_username)
+        .ok_or("User not found")?;
+    
+    ChatChannelMember::find(ctx, channel.channel_id, &target.identity)
+        .ok_or_else(|| "That player is not a member of this channel".to_string())
+}
+
+/// Create a new chat channel, optionally protected by a password
+#[reducer]
+pub fn create_chat_channel(
+    ctx: &ReducerContext,
+    name: String,
+    password: Option<String>
+) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    validate_rate_limit(ctx, "create_chat_channel")?;
+    
+    let name = validate_chat_channel_name(&name)?;
+    
+    if ChatChannel::filter_by_name(ctx, &name).is_some() {
+        return Err("A channel with that name already exists".to_string());
+    }
+    
+    if ChatChannelMember::count_for_player(ctx, &player.identity) >= MAX_CHAT_CHANNELS_PER_PLAYER {
+        return Err(format!("You cannot be in more than {} channels", MAX_CHAT_CHANNELS_PER_PLAYER));
+    }
+    
+    let (password_hash, password_salt) = match password.filter(|p| !p.is_empty()) {
+        Some(password) => {
+            let salt = generate_salt(&ctx.sender, ctx.timestamp);
+            (hash_password(&password, &salt), salt)
+        },
+        None => (String::new(), String::new()),
+    };
+    
+    let channel = ctx.db.chat_channel().insert(ChatChannel {
+        channel_id: 0, // auto_inc
+        name: name.clone(),
+        owner_identity: player.identity,
+        password_hash,
+        password_salt,
+        created_at: ctx.timestamp,
+    });
+    
+    ChatChannelMember::add_member(ctx, &channel, player.identity, player.username.clone(), ChannelRole::Owner);
+    
+    log::info!("Player {} created chat channel '{}'", player.username, name);
+    Ok(())
+}
+
+/// Join a chat channel (invited players skip the password)
+#[reducer]
+pub fn join_chat_channel(
+    ctx: &ReducerContext,
+    name: String,
+    password: Option<String>
+) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    validate_rate_limit(ctx, "join_chat_channel")?;
+    
+    let channel = ChatChannel::filter_by_name(ctx, &name.trim().to_lowercase())
+        .ok_or("Channel not found")?;
+    
+    if ChatChannelMember::find(ctx, channel.channel_id, &player.identity).is_some() {
+        return Err("You are already in this channel".to_string());
+    }
+    
+    if ChatChannelMember::get_members(ctx, channel.channel_id).len() >= MAX_CHAT_CHANNEL_MEMBERS {
+        return Err("This channel is full".to_string());
+    }
+    
+    if ChatChannelMember::count_for_player(ctx, &player.identity) >= MAX_CHAT_CHANNELS_PER_PLAYER {
+        return Err(format!("You cannot be in more than {} channels", MAX_CHAT_CHANNELS_PER_PLAYER));
+    }
+    
+    let invite = ChatChannelInvite::find(ctx, channel.channel_id, &player.identity);
+    
+    if invite.is_none() && !channel.password_hash.is_empty() {
+        let password = password.unwrap_or_default();
+        if !verify_password(&password, &channel.password_hash, &channel.password_salt) {
+            return Err("Incorrect channel password".to_string());
+        }
+    }
+    
+    if let Some(invite) = invite {
+        ctx.db.chat_channel_invite().invite_id().delete(&invite.invite_id);
+    }
+    
+    ChatChannelMember::add_member(ctx, &channel, player.identity, player.username.clone(), ChannelRole::Member);
+    
+    log::info!("Player {} joined chat channel '{}'", player.username, channel.name);
+    Ok(())
+}
+
+/// Leave a chat channel; ownership passes on, and an empty channel is deleted
+#[reducer]
+pub fn leave_chat_channel(ctx: &ReducerContext, name: String) -> Result<(), String> {
+    let (channel, membership) = find_channel_membership(ctx, &name)?;
+    
+    ctx.db.chat_channel_member().membership_id().delete(&membership.membership_id);
+    
+    if membership.role() == ChannelRole::Owner {
+        // Prefer the longest-serving moderator, then the longest-serving member
+        let successor = ChatChannelMember::get_members(ctx, channel.channel_id)
+            .into_iter()
+            .min_by_key(|member| (member.role() != ChannelRole::Moderator, member.joined_at));
+        
+        match successor {
+            Some(mut successor) => {
+                successor.role = ChannelRole::Owner.to_string();
+                let mut updated_channel = channel.clone();
+                updated_channel.owner_identity = successor.member_identity;
+                
+                log::info!("Chat channel '{}' ownership passed to {}", channel.name, successor.member_username);
+                ctx.db.chat_channel().channel_id().update(updated_channel);
+                ctx.db.chat_channel_member().membership_id().update(successor);
+            },
+            None => {
+                ChatChannel::delete_channel(ctx, &channel);
+                log::info!("Chat channel '{}' deleted after its last member left", channel.name);
+            },
+        }
+    }
+    
+    Ok(())
+}
+
+/// Invite a player to a chat channel (owner and moderators)
+#[reducer]
+pub fn invite_to_chat_channel(
+    ctx: &ReducerContext,
+    name: String,
+    target_username: String
+) -> Result<(), String> {
+    let (channel, membership) = find_channel_membership(ctx, &name)?;
+    validate_rate_limit(ctx, "invite_to_chat_channel")?;
+    
+    if !membership.role().can_moderate() {
+        return Err("Only channel moderators can invite players".to_string());
+    }
+    
+    let target = User::filter_by_username(ctx, &target_username)
+        .ok_or("User not found")?;
+    
+    if ChatChannelMember::find(ctx, channel.channel_id, &target.identity).is_some() {
+        return Err("That player is already in this channel".to_string());
+    }
+    
+    if ChatChannelInvite::find(ctx, channel.channel_id, &target.identity).is_some() {
+        return Err("That player has already been invited".to_string());
+    }
+    
+    ctx.db.chat_channel_invite().insert(ChatChannelInvite {
+        invite_id: 0, // auto_inc
+        channel_id: channel.channel_id,
+        channel_name: channel.name.clone(),
+        invitee_identity: target.identity,
+        invited_by: ctx.sender,
+        created_at: ctx.timestamp,
+    });
+    
+    PlayerNotification::send(
+        ctx,
+        target.identity,
+        "chat",
+        format!("{} invited you to join channel '{}'", membership.member_username, channel.name)
+    );
+    
+    Ok(())
+}
+
+/// Remove a player from a chat channel (owner and moderators)
+#[reducer]
+pub fn kick_from_chat_channel(
+    ctx: &ReducerContext,
+    name: String,
+    target_username: String
+) -> Result<(), String> {
+    let (channel, membership) = find_channel_membership(ctx, &name)?;
+    let target = find_target_membership(ctx, &channel, &target_username)?;
+    
+    if !membership.role().can_moderate() || !membership.role().outranks(&target.role()) {
+        return Err("You cannot kick that player".to_string());
+    }
+    
+    ctx.db.chat_channel_member().membership_id().delete(&target.membership_id);
+    
+    PlayerNotification::send(
+        ctx,
+        target.member_identity,
+        "chat",
+        format!("You were removed from channel '{}'", channel.name)
+    );
+    
+    log::info!("{} kicked {} from chat channel '{}'", membership.member_username, target.member_username, channel.name);
+    Ok(())
+}
+
+/// Mute a player in a chat channel for a number of seconds (owner and moderators)
+#[reducer]
+pub fn mute_in_chat_channel(
+    ctx: &ReducerContext,
+    name: String,
+    target_username: String,
+    duration_seconds: u64
+) -> Result<(), String> {
+    let (channel, membership) = find_channel_membership(ctx, &name)?;
+    let target = find_target_membership(ctx, &channel, &target_username)?;
+    
+    if !membership.role().can_moderate() || !membership.role().outranks(&target.role()) {
+        return Err("You cannot mute that player".to_string());
+    }
+    
+    if duration_seconds == 0 || duration_seconds > MAX_CHANNEL_MUTE_SECONDS {
+        return Err(format!("Mute duration must be between 1 and {} seconds", MAX_CHANNEL_MUTE_SECONDS));
+    }
+    
+    let muted_until = ctx.timestamp + std::time::Duration::from_secs(duration_seconds);
+    ChatMute::create_mute(
+        ctx,
+        target.member_identity,
+        channel.name.clone(),
+        muted_until,
+        format!("Muted by channel moderator {}", membership.member_username),
+        ctx.sender
+    );
+    
+    PlayerNotification::send(
+        ctx,
+        target.member_identity,
+        "chat",
+        format!("You were muted in channel '{}' for {} seconds", channel.name, duration_seconds)
+    );
+    
+    Ok(())
+}
+
+/// Promote a member to moderator or demote them back (owner only)
+#[reducer]
+pub fn set_chat_channel_moderator(
+    ctx: &ReducerContext,
+    name: String,
+    target_username: String,
+    is_moderator: bool
+) -> Result<(), String> {
+    let (channel, membership) = find_channel_membership(ctx, &name)?;
+    let mut target = find_target_membership(ctx, &channel, &target_username)?;
+    
+    if membership.role() != ChannelRole::Owner {
+        return Err("Only the channel owner can change moderators".to_string());
+    }
+    
+    if target.role() == ChannelRole::Owner {
+        return Err("The owner's role cannot be changed".to_string());
+    }
+    
+    let new_role = if is_moderator { ChannelRole::Moderator } else { ChannelRole::Member };
+    target.role = new_role.to_string();
+    ctx.db.chat_channel_member().membership_id().update(target);
+    
+    Ok(())
+}
@@ -0,0 +1,353 @@
+//! Slash-command layer for chat input
+//!
+//! `send_chat_message` hands anything starting with '/' to `execute_chat_command`,
+//! which looks the command up in `CHAT_COMMANDS`, validates the argument count
+//! and runs its handler. Other modules add commands with `register_chat_commands!`.
+
+use std::sync::Mutex;
+use spacetimedb::rand::Rng;
+use spacetimedb::ReducerContext;
+use crate::tables::*;
+use crate::reducers::chat::{post_chat_message, send_whisper};
+use crate::reducers::friend::{ignore_player, unignore_player};
+use crate::utils::validation::validate_player_in_game;
+
+/// Most names listed by /who
+const MAX_WHO_RESULTS: usize = 50;
+
+/// Largest range accepted by /roll
+const MAX_ROLL: u32 = 1_000_000;
+
+/// What a command handler knows about the call
+pub struct ChatCommandContext {
+    /// The player who typed the command
+    pub player: Player,
+    /// The channel the command was typed in
+    pub channel: String,
+}
+
+/// Signature every command handler implements
+pub type ChatCommandHandler = fn(&ReducerContext, &ChatCommandContext, &[&str]) -> Result<(), String>;
+
+/// A chat command definition
+#[derive(Clone)]
+pub struct ChatCommand {
+    /// Name typed after the slash, lowercase
+    pub name: &'static str,
+    /// Alternative names (e.g. "w" for "whisper")
+    pub aliases: &'static [&'static str],
+    /// Argument synopsis shown by /help, e.g. "<player> <message>"
+    pub usage: &'static str,
+    /// One-line description shown by /help
+    pub help: &'static str,
+    /// Fewest arguments the command accepts
+    pub min_args: usize,
+    /// Most arguments the command accepts; the last one keeps the rest of the line
+    pub max_args: usize,
+    pub handler: ChatCommandHandler,
+}
+
+impl ChatCommand {
+    fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+
+    fn usage_line(&self) -> String {
+        if self.usage.is_empty() {
+            format!("/{}", self.name)
+        } else {
+            format!("/{} {}", self.name, self.usage)
+        }
+    }
+}
+
+/// Command tables added by other modules (e.g. CustomServerModule)
+/// Filled by the preinit hook that register_chat_commands! exports, which the host
+/// runs every time a module instance starts, so a restarted instance has them too
+static REGISTERED_CHAT_COMMANDS: Mutex<Vec<&'static [ChatCommand]>> = Mutex::new(Vec::new());
+
+/// Add a table of commands; called from the hook register_chat_commands! exports
+/// A name or alias that is already taken keeps resolving to the existing command
+pub fn register_chat_command_table(commands: &'static [ChatCommand]) {
+    for command in commands {
+        if find_command(command.name).is_some() {
+            log::warn!("Chat command '/{}' is already defined; keeping the existing one", command.name);
+        }
+    }
+    REGISTERED_CHAT_COMMANDS.lock().unwrap().push(commands);
+}
+
+/// Register a module's own chat commands with the parser
+/// Takes the name of a `&'static [ChatCommand]` const in scope and exports a preinit hook for it
+#[macro_export]
+macro_rules! register_chat_commands {
+    ($commands:ident) => {
+        const _: () = {
+            #[export_name = concat!("__preinit__30_register_chat_commands_", stringify!($commands))]
+            extern "C" fn __register_chat_commands() {
+                $crate::reducers::chat_commands::register_chat_command_table($commands);
+            }
+        };
+    };
+}
+
+/// Every command: the built-ins, then those other modules registered
+fn all_commands() -> Vec<&'static ChatCommand> {
+    let registered = REGISTERED_CHAT_COMMANDS.lock().unwrap();
+    CHAT_COMMANDS.iter()
+        .chain(registered.iter().flat_map(|commands| commands.iter()))
+        .collect()
+}
+
+/// Find a command by name or alias
+fn find_command(name: &str) -> Option<&'static ChatCommand> {
+    all_commands().into_iter().find(|command| command.matches(name))
+}
+
+/// Split command arguments on whitespace; the last allowed argument keeps the rest of the line
+pub fn split_command_args(input: &str, max_args: usize) -> Vec<&str> {
+    let mut args = Vec::new();
+    let mut rest = input.trim();
+
+    while !rest.is_empty() {
+        if args.len() + 1 == max_args {
+            args.push(rest);
+            break;
+        }
+
+        match rest.find(char::is_whitespace) {
+            Some(end) => {
+                args.push(&rest[..end]);
+                rest = rest[end..].trim_start();
+            },
+            None => {
+                args.push(rest);
+                break;
+            },
+        }
+    }
+
+    args
+}
+
+/// Parse and run a command line (the text after the leading '/')
+pub fn execute_chat_command(
+    ctx: &ReducerContext,
+    command_line: &str,
+    channel: &str
+) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+
+    let command_line = command_line.trim();
+    let (name, arg_text) = match command_line.find(char::is_whitespace) {
+        Some(end) => (&command_line[..end], &command_line[end..]),
+        None => (command_line, ""),
+    };
+    let name = name.to_lowercase();
+
+    let command = find_command(&name)
+        .ok_or_else(|| format!("Unknown command '/{}'. Type /help for a list of commands", name))?;
+
+    // Allow one extra split so we can tell "too many arguments" apart
+    let args = split_command_args(arg_text, command.max_args + 1);
+    if args.len() < command.min_args || args.len() > command.max_args {
+        return Err(format!("Usage: {}", command.usage_line()));
+    }
+
+    let command_ctx = ChatCommandContext {
+        player,
+        channel: channel.trim().to_lowercase(),
+    };
+
+    (command.handler)(ctx, &command_ctx, &args)
+}
+
+/// Built-in chat commands
+const CHAT_COMMANDS: &[ChatCommand] = &[
+    ChatCommand {
+        name: "help",
+        aliases: &["?"],
+        usage: "[command]",
+        help: "List commands, or show how to use one",
+        min_args: 0,
+        max_args: 1,
+        handler: command_help,
+    },
+    ChatCommand {
+        name: "whisper",
+        aliases: &["w", "tell", "t"],
+        usage: "<player> <message>",
+        help: "Send a private message",
+        min_args: 2,
+        max_args: 2,
+        handler: command_whisper,
+    },
+    ChatCommand {
+        name: "party",
+        aliases: &["p"],
+        usage: "<message>",
+        help: "Talk to your party",
+        min_args: 1,
+        max_args: 1,
+        handler: command_party,
+    },
+    ChatCommand {
+        name: "guild",
+        aliases: &["g"],
+        usage: "<message>",
+        help: "Talk to your guild",
+        min_args: 1,
+        max_args: 1,
+        handler: command_guild,
+    },
+    ChatCommand {
+        name: "zone",
+        aliases: &["z"],
+        usage: "<message>",
+        help: "Talk to everyone in your zone",
+        min_args: 1,
+        max_args: 1,
+        handler: command_zone,
+    },
+    ChatCommand {
+        name: "roll",
+        aliases: &["random"],
+        usage: "[max]",
+        help: "Roll a random number from 1 to max (default 100)",
+        min_args: 0,
+        max_args: 1,
+        handler: command_roll,
+    },
+    ChatCommand {
+        name: "who",
+        aliases: &[],
+        usage: "[zone]",
+        help: "List online players in your zone or another zone",
+        min_args: 0,
+        max_args: 1,
+        handler: command_who,
+    },
+    ChatCommand {
+        name: "ignore",
+        aliases: &[],
+        usage: "[player]",
+        help: "Ignore a player, or list who you are ignoring",
+        min_args: 0,
+        max_args: 1,
+        handler: command_ignore,
+    },
+    ChatCommand {
+        name: "unignore",
+        aliases: &[],
+        usage: "<player>",
+        help: "Stop ignoring a player",
+        min_args: 1,
+        max_args: 1,
+        handler: command_unignore,
+    },
+];
+
+fn command_help(ctx: &ReducerContext, command_ctx: &ChatCommandContext, args: &[&str]) -> Result<(), String> {
+    let text = match args.first() {
+        Some(name) => {
+            let name = name.trim_start_matches('/').to_lowercase();
+            let command = find_command(&name)
+                .ok_or_else(|| format!("Unknown command '/{}'", name))?;
+            format!("{} - {}", command.usage_line(), command.help)
+        },
+        None => {
+            let names: Vec<String> = all_commands().into_iter()
+                .map(|command| format!("/{}", command.name))
+                .collect();
+            format!("Commands: {}. Type /help <command> for details", names.join(", "))
+        },
+    };
+
+    PlayerNotification::send(ctx, command_ctx.player.identity, "command", text);
+    Ok(())
+}
+
+fn command_whisper(ctx: &ReducerContext, _command_ctx: &ChatCommandContext, args: &[&str]) -> Result<(), String> {
+    send_whisper(ctx, args[0].to_string(), args[1].to_string())
+}
+
+fn command_party(ctx: &ReducerContext, _command_ctx: &ChatCommandContext, args: &[&str]) -> Result<(), String> {
+    post_chat_message(ctx, args[0], "party")
+}
+
+fn command_guild(ctx: &ReducerContext, _command_ctx: &ChatCommandContext, args: &[&str]) -> Result<(), String> {
+    post_chat_message(ctx, args[0], "guild")
+}
+
+fn command_zone(ctx: &ReducerContext, _command_ctx: &ChatCommandContext, args: &[&str]) -> Result<(), String> {
+    post_chat_message(ctx, args[0], "zone")
+}
+
+fn command_roll(ctx: &ReducerContext, command_ctx: &ChatCommandContext, args: &[&str]) -> Result<(), String> {
+    let max = match args.first() {
+        Some(value) => value.parse::<u32>()
+            .ok()
+            .filter(|max| (1..=MAX_ROLL).contains(max))
+            .ok_or_else(|| format!("Roll range must be a number from 1 to {}", MAX_ROLL))?,
+        None => 100,
+    };
+
+    let result = ctx.rng().gen_range(1..=max);
+
+    // The roll is announced in the channel it was typed in, like any other message
+    post_chat_message(ctx, &format!("rolls {} (1-{})", result, max), &command_ctx.channel)
+}
+
+fn command_who(ctx: &ReducerContext, command_ctx: &ChatCommandContext, args: &[&str]) -> Result<(), String> {
+    let zone = args.first()
+        .map(|zone| zone.to_string())
+        .unwrap_or_else(|| command_ctx.player.current_zone.clone());
+
+    let players = Player::get_players_in_zone(ctx, &zone);
+    let mut names: Vec<String> = players.iter()
+        .take(MAX_WHO_RESULTS)
+        .map(|player| format!("{} (level {})", player.username, player.level))
+        .collect();
+
+    if players.len() > MAX_WHO_RESULTS {
+        names.push(format!("and {} more", players.len() - MAX_WHO_RESULTS));
+    }
+
+    let text = if names.is_empty() {
+        format!("Nobody is online in {}", zone)
+    } else {
+        format!("{} online in {}: {}", players.len(), zone, names.join(", "))
+    };
+
+    PlayerNotification::send(ctx, command_ctx.player.identity, "command", text);
+    Ok(())
+}
+
+fn command_ignore(ctx: &ReducerContext, command_ctx: &ChatCommandContext, args: &[&str]) -> Result<(), String> {
+    let text = match args.first() {
+        Some(username) => {
+            ignore_player(ctx, username.to_string())?;
+            format!("You are now ignoring {}", username)
+        },
+        None => {
+            let names: Vec<String> = IgnoredPlayer::get_ignored(ctx, &command_ctx.player.identity)
+                .into_iter()
+                .map(|entry| entry.ignored_username)
+                .collect();
+            if names.is_empty() {
+                "You are not ignoring anyone".to_string()
+            } else {
+                format!("Ignoring: {}", names.join(", "))
+            }
+        },
+    };
+
+    PlayerNotification::send(ctx, command_ctx.player.identity, "command", text);
+    Ok(())
+}
+
+fn command_unignore(ctx: &ReducerContext, command_ctx: &ChatCommandContext, args: &[&str]) -> Result<(), String> {
+    unignore_player(ctx, args[0].to_string())?;
+    PlayerNotification::send(ctx, command_ctx.player.identity, "command", format!("You are no longer ignoring {}", args[0]));
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Friends list and ignore list reducers
+
+use spacetimedb::{reducer, ReducerContext, Table};
+use shared_module::*;
+use crate::tables::*;
+use crate::utils::validation::{validate_player_in_game, validate_rate_limit};
+
+/// Look up any registered player by name, online or not
+fn find_user(ctx: &ReducerContext, username: &str) -> Result<User, String> {
+    User::filter_by_username(ctx, username)
+        .ok_or_else(|| "No player with that name exists".to_string())
+}
+
+/// Send a friend request; if they already asked you, this accepts theirs instead
+#[reducer]
+pub fn send_friend_request(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+    let target = find_user(ctx, &target_username)?;
+
+    if target.identity == player.identity {
+        return Err("You cannot befriend yourself".to_string());
+    }
+
+    if Friendship::are_friends(ctx, &player.identity, &target.identity) {
+        return Err("You are already friends".to_string());
+    }
+
+    if IgnoredPlayer::is_ignoring(ctx, &player.identity, &target.identity) {
+        return Err("Stop ignoring that player first".to_string());
+    }
+
+    if let Some(reverse) = FriendRequest::find(ctx, &target.identity, &player.identity) {
+        return accept_friend_request(ctx, reverse.request_id);
+    }
+
+    if FriendRequest::find(ctx, &player.identity, &target.identity).is_some() {
+        return Err("You have already sent that player a request".to_string());
+    }
+
+    if FriendRequest::count_pending_from(ctx, &player.identity) >= MAX_PENDING_FRIEND_REQUESTS {
+        return Err("You have too many unanswered friend requests".to_string());
+    }
+
+    if Friendship::get_friends(ctx, &player.identity).len() >= MAX_FRIENDS {
+        return Err("Your friends list is full".to_string());
+    }
+
+    validate_rate_limit(ctx, "friend_request")?;
+
+    // Requests to someone ignoring you look sent but never arrive
+    if IgnoredPlayer::is_ignoring(ctx, &target.identity, &player.identity) {
+        return Ok(());
+    }
+
+    ctx.db.friend_request().insert(FriendRequest {
+        request_id: 0, // auto_inc
+        sender_identity: player.identity,
+        sender_username: player.username.clone(),
+        recipient_identity: target.identity,
+        recipient_username: target.username.clone(),
+        created_at: ctx.timestamp,
+    });
+
+    PlayerNotification::send(
+        ctx,
+        target.identity,
+        "friends",
+        format!("{} wants to be your friend", player.username)
+    );
+
+    Ok(())
+}
+
+/// Accept a friend request sent to you
+#[reducer]
+pub fn accept_friend_request(ctx: &ReducerContext, request_id: u64) -> Result<(), String> {
+    let request = ctx.db.friend_request().request_id().find(&request_id)
+        .filter(|request| request.recipient_identity == ctx.sender)
+        .ok_or("Friend request not found")?;
+
+    ctx.db.friend_request().request_id().delete(&request_id);
+
+    if Friendship::are_friends(ctx, &request.sender_identity, &request.recipient_identity) {
+        return Ok(());
+    }
+
+    if Friendship::get_friends(ctx, &request.recipient_identity).len() >= MAX_FRIENDS {
+        return Err("Your friends list is full".to_string());
+    }
+
+    if Friendship::get_friends(ctx, &request.sender_identity).len() >= MAX_FRIENDS {
+        return Err("Their friends list is full".to_string());
+    }
+
+    Friendship::add(
+        ctx,
+        request.sender_identity,
+        request.sender_username.clone(),
+        request.recipient_identity,
+        request.recipient_username.clone()
+    );
+
+    // A crossed request in the other direction is answered by this one too
+    FriendRequest::clear_between(ctx, &request.sender_identity, &request.recipient_identity);
+
+    PlayerNotification::send(
+        ctx,
+        request.sender_identity,
+        "friends",
+        format!("{} accepted your friend request", request.recipient_username)
+    );
+
+    Ok(())
+}
+
+/// Decline a friend request sent to you; the sender is not told
+#[reducer]
+pub fn decline_friend_request(ctx: &ReducerContext, request_id: u64) -> Result<(), String> {
+    ctx.db.friend_request().request_id().find(&request_id)
+        .filter(|request| request.recipient_identity == ctx.sender)
+        .ok_or("Friend request not found")?;
+
+    ctx.db.friend_request().request_id().delete(&request_id);
+    Ok(())
+}
+
+/// Withdraw a friend request you sent
+#[reducer]
+pub fn cancel_friend_request(ctx: &ReducerContext, request_id: u64) -> Result<(), String> {
+    ctx.db.friend_request().request_id().find(&request_id)
+        .filter(|request| request.sender_identity == ctx.sender)
+        .ok_or("Friend request not found")?;
+
+    ctx.db.friend_request().request_id().delete(&request_id);
+    Ok(())
+}
+
+/// Remove someone from your friends list (and yourself from theirs)
+#[reducer]
+pub fn remove_friend(ctx: &ReducerContext, friend_username: String) -> Result<(), String> {
+    let friend = find_user(ctx, &friend_username)?;
+
+    if !Friendship::remove(ctx, &ctx.sender, &friend.identity) {
+        return Err("That player is not on your friends list".to_string());
+    }
+
+    Ok(())
+}
+
+/// Ignore a player: their whispers, invites, friend and trade requests stop reaching you
+/// Ignoring someone also ends any friendship and pending requests with them
+#[reducer]
+pub fn ignore_player(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let target = find_user(ctx, &target_username)?;
+
+    if target.identity == ctx.sender {
+        return Err("You cannot ignore yourself".to_string());
+    }
+
+    if IgnoredPlayer::is_ignoring(ctx, &ctx.sender, &target.identity) {
+        return Err("You are already ignoring that player".to_string());
+    }
+
+    if IgnoredPlayer::get_ignored(ctx, &ctx.sender).len() >= MAX_IGNORED_PLAYERS {
+        return Err("Your ignore list is full".to_string());
+    }
+
+    // Staff must stay reachable
+    if StaffMember::find_role(ctx, &target.identity).is_some() {
+        return Err("You cannot ignore staff members".to_string());
+    }
+
+    ctx.db.ignored_player().insert(IgnoredPlayer {
+        ignore_id: 0, // auto_inc
+        player_identity: ctx.sender,
+        ignored_identity: target.identity,
+        ignored_username: target.username.clone(),
+        created_at: ctx.timestamp,
+    });
+
+    Friendship::remove(ctx, &ctx.sender, &target.identity);
+    FriendRequest::clear_between(ctx, &ctx.sender, &target.identity);
+
+    // Invites already waiting from them are dropped as well
+    if let Some(invite) = PartyInvite::find(ctx, &target.identity, &ctx.sender) {
+        ctx.db.party_invite().invite_id().delete(&invite.invite_id);
+    }
+    let guild_invites: Vec<GuildInvite> = ctx.db.guild_invite().invitee_identity()
+        .filter(&ctx.sender)
+        .filter(|invite| invite.invited_by == target.identity)
+        .collect();
+    for invite in guild_invites {
+        ctx.db.guild_invite().invite_id().delete(&invite.invite_id);
+    }
+
+    Ok(())
+}
+
+/// Stop ignoring a player
+#[reducer]
+pub fn unignore_player(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let target = find_user(ctx, &target_username)?;
+
+    let entry = IgnoredPlayer::find(ctx, &ctx.sender, &target.identity)
+        .ok_or("You are not ignoring that player")?;
+
+    ctx.db.ignored_player().ignore_id().delete(&entry.ignore_id);
+    Ok(())
+}
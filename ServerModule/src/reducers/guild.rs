@@ -0,0 +1,422 @@
+//! Guild reducers
+
+use spacetimedb::{reducer, ReducerContext, Table};
+use shared_module::*;
+use crate::tables::*;
+use crate::utils::validation::{validate_player_in_game, validate_rate_limit, validate_target_player};
+
+/// Look up the calling player's guild membership, rank and guild
+pub fn find_own_guild(ctx: &ReducerContext) -> Result<(GuildMember, GuildRank, Guild), String> {
+    let membership = GuildMember::find_by_member(ctx, &ctx.sender)
+        .ok_or("You are not in a guild")?;
+    let rank = membership.rank(ctx)
+        .ok_or("Guild rank not found")?;
+    let guild = Guild::filter_by_guild_id(ctx, membership.guild_id)
+        .ok_or("Guild not found")?;
+
+    Ok((membership, rank, guild))
+}
+
+/// Look up the calling player's guild and make sure their rank grants a permission
+pub fn find_guild_with_permission(
+    ctx: &ReducerContext,
+    permission: u32
+) -> Result<(GuildMember, GuildRank, Guild), String> {
+    let (membership, rank, guild) = find_own_guild(ctx)?;
+
+    if !rank.has_permission(permission) {
+        return Err("Your guild rank does not allow that".to_string());
+    }
+
+    Ok((membership, rank, guild))
+}
+
+/// Look up the calling player's guild and make sure they lead it
+fn find_led_guild(ctx: &ReducerContext) -> Result<(GuildMember, GuildRank, Guild), String> {
+    let (membership, rank, guild) = find_own_guild(ctx)?;
+
+    if guild.leader_identity != ctx.sender {
+        return Err("Only the guild master can do that".to_string());
+    }
+
+    Ok((membership, rank, guild))
+}
+
+/// Find a member of the given guild by username
+fn find_guild_member(ctx: &ReducerContext, guild_id: u64, username: &str) -> Result<GuildMember, String> {
+    GuildMember::get_members(ctx, guild_id)
+        .into_iter()
+        .find(|member| member.member_username == username)
+        .ok_or_else(|| "That player is not in your guild".to_string())
+}
+
+/// Tell every member of a guild something
+pub fn notify_guild(ctx: &ReducerContext, guild_id: u64, message: String) {
+    for member in GuildMember::get_members(ctx, guild_id) {
+        PlayerNotification::send(ctx, member.member_identity, "guild", message.clone());
+    }
+}
+
+/// Run player-chosen guild text through the chat sanitizer and word filters, rejecting anything filtered
+fn validate_guild_text(ctx: &ReducerContext, text: &str, max_length: usize) -> Result<String, String> {
+    let text = sanitize_text(text, max_length)?;
+    let outcome = apply_word_filters(&text, &ChatWordFilter::get_rules(ctx))?;
+
+    if outcome.message != text {
+        return Err("That text contains words that are not allowed".to_string());
+    }
+
+    Ok(text)
+}
+
+/// Found a new guild with yourself as guild master
+#[reducer]
+pub fn create_guild(ctx: &ReducerContext, name: String) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+
+    if GuildMember::find_by_member(ctx, &player.identity).is_some() {
+        return Err("You are already in a guild".to_string());
+    }
+
+    let name = validate_guild_text(ctx, &validate_guild_name(&name)?, MAX_GUILD_NAME_LENGTH)?;
+
+    if Guild::filter_by_name(ctx, &name).is_some() {
+        return Err("A guild with that name already exists".to_string());
+    }
+
+    validate_rate_limit(ctx, "create_guild")?;
+
+    let guild = Guild::create_guild(ctx, name, player.identity, player.username.clone());
+
+    // Any invites the founder was holding are moot now
+    ctx.db.guild_invite().invitee_identity().delete(&player.identity);
+
+    log::info!("Guild '{}' ({}) founded by {}", guild.name, guild.guild_id, player.username);
+    Ok(())
+}
+
+/// Invite a player to your guild
+#[reducer]
+pub fn invite_to_guild(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let (membership, _rank, guild) = find_guild_with_permission(ctx, GUILD_PERM_INVITE)?;
+    let target = validate_target_player(ctx, &target_username)?;
+
+    if target.identity == membership.member_identity {
+        return Err("You cannot invite yourself".to_string());
+    }
+
+    if GuildMember::find_by_member(ctx, &target.identity).is_some() {
+        return Err("That player is already in a guild".to_string());
+    }
+
+    if GuildMember::get_members(ctx, guild.guild_id).len() >= MAX_GUILD_MEMBERS {
+        return Err("Your guild is full".to_string());
+    }
+
+    // Replace a stale invite rather than stacking duplicates
+    if let Some(existing) = GuildInvite::find(ctx, guild.guild_id, &target.identity) {
+        if !existing.is_expired(ctx.timestamp) {
+            return Err("That player already has an invite to your guild".to_string());
+        }
+        ctx.db.guild_invite().invite_id().delete(&existing.invite_id);
+    }
+
+    validate_rate_limit(ctx, "guild_invite")?;
+
+    // Invites to someone ignoring the inviter are dropped silently
+    if IgnoredPlayer::is_ignoring(ctx, &target.identity, &membership.member_identity) {
+        return Ok(());
+    }
+
+    ctx.db.guild_invite().insert(GuildInvite {
+        invite_id: 0, // auto_inc
+        guild_id: guild.guild_id,
+        guild_name: guild.name.clone(),
+        invitee_identity: target.identity,
+        invited_by: membership.member_identity,
+        created_at: ctx.timestamp,
+    });
+
+    PlayerNotification::send(
+        ctx,
+        target.identity,
+        "guild",
+        format!("{} invited you to join <{}>", membership.member_username, guild.name)
+    );
+
+    Ok(())
+}
+
+/// Accept a guild invite and join at the guild's lowest rank
+#[reducer]
+pub fn accept_guild_invite(ctx: &ReducerContext, invite_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+
+    let invite = ctx.db.guild_invite().invite_id().find(&invite_id)
+        .filter(|invite| invite.invitee_identity == player.identity)
+        .ok_or("Invite not found")?;
+
+    // Invites are single use
+    ctx.db.guild_invite().invite_id().delete(&invite_id);
+
+    if invite.is_expired(ctx.timestamp) {
+        return Err("That invite has expired".to_string());
+    }
+
+    if GuildMember::find_by_member(ctx, &player.identity).is_some() {
+        return Err("You are already in a guild".to_string());
+    }
+
+    let guild = Guild::filter_by_guild_id(ctx, invite.guild_id)
+        .ok_or("That guild no longer exists")?;
+
+    if GuildMember::get_members(ctx, guild.guild_id).len() >= MAX_GUILD_MEMBERS {
+        return Err("That guild is full".to_string());
+    }
+
+    let rank = GuildRank::lowest_rank(ctx, guild.guild_id)
+        .ok_or("Guild rank not found")?;
+
+    GuildMember::add_member(ctx, guild.guild_id, player.identity, player.username.clone(), rank.rank_id);
+
+    // Joining one guild voids invites from the others
+    ctx.db.guild_invite().invitee_identity().delete(&player.identity);
+
+    notify_guild(ctx, guild.guild_id, format!("{} joined the guild", player.username));
+    if !guild.motd.is_empty() {
+        PlayerNotification::send(ctx, player.identity, "guild", format!("Guild message of the day: {}", guild.motd));
+    }
+
+    Ok(())
+}
+
+/// Decline a guild invite
+#[reducer]
+pub fn decline_guild_invite(ctx: &ReducerContext, invite_id: u64) -> Result<(), String> {
+    let invite = ctx.db.guild_invite().invite_id().find(&invite_id)
+        .filter(|invite| invite.invitee_identity == ctx.sender)
+        .ok_or("Invite not found")?;
+
+    ctx.db.guild_invite().invite_id().delete(&invite_id);
+
+    if let Some(player) = Player::filter_by_identity(ctx, &ctx.sender) {
+        PlayerNotification::send(
+            ctx,
+            invite.invited_by,
+            "guild",
+            format!("{} declined your guild invite", player.username)
+        );
+    }
+
+    Ok(())
+}
+
+/// Leave your guild
+/// The guild master must hand over leadership first, unless they are the last member
+#[reducer]
+pub fn leave_guild(ctx: &ReducerContext) -> Result<(), String> {
+    let (membership, _rank, guild) = find_own_guild(ctx)?;
+
+    if guild.leader_identity == membership.member_identity {
+        if GuildMember::get_members(ctx, guild.guild_id).len() > 1 {
+            return Err("Transfer guild leadership before leaving".to_string());
+        }
+        return disband_guild(ctx);
+    }
+
+    ctx.db.guild_member().membership_id().delete(&membership.membership_id);
+    notify_guild(ctx, guild.guild_id, format!("{} left the guild", membership.member_username));
+
+    Ok(())
+}
+
+/// Remove a lower-ranked member from your guild
+#[reducer]
+pub fn kick_from_guild(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let (_membership, rank, guild) = find_guild_with_permission(ctx, GUILD_PERM_KICK)?;
+    let target = find_guild_member(ctx, guild.guild_id, &target_username)?;
+
+    if target.member_identity == ctx.sender {
+        return Err("Use leave_guild to leave your own guild".to_string());
+    }
+
+    let target_rank = target.rank(ctx)
+        .ok_or("Guild rank not found")?;
+    if !rank.outranks(&target_rank) {
+        return Err("You can only remove members ranked below you".to_string());
+    }
+
+    ctx.db.guild_member().membership_id().delete(&target.membership_id);
+
+    PlayerNotification::send(ctx, target.member_identity, "guild", format!("You were removed from <{}>", guild.name));
+    notify_guild(ctx, guild.guild_id, format!("{} was removed from the guild", target.member_username));
+
+    Ok(())
+}
+
+/// Move a lower-ranked member to another rank below your own
+#[reducer]
+pub fn set_guild_member_rank(
+    ctx: &ReducerContext,
+    target_username: String,
+    rank_name: String
+) -> Result<(), String> {
+    let (_membership, rank, guild) = find_guild_with_permission(ctx, GUILD_PERM_SET_RANK)?;
+    let mut target = find_guild_member(ctx, guild.guild_id, &target_username)?;
+
+    let target_rank = target.rank(ctx)
+        .ok_or("Guild rank not found")?;
+    if !rank.outranks(&target_rank) {
+        return Err("You can only change the rank of members ranked below you".to_string());
+    }
+
+    let new_rank = GuildRank::find_by_name(ctx, guild.guild_id, &rank_name)
+        .ok_or("Rank not found")?;
+    if !rank.outranks(&new_rank) {
+        return Err("You can only assign ranks below your own".to_string());
+    }
+
+    target.rank_id = new_rank.rank_id;
+    ctx.db.guild_member().membership_id().update(target.clone());
+
+    notify_guild(ctx, guild.guild_id, format!("{} is now {}", target.member_username, new_rank.name));
+
+    Ok(())
+}
+
+/// Create or update a rank below your own
+/// Rank 0 belongs to the guild master and cannot be changed here
+#[reducer]
+pub fn set_guild_rank(
+    ctx: &ReducerContext,
+    rank_name: String,
+    rank_order: u32,
+    permissions: u32,
+    daily_withdraw_limit: u32
+) -> Result<(), String> {
+    let (_membership, rank, guild) = find_guild_with_permission(ctx, GUILD_PERM_MANAGE_RANKS)?;
+
+    let rank_name = validate_guild_text(ctx, &rank_name, MAX_GUILD_NAME_LENGTH)?;
+
+    if rank_order <= rank.rank_order {
+        return Err("You can only manage ranks below your own".to_string());
+    }
+
+    // Nobody can hand out permissions they do not hold themselves
+    if permissions & !rank.permissions != 0 {
+        return Err("You cannot grant permissions your own rank lacks".to_string());
+    }
+
+    if daily_withdraw_limit > rank.daily_withdraw_limit && !rank.is_guild_master() {
+        return Err("You cannot grant a higher withdrawal limit than your own rank has".to_string());
+    }
+
+    let ranks = GuildRank::get_ranks(ctx, guild.guild_id);
+
+    if ranks.iter().any(|other| other.rank_order == rank_order && !other.name.eq_ignore_ascii_case(&rank_name)) {
+        return Err("Another rank already uses that position".to_string());
+    }
+
+    match ranks.into_iter().find(|existing| existing.name.eq_ignore_ascii_case(&rank_name)) {
+        Some(mut existing) => {
+            if !rank.outranks(&existing) {
+                return Err("You can only manage ranks below your own".to_string());
+            }
+            existing.name = rank_name;
+            existing.rank_order = rank_order;
+            existing.permissions = permissions;
+            existing.daily_withdraw_limit = daily_withdraw_limit;
+            ctx.db.guild_rank().rank_id().update(existing);
+        },
+        None => {
+            if GuildRank::get_ranks(ctx, guild.guild_id).len() >= MAX_GUILD_RANKS {
+                return Err(format!("A guild can have at most {} ranks", MAX_GUILD_RANKS));
+            }
+            GuildRank::create_rank(ctx, guild.guild_id, rank_name, rank_order, permissions, daily_withdraw_limit);
+        },
+    }
+
+    Ok(())
+}
+
+/// Delete an unused rank below your own
+#[reducer]
+pub fn delete_guild_rank(ctx: &ReducerContext, rank_name: String) -> Result<(), String> {
+    let (_membership, rank, guild) = find_guild_with_permission(ctx, GUILD_PERM_MANAGE_RANKS)?;
+
+    let target_rank = GuildRank::find_by_name(ctx, guild.guild_id, &rank_name)
+        .ok_or("Rank not found")?;
+
+    if !rank.outranks(&target_rank) {
+        return Err("You can only manage ranks below your own".to_string());
+    }
+
+    // New members need somewhere to start
+    if GuildRank::get_ranks(ctx, guild.guild_id).len() <= 2 {
+        return Err("A guild needs at least one rank besides the guild master".to_string());
+    }
+
+    if GuildMember::get_members(ctx, guild.guild_id).iter().any(|member| member.rank_id == target_rank.rank_id) {
+        return Err("Move the members holding this rank first".to_string());
+    }
+
+    ctx.db.guild_rank().rank_id().delete(&target_rank.rank_id);
+
+    Ok(())
+}
+
+/// Set the guild message of the day; an empty message clears it
+#[reducer]
+pub fn set_guild_motd(ctx: &ReducerContext, motd: String) -> Result<(), String> {
+    let (_membership, _rank, mut guild) = find_guild_with_permission(ctx, GUILD_PERM_EDIT_MOTD)?;
+
+    guild.motd = match motd.trim().is_empty() {
+        true => String::new(),
+        false => validate_guild_text(ctx, &motd, MAX_GUILD_MOTD_LENGTH)?,
+    };
+    ctx.db.guild().guild_id().update(guild.clone());
+
+    if !guild.motd.is_empty() {
+        notify_guild(ctx, guild.guild_id, format!("Guild message of the day: {}", guild.motd));
+    }
+
+    Ok(())
+}
+
+/// Hand guild leadership to another member (guild master only)
+/// The outgoing guild master takes over the new leader's previous rank
+#[reducer]
+pub fn transfer_guild_leadership(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let (mut membership, _rank, mut guild) = find_led_guild(ctx)?;
+    let mut target = find_guild_member(ctx, guild.guild_id, &target_username)?;
+
+    if target.member_identity == membership.member_identity {
+        return Err("You already lead this guild".to_string());
+    }
+
+    std::mem::swap(&mut membership.rank_id, &mut target.rank_id);
+    ctx.db.guild_member().membership_id().update(membership);
+    ctx.db.guild_member().membership_id().update(target.clone());
+
+    guild.leader_identity = target.member_identity;
+    ctx.db.guild().guild_id().update(guild.clone());
+
+    notify_guild(ctx, guild.guild_id, format!("{} is now the guild master", target.member_username));
+
+    Ok(())
+}
+
+/// Disband your guild (guild master only); the guild bank must be emptied first
+#[reducer]
+pub fn disband_guild(ctx: &ReducerContext) -> Result<(), String> {
+    let (_membership, _rank, guild) = find_led_guild(ctx)?;
+
+    if !GuildBankItem::get_items(ctx, guild.guild_id).is_empty() {
+        return Err("Empty the guild bank before disbanding".to_string());
+    }
+
+    notify_guild(ctx, guild.guild_id, format!("<{}> has been disbanded", guild.name));
+    Guild::disband(ctx, guild.guild_id);
+
+    Ok(())
+}
@@ -4,10 +4,24 @@
 pub mod auth;
 pub mod player;
 pub mod chat;
+pub mod chat_channel;
+pub mod chat_commands;
+pub mod moderation;
+pub mod report;
+pub mod party;
+pub mod guild;
+pub mod friend;
 pub mod session;
 
 // Re-export all reducer modules
 pub use auth::*;
 pub use player::*;
 pub use chat::*;
+pub use chat_channel::*;
+pub use chat_commands::*;
+pub use moderation::*;
+pub use report::*;
+pub use party::*;
+pub use guild::*;
+pub use friend::*;
 pub use session::*;
\ No newline at end of file
@@ -0,0 +1,213 @@
+//! Staff management and moderator chat tools
+
+use spacetimedb::{reducer, ReducerContext};
+use shared_module::*;
+use crate::tables::*;
+use crate::utils::validation::{
+    can_moderate_chat_channel,
+    validate_admin_permissions,
+    validate_moderator_permissions,
+};
+
+/// Grant a staff role ("moderator" or "admin") to a user (admin function)
+#[reducer]
+pub fn grant_staff_role(
+    ctx: &ReducerContext,
+    target_username: String,
+    role: String
+) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+
+    let role = StaffRole::from_string(&role)
+        .ok_or("Staff role must be one of: moderator, admin")?;
+
+    let target = User::filter_by_username(ctx, &target_username)
+        .ok_or("User not found")?;
+
+    StaffMember::grant(ctx, target.identity, role.clone(), ctx.sender);
+
+    log::info!("Staff role '{}' granted to {}", role.to_string(), target_username);
+    Ok(())
+}
+
+/// Remove a user's staff role (admin function)
+#[reducer]
+pub fn revoke_staff_role(
+    ctx: &ReducerContext,
+    target_username: String
+) -> Result<(), String> {
+    validate_admin_permissions(ctx)?;
+
+    let target = User::filter_by_username(ctx, &target_username)
+        .ok_or("User not found")?;
+
+    // Keeps at least one admin around: someone else has to demote you
+    if target.identity == ctx.sender {
+        return Err("You cannot revoke your own staff role".to_string());
+    }
+
+    if !StaffMember::revoke(ctx, &target.identity) {
+        return Err("User is not a staff member".to_string());
+    }
+
+    log::info!("Staff role revoked from {}", target_username);
+    Ok(())
+}
+
+/// Mute a player in one channel, or in every channel when none is given (moderator function)
+#[reducer]
+pub fn mute_player(
+    ctx: &ReducerContext,
+    target_username: String,
+    channel: Option<String>,
+    duration_seconds: u64,
+    reason: String
+) -> Result<(), String> {
+    validate_moderator_permissions(ctx)?;
+
+    let target = User::filter_by_username(ctx, &target_username)
+        .ok_or("User not found")?;
+
+    if duration_seconds == 0 || duration_seconds > MAX_MODERATOR_MUTE_SECONDS {
+        return Err(format!("Mute duration must be between 1 and {} seconds", MAX_MODERATOR_MUTE_SECONDS));
+    }
+
+    // Empty channel means the mute applies everywhere
+    let channel = channel
+        .map(|channel| channel.trim().to_lowercase())
+        .unwrap_or_default();
+
+    let muted_until = ctx.timestamp + std::time::Duration::from_secs(duration_seconds);
+    ChatMute::create_mute(ctx, target.identity, channel.clone(), muted_until, reason.clone(), ctx.sender);
+
+    let scope = if channel.is_empty() { "all channels".to_string() } else { format!("'{}'", channel) };
+    PlayerNotification::send(
+        ctx,
+        target.identity,
+        "chat",
+        format!("You were muted in {} for {} seconds: {}", scope, duration_seconds, reason)
+    );
+
+    log::info!("{} muted in {} for {}s", target_username, scope, duration_seconds);
+    Ok(())
+}
+
+/// Lift a player's active mutes in one channel, or all of them (moderator function)
+#[reducer]
+pub fn unmute_player(
+    ctx: &ReducerContext,
+    target_username: String,
+    channel: Option<String>
+) -> Result<(), String> {
+    validate_moderator_permissions(ctx)?;
+
+    let target = User::filter_by_username(ctx, &target_username)
+        .ok_or("User not found")?;
+
+    let channel = channel.map(|channel| channel.trim().to_lowercase());
+    let lifted = ChatMute::lift(ctx, &target.identity, channel.as_deref());
+
+    if lifted == 0 {
+        return Err("Player has no active mutes".to_string());
+    }
+
+    PlayerNotification::send(ctx, target.identity, "chat", "Your chat mute was lifted".to_string());
+
+    log::info!("Lifted {} mute(s) from {}", lifted, target_username);
+    Ok(())
+}
+
+/// Delete a chat message, leaving a tombstone for audit
+/// Staff can delete anywhere; custom channel moderators within their channel
+#[reducer]
+pub fn delete_chat_message(
+    ctx: &ReducerContext,
+    message_id: u64,
+    reason: String
+) -> Result<(), String> {
+    let message = ChatMessage::filter_by_message_id(ctx, message_id)
+        .ok_or("Message not found")?;
+
+    if !can_moderate_chat_channel(ctx, &ctx.sender, &message.channel) {
+        return Err("You cannot delete messages in this channel".to_string());
+    }
+
+    ChatMessageTombstone::record(ctx, &message, ctx.sender, reason);
+    ctx.db.chatmessage().message_id().delete(&message_id);
+
+    log::info!("Chat message {} in '{}' deleted", message_id, message.channel);
+    Ok(())
+}
+
+/// Set read-only and slow mode on a channel
+/// Passing false and 0 removes all restrictions
+#[reducer]
+pub fn set_chat_channel_mode(
+    ctx: &ReducerContext,
+    channel: String,
+    read_only: bool,
+    slow_mode_seconds: u32
+) -> Result<(), String> {
+    let channel = channel.trim().to_lowercase();
+
+    let is_builtin = matches!(channel.as_str(), "global" | "zone" | "whisper");
+    if !is_builtin && ChatChannel::filter_by_name(ctx, &channel).is_none() {
+        return Err("Invalid chat channel".to_string());
+    }
+
+    if !can_moderate_chat_channel(ctx, &ctx.sender, &channel) {
+        return Err("You cannot change this channel's mode".to_string());
+    }
+
+    if slow_mode_seconds > MAX_CHAT_SLOW_MODE_SECONDS {
+        return Err(format!("Slow mode cannot exceed {} seconds", MAX_CHAT_SLOW_MODE_SECONDS));
+    }
+
+    ChatChannelSettings::set(ctx, channel.clone(), read_only, slow_mode_seconds, ctx.sender);
+
+    log::info!("Channel '{}' mode: read_only={}, slow_mode={}s", channel, read_only, slow_mode_seconds);
+    Ok(())
+}
+
+/// Post a message as the server (moderator function)
+/// "system" and "global" reach everyone, "zone" needs a zone, or name a custom channel
+#[reducer]
+pub fn post_system_message(
+    ctx: &ReducerContext,
+    channel: String,
+    zone: Option<String>,
+    message: String
+) -> Result<(), String> {
+    validate_moderator_permissions(ctx)?;
+
+    let channel = channel.trim().to_lowercase();
+    let message = sanitize_chat_message(&message)?;
+
+    let (zone, group_id) = match channel.as_str() {
+        "system" | "global" => (String::new(), 0),
+        "zone" => {
+            let zone = zone.filter(|zone| !zone.is_empty())
+                .ok_or("A zone is required for zone announcements")?;
+            (zone, 0)
+        },
+        custom => {
+            let custom_channel = ChatChannel::filter_by_name(ctx, custom)
+                .ok_or("Invalid chat channel")?;
+            (String::new(), custom_channel.channel_id)
+        }
+    };
+
+    ChatMessage::create_message(
+        ctx,
+        ctx.identity(),
+        SYSTEM_SENDER_NAME.to_string(),
+        message,
+        channel.clone(),
+        zone,
+        group_id,
+        ctx.timestamp
+    );
+
+    log::info!("System message posted to '{}'", channel);
+    Ok(())
+}
@@ -0,0 +1,217 @@
+//! Party reducers
+
+use spacetimedb::{reducer, ReducerContext, Table};
+use shared_module::*;
+use crate::tables::*;
+use crate::utils::validation::{validate_player_in_game, validate_rate_limit, validate_target_player};
+
+/// Look up the calling player's party membership and the party itself
+fn find_own_party(ctx: &ReducerContext) -> Result<(PartyMember, Party), String> {
+    let membership = PartyMember::find_by_member(ctx, &ctx.sender)
+        .ok_or("You are not in a party")?;
+    let party = Party::filter_by_party_id(ctx, membership.party_id)
+        .ok_or("Party not found")?;
+
+    Ok((membership, party))
+}
+
+/// Look up the calling player's party and make sure they lead it
+fn find_led_party(ctx: &ReducerContext) -> Result<(PartyMember, Party), String> {
+    let (membership, party) = find_own_party(ctx)?;
+
+    if party.leader_identity != ctx.sender {
+        return Err("Only the party leader can do that".to_string());
+    }
+
+    Ok((membership, party))
+}
+
+/// Find a member of the given party by username
+fn find_party_member(ctx: &ReducerContext, party_id: u64, username: &str) -> Result<PartyMember, String> {
+    PartyMember::get_members(ctx, party_id)
+        .into_iter()
+        .find(|member| member.member_username == username)
+        .ok_or_else(|| "That player is not in your party".to_string())
+}
+
+/// Tell every member of a party something
+fn notify_party(ctx: &ReducerContext, party_id: u64, message: String) {
+    for member in PartyMember::get_members(ctx, party_id) {
+        PlayerNotification::send(ctx, member.member_identity, "party", message.clone());
+    }
+}
+
+/// Invite a player to your party (a party is formed when they accept)
+#[reducer]
+pub fn invite_to_party(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let inviter = validate_player_in_game(ctx)?;
+    let target = validate_target_player(ctx, &target_username)?;
+
+    if target.identity == inviter.identity {
+        return Err("You cannot invite yourself".to_string());
+    }
+
+    if let Some(membership) = PartyMember::find_by_member(ctx, &inviter.identity) {
+        let party = Party::filter_by_party_id(ctx, membership.party_id)
+            .ok_or("Party not found")?;
+
+        if party.leader_identity != inviter.identity {
+            return Err("Only the party leader can invite".to_string());
+        }
+
+        if PartyMember::get_members(ctx, party.party_id).len() >= MAX_PARTY_SIZE {
+            return Err("Your party is full".to_string());
+        }
+    }
+
+    if PartyMember::find_by_member(ctx, &target.identity).is_some() {
+        return Err("That player is already in a party".to_string());
+    }
+
+    // Replace a stale invite rather than stacking duplicates
+    if let Some(existing) = PartyInvite::find(ctx, &inviter.identity, &target.identity) {
+        if !existing.is_expired(ctx.timestamp) {
+            return Err("You have already invited that player".to_string());
+        }
+        ctx.db.party_invite().invite_id().delete(&existing.invite_id);
+    }
+
+    validate_rate_limit(ctx, "party_invite")?;
+
+    // Invites to someone ignoring the inviter are dropped silently
+    if IgnoredPlayer::is_ignoring(ctx, &target.identity, &inviter.identity) {
+        return Ok(());
+    }
+
+    ctx.db.party_invite().insert(PartyInvite {
+        invite_id: 0, // auto_inc
+        inviter_identity: inviter.identity,
+        inviter_username: inviter.username.clone(),
+        invitee_identity: target.identity,
+        created_at: ctx.timestamp,
+    });
+
+    PlayerNotification::send(
+        ctx,
+        target.identity,
+        "party",
+        format!("{} invited you to a party", inviter.username)
+    );
+
+    Ok(())
+}
+
+/// Accept a party invite
+#[reducer]
+pub fn accept_party_invite(ctx: &ReducerContext, invite_id: u64) -> Result<(), String> {
+    let player = validate_player_in_game(ctx)?;
+
+    let invite = ctx.db.party_invite().invite_id().find(&invite_id)
+        .filter(|invite| invite.invitee_identity == player.identity)
+        .ok_or("Invite not found")?;
+
+    // Invites are single use
+    ctx.db.party_invite().invite_id().delete(&invite_id);
+
+    // Returning an error would roll the delete back, so the expired invite is
+    // cleared and the player told instead
+    if invite.is_expired(ctx.timestamp) {
+        PlayerNotification::send(ctx, player.identity, "party", "That invite has expired".to_string());
+        return Ok(());
+    }
+
+    if PartyMember::find_by_member(ctx, &player.identity).is_some() {
+        return Err("You are already in a party".to_string());
+    }
+
+    // Join the inviter's party, or form one with them as leader
+    let party = match PartyMember::find_by_member(ctx, &invite.inviter_identity) {
+        Some(membership) => {
+            let party = Party::filter_by_party_id(ctx, membership.party_id)
+                .ok_or("Party not found")?;
+            if party.leader_identity != invite.inviter_identity {
+                return Err("The inviter no longer leads their party".to_string());
+            }
+            party
+        },
+        None => {
+            let inviter = Player::filter_by_identity(ctx, &invite.inviter_identity)
+                .filter(|inviter| inviter.is_online)
+                .ok_or("The inviter is no longer online")?;
+            Party::create_party(ctx, &inviter)
+        },
+    };
+
+    if PartyMember::get_members(ctx, party.party_id).len() >= MAX_PARTY_SIZE {
+        return Err("That party is full".to_string());
+    }
+
+    PartyMember::add_member(ctx, party.party_id, &player);
+    notify_party(ctx, party.party_id, format!("{} joined the party", player.username));
+
+    Ok(())
+}
+
+/// Decline a party invite
+#[reducer]
+pub fn decline_party_invite(ctx: &ReducerContext, invite_id: u64) -> Result<(), String> {
+    let invite = ctx.db.party_invite().invite_id().find(&invite_id)
+        .filter(|invite| invite.invitee_identity == ctx.sender)
+        .ok_or("Invite not found")?;
+
+    ctx.db.party_invite().invite_id().delete(&invite_id);
+
+    if let Some(player) = Player::filter_by_identity(ctx, &ctx.sender) {
+        PlayerNotification::send(
+            ctx,
+            invite.inviter_identity,
+            "party",
+            format!("{} declined your party invite", player.username)
+        );
+    }
+
+    Ok(())
+}
+
+/// Leave your party
+#[reducer]
+pub fn leave_party(ctx: &ReducerContext) -> Result<(), String> {
+    let (membership, party) = find_own_party(ctx)?;
+
+    Party::remove_member(ctx, &membership);
+    notify_party(ctx, party.party_id, format!("{} left the party", membership.member_username));
+
+    Ok(())
+}
+
+/// Remove a member from your party (leader only)
+#[reducer]
+pub fn kick_from_party(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let (_membership, party) = find_led_party(ctx)?;
+    let target = find_party_member(ctx, party.party_id, &target_username)?;
+
+    if target.member_identity == ctx.sender {
+        return Err("Use leave_party to leave your own party".to_string());
+    }
+
+    Party::remove_member(ctx, &target);
+
+    PlayerNotification::send(ctx, target.member_identity, "party", "You were removed from the party".to_string());
+    notify_party(ctx, party.party_id, format!("{} was removed from the party", target.member_username));
+
+    Ok(())
+}
+
+/// Hand party leadership to another member (leader only)
+#[reducer]
+pub fn promote_party_leader(ctx: &ReducerContext, target_username: String) -> Result<(), String> {
+    let (_membership, mut party) = find_led_party(ctx)?;
+    let target = find_party_member(ctx, party.party_id, &target_username)?;
+
+    party.leader_identity = target.member_identity;
+    ctx.db.party().party_id().update(party.clone());
+
+    notify_party(ctx, party.party_id, format!("{} is now the party leader", target.member_username));
+
+    Ok(())
+}
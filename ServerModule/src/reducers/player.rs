@@ -1,9 +1,51 @@
 //! Player-related reducers
 
-use spacetimedb::{reducer, ReducerContext};
+use std::sync::Mutex;
+use spacetimedb::{reducer, ReducerContext, Identity};
 use shared_module::*;
 use crate::tables::*;
 
+/// Signature of a function run after a player moves or changes zone
+pub type PlayerMovedHook = fn(&ReducerContext, &Player);
+
+/// Movement hooks added by other modules (e.g. CustomServerModule)
+/// Filled by the preinit hook that register_player_moved_hook! exports, which the host
+/// runs every time a module instance starts, so a restarted instance has them too
+static PLAYER_MOVED_HOOKS: Mutex<Vec<PlayerMovedHook>> = Mutex::new(Vec::new());
+
+/// Add a movement hook; called from the hook register_player_moved_hook! exports
+pub fn register_player_moved_callback(hook: PlayerMovedHook) {
+    PLAYER_MOVED_HOOKS.lock().unwrap().push(hook);
+}
+
+/// Run a function whenever a player moves or changes zone
+/// Takes the name of a `PlayerMovedHook` function in scope and exports a preinit hook for it
+#[macro_export]
+macro_rules! register_player_moved_hook {
+    ($hook:ident) => {
+        const _: () = {
+            #[export_name = concat!("__preinit__30_register_player_moved_hook_", stringify!($hook))]
+            extern "C" fn __register_player_moved_hook() {
+                $crate::reducers::player::register_player_moved_callback($hook);
+            }
+        };
+    };
+}
+
+/// Run the movement hooks for a player's new position
+fn run_player_moved_hooks(ctx: &ReducerContext, identity: &Identity) {
+    let hooks: Vec<PlayerMovedHook> = PLAYER_MOVED_HOOKS.lock().unwrap().clone();
+    if hooks.is_empty() {
+        return;
+    }
+    
+    if let Some(player) = Player::filter_by_identity(ctx, identity) {
+        for hook in hooks {
+            hook(ctx, &player);
+        }
+    }
+}
+
 /// Join the game world as a player
 #[reducer]
 pub fn join_game(
@@ -71,6 +113,7 @@ pub fn update_player_position(
     
     // Update position
     Player::update_position(ctx, &ctx.sender, x, y, z, yaw, ctx.timestamp);
+    run_player_moved_hooks(ctx, &ctx.sender);
     
     // Update session activity
     GameSession::update_activity(ctx, &ctx.sender, ctx.timestamp);
@@ -134,6 +177,7 @@ pub fn update_player_stats(
     }
     
     player.last_seen = ctx.timestamp;
+    PartyMember::sync_from_player(ctx, &player);
     ctx.db.player().identity().update(player);
     
     // Update session activity
@@ -166,7 +210,9 @@ pub fn change_zone(
     player.position_z = spawn_z;
     player.last_seen = ctx.timestamp;
     
+    PartyMember::sync_from_player(ctx, &player);
     ctx.db.player().identity().update(player);
+    run_player_moved_hooks(ctx, &ctx.sender);
     
     // Update session activity
     GameSession::update_activity(ctx, &ctx.sender, ctx.timestamp);
@@ -0,0 +1,177 @@
+//! Player report reducers and the moderator ticket queue
+
+use spacetimedb::{reducer, ReducerContext};
+use shared_module::*;
+use crate::tables::*;
+use crate::utils::validation::{validate_moderator_permissions, validate_player_in_game};
+
+/// Look up a ticket that a moderator is about to work on
+fn find_open_report(ctx: &ReducerContext, report_id: u64) -> Result<PlayerReport, String> {
+    let report = PlayerReport::filter_by_report_id(ctx, report_id)
+        .ok_or("Report not found")?;
+
+    if report.status().is_closed() {
+        return Err("Report is already closed".to_string());
+    }
+
+    Ok(report)
+}
+
+/// Report another player for abuse
+#[reducer]
+pub fn report_player(
+    ctx: &ReducerContext,
+    target_username: String,
+    category: String,
+    description: String
+) -> Result<(), String> {
+    let reporter = validate_player_in_game(ctx)?;
+
+    let category = ReportCategory::from_string(&category)
+        .ok_or("Report category must be one of: harassment, spam, cheating, offensive_name, other")?;
+
+    // Reported players may have logged off since, so look them up regardless of online state
+    let reported = ctx.db.game_players().iter()
+        .find(|p| p.username == target_username)
+        .ok_or("Player not found")?;
+
+    if reported.identity == reporter.identity {
+        return Err("You cannot report yourself".to_string());
+    }
+
+    if PlayerReport::has_open_report(ctx, &reporter.identity, &reported.identity) {
+        return Err("You already have an open report against this player".to_string());
+    }
+
+    let description = sanitize_chat_message(&description)?;
+
+    if !RateLimitBucket::try_consume(ctx, &ctx.sender, "report", REPORT_BURST_CAPACITY, REPORT_REFILL_PER_SECOND) {
+        return Err("You are filing reports too quickly".to_string());
+    }
+
+    let chat_since = ctx.timestamp - std::time::Duration::from_secs(REPORT_CHAT_WINDOW_SECONDS);
+    let report = PlayerReport::create_report(
+        ctx,
+        &reporter,
+        &reported,
+        category,
+        description,
+        chat_since,
+        REPORT_CHAT_SNAPSHOT_SIZE
+    );
+
+    PlayerNotification::send(
+        ctx,
+        reporter.identity,
+        "report",
+        format!("Thank you. Your report #{} has been sent to the moderators", report.report_id)
+    );
+
+    log::info!("Report #{} filed against {}", report.report_id, reported.username);
+    Ok(())
+}
+
+/// Assign a report to a moderator, or to yourself when no name is given (moderator function)
+#[reducer]
+pub fn assign_report(
+    ctx: &ReducerContext,
+    report_id: u64,
+    moderator_username: Option<String>
+) -> Result<(), String> {
+    validate_moderator_permissions(ctx)?;
+
+    let mut report = find_open_report(ctx, report_id)?;
+
+    let assignee = match moderator_username {
+        Some(username) => {
+            let user = User::filter_by_username(ctx, &username)
+                .ok_or("User not found")?;
+            StaffMember::find_role(ctx, &user.identity)
+                .ok_or("That user is not a moderator")?;
+            user.identity
+        },
+        None => ctx.sender,
+    };
+
+    report.assigned_to = Some(assignee);
+    report.status = ReportStatus::Assigned.to_string();
+    PlayerReport::save(ctx, report);
+
+    log::info!("Report #{} assigned", report_id);
+    Ok(())
+}
+
+/// Close a report as resolved, optionally linking the sanction that was issued (moderator function)
+/// Supported sanction types: "mute" (ChatMute ID)
+#[reducer]
+pub fn resolve_report(
+    ctx: &ReducerContext,
+    report_id: u64,
+    resolution_note: String,
+    sanction_type: Option<String>,
+    sanction_id: u64
+) -> Result<(), String> {
+    validate_moderator_permissions(ctx)?;
+
+    let mut report = find_open_report(ctx, report_id)?;
+
+    match sanction_type.as_deref() {
+        None => {},
+        Some("mute") => {
+            let mute = ctx.db.chat_mute().mute_id().find(&sanction_id)
+                .ok_or("Mute not found")?;
+            if mute.identity != report.reported_identity {
+                return Err("That mute was not issued to the reported player".to_string());
+            }
+        },
+        Some(other) => return Err(format!("Unknown sanction type '{}'", other)),
+    }
+
+    report.status = ReportStatus::Resolved.to_string();
+    report.assigned_to = report.assigned_to.or(Some(ctx.sender));
+    report.resolution_note = resolution_note;
+    report.sanction_type = sanction_type.unwrap_or_default();
+    report.sanction_id = if report.sanction_type.is_empty() { 0 } else { sanction_id };
+
+    let reporter_identity = report.reporter_identity;
+    PlayerReport::save(ctx, report);
+
+    PlayerNotification::send(
+        ctx,
+        reporter_identity,
+        "report",
+        format!("Your report #{} has been reviewed and action was taken", report_id)
+    );
+
+    log::info!("Report #{} resolved", report_id);
+    Ok(())
+}
+
+/// Close a report without action (moderator function)
+#[reducer]
+pub fn dismiss_report(
+    ctx: &ReducerContext,
+    report_id: u64,
+    resolution_note: String
+) -> Result<(), String> {
+    validate_moderator_permissions(ctx)?;
+
+    let mut report = find_open_report(ctx, report_id)?;
+
+    report.status = ReportStatus::Dismissed.to_string();
+    report.assigned_to = report.assigned_to.or(Some(ctx.sender));
+    report.resolution_note = resolution_note;
+
+    let reporter_identity = report.reporter_identity;
+    PlayerReport::save(ctx, report);
+
+    PlayerNotification::send(
+        ctx,
+        reporter_identity,
+        "report",
+        format!("Your report #{} has been reviewed", report_id)
+    );
+
+    log::info!("Report #{} dismissed", report_id);
+    Ok(())
+}
@@ -0,0 +1,377 @@
+//! Chat message table definition
+
+use spacetimedb::{table, client_visibility_filter, Filter, Identity, Timestamp, ReducerContext, ScheduleAt, Table};
+use shared_module::*;
+use super::player::Player;
+use super::party::PartyMember;
+use super::guild::GuildMember;
+use super::chat_channel::ChatChannelMember;
+
+/// Audience a channel message is routed to
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChatScope {
+    /// Visible to everyone
+    Global,
+    /// Visible to players currently in the given zone
+    Zone(String),
+    /// Visible to members of the given group (party, guild or custom channel ID)
+    Group(u64),
+}
+
+/// Channel types that retention policies are defined for
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChatChannelType {
+    Global,
+    Zone,
+    System,
+    Party,
+    Guild,
+    /// Player-created channels
+    Custom,
+}
+
+impl std::fmt::Display for ChatChannelType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ChatChannelType::Global => "global",
+            ChatChannelType::Zone => "zone",
+            ChatChannelType::System => "system",
+            ChatChannelType::Party => "party",
+            ChatChannelType::Guild => "guild",
+            ChatChannelType::Custom => "custom",
+        })
+    }
+}
+
+impl ChatChannelType {
+    pub fn from_string(s: &str) -> Option<ChatChannelType> {
+        match s {
+            "global" => Some(ChatChannelType::Global),
+            "zone" => Some(ChatChannelType::Zone),
+            "system" => Some(ChatChannelType::System),
+            "party" => Some(ChatChannelType::Party),
+            "guild" => Some(ChatChannelType::Guild),
+            "custom" => Some(ChatChannelType::Custom),
+            _ => None,
+        }
+    }
+
+    /// Type of a ChatMessage.channel value; anything that is not built in is a custom channel
+    pub fn from_channel(channel: &str) -> ChatChannelType {
+        ChatChannelType::from_string(channel).unwrap_or(ChatChannelType::Custom)
+    }
+}
+
+/// A message posted to a chat channel
+/// Every index ends in the timestamp or message ID so history queries and cleanup never scan the whole table
+/// Message IDs only ever grow, so clients page through history with a message ID cursor, e.g.
+/// `SELECT * FROM chatmessage WHERE channel = 'guild' AND message_id < <oldest loaded ID>` for older messages, or
+/// `SELECT * FROM chatmessage WHERE channel = 'guild' AND message_id > <newest loaded ID>` for newer ones
+#[derive(Clone, Debug)]
+#[table(
+    name = chatmessage,
+    public,
+    index(name = channel_message, btree(columns = [channel, message_id])),
+    index(name = channel_time, btree(columns = [channel, timestamp])),
+    index(name = channel_zone, btree(columns = [channel, zone, timestamp])),
+    index(name = channel_group, btree(columns = [channel, group_id, timestamp]))
+)]
+pub struct ChatMessage {
+    /// Unique message identifier, increasing with every message
+    #[primary_key]
+    #[auto_inc]
+    pub message_id: u64,
+
+    /// Who sent the message
+    pub sender_identity: Identity,
+
+    /// Sender's display name (copied from Player for quick access)
+    pub sender_username: String,
+
+    /// Channel the message was posted to ("global", "zone", ...)
+    pub channel: String,
+
+    /// Zone the sender was in when the message was sent
+    /// For "zone" messages this is also the audience
+    pub zone: String,
+
+    /// Party, guild or custom channel the message is addressed to (0 when not group-scoped)
+    pub group_id: u64,
+
+    /// When the message was sent
+    #[index(btree)]
+    pub timestamp: Timestamp,
+
+    /// Message body (already sanitized)
+    pub message: String,
+}
+
+/// A chat message moved out of the live table by a retention policy
+/// Private: kept for moderation and export, not for clients
+#[derive(Clone, Debug)]
+#[table(name = chatmessage_archive)]
+pub struct ChatMessageArchive {
+    #[primary_key]
+    pub message_id: u64,
+
+    pub sender_identity: Identity,
+    pub sender_username: String,
+
+    #[index(btree)]
+    pub channel: String,
+
+    pub zone: String,
+    pub group_id: u64,
+
+    #[index(btree)]
+    pub timestamp: Timestamp,
+
+    pub message: String,
+
+    /// When the message was moved here
+    pub archived_at: Timestamp,
+}
+
+/// How long and how many messages each channel type keeps live
+/// Counts apply per conversation (per zone, per party/guild/custom channel)
+#[derive(Clone, Debug)]
+#[table(name = chat_retention_policy, public)]
+pub struct ChatRetentionPolicy {
+    /// ChatChannelType as a string
+    #[primary_key]
+    pub channel_type: String,
+
+    /// Messages older than this are archived (0 = no age limit)
+    pub max_age_seconds: u64,
+
+    /// Only this many of the newest messages stay live (0 = no count limit)
+    pub max_count: u32,
+}
+
+/// Periodic job that applies the chat retention policies
+#[table(name = chat_retention_schedule, scheduled(crate::reducers::chat::run_chat_retention))]
+pub struct ChatRetentionSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub scheduled_id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+impl ChatMessage {
+    /// Create a new chat message
+    pub fn create_message(
+        ctx: &ReducerContext,
+        sender_identity: Identity,
+        sender_username: String,
+        message: String,
+        channel: String,
+        zone: String,
+        group_id: u64,
+        timestamp: Timestamp
+    ) -> ChatMessage {
+        let chat_message = ChatMessage {
+            message_id: 0, // auto_inc
+            sender_identity,
+            sender_username,
+            channel,
+            zone,
+            group_id,
+            timestamp,
+            message,
+        };
+
+        let chat_message = ctx.db.chatmessage().insert(chat_message);
+
+        // Count limits are applied as messages arrive; age limits by the retention job
+        Self::enforce_count_limit(ctx, &chat_message);
+        chat_message
+    }
+
+    /// Find message by ID
+    pub fn filter_by_message_id(ctx: &ReducerContext, message_id: u64) -> Option<ChatMessage> {
+        ctx.db.chatmessage().message_id().find(&message_id)
+    }
+
+    /// Get the most recent messages in a channel, newest first
+    pub fn get_recent_messages(ctx: &ReducerContext, channel: &str, limit: usize) -> Vec<ChatMessage> {
+        let mut messages: Vec<ChatMessage> = ctx.db.chatmessage().channel_time().filter(channel).collect();
+
+        messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        messages.truncate(limit);
+        messages
+    }
+
+    /// Every live message in one conversation of a channel, read from the matching index
+    fn get_conversation(ctx: &ReducerContext, channel: &str, scope: &ChatScope) -> Vec<ChatMessage> {
+        match scope {
+            ChatScope::Global => ctx.db.chatmessage().channel_time().filter(channel).collect(),
+            ChatScope::Zone(zone) => ctx.db.chatmessage().channel_zone().filter((channel, zone.as_str())).collect(),
+            ChatScope::Group(group_id) => ctx.db.chatmessage().channel_group().filter((channel, *group_id)).collect(),
+        }
+    }
+
+    /// Get all messages sent before the cutoff time, oldest first
+    pub fn get_messages_before(ctx: &ReducerContext, cutoff_time: Timestamp) -> Vec<ChatMessage> {
+        ctx.db.chatmessage().timestamp().filter(..cutoff_time).collect()
+    }
+
+    /// Move messages into the archive table
+    pub fn archive_messages(ctx: &ReducerContext, messages: &[ChatMessage]) {
+        for msg in messages {
+            ctx.db.chatmessage_archive().insert(ChatMessageArchive {
+                message_id: msg.message_id,
+                sender_identity: msg.sender_identity,
+                sender_username: msg.sender_username.clone(),
+                channel: msg.channel.clone(),
+                zone: msg.zone.clone(),
+                group_id: msg.group_id,
+                timestamp: msg.timestamp,
+                message: msg.message.clone(),
+                archived_at: ctx.timestamp,
+            });
+            ctx.db.chatmessage().message_id().delete(&msg.message_id);
+        }
+    }
+
+    /// Retention policy type of this message's channel
+    pub fn channel_type(&self) -> ChatChannelType {
+        ChatChannelType::from_channel(&self.channel)
+    }
+
+    /// Whether a player can read this message, by the same rules as the chatmessage visibility filters
+    pub fn is_visible_to(&self, ctx: &ReducerContext, player: &Player) -> bool {
+        match self.channel_type() {
+            ChatChannelType::Global | ChatChannelType::System => true,
+            ChatChannelType::Zone => self.zone == player.current_zone,
+            ChatChannelType::Party => PartyMember::find_by_member(ctx, &player.identity)
+                .is_some_and(|membership| membership.party_id == self.group_id),
+            ChatChannelType::Guild => GuildMember::find_by_member(ctx, &player.identity)
+                .is_some_and(|membership| membership.guild_id == self.group_id),
+            ChatChannelType::Custom => ChatChannelMember::find(ctx, self.group_id, &player.identity).is_some(),
+        }
+    }
+
+    /// The conversation this message counts against for retention:
+    /// one per zone for zone chat, one per group for party/guild/custom channels
+    pub fn retention_scope(&self) -> ChatScope {
+        match self.channel_type() {
+            ChatChannelType::Zone => ChatScope::Zone(self.zone.clone()),
+            ChatChannelType::Party | ChatChannelType::Guild | ChatChannelType::Custom => ChatScope::Group(self.group_id),
+            ChatChannelType::Global | ChatChannelType::System => ChatScope::Global,
+        }
+    }
+
+    /// Archive the oldest messages in this message's conversation beyond its policy's max_count
+    /// Called as each message is posted, so a conversation never holds more than one extra
+    /// Returns the number of messages archived
+    pub fn enforce_count_limit(ctx: &ReducerContext, msg: &ChatMessage) -> usize {
+        let max_count = ctx.db.chat_retention_policy().channel_type().find(&msg.channel_type().to_string())
+            .map(|policy| policy.max_count as usize)
+            .unwrap_or(0);
+        if max_count == 0 {
+            return 0;
+        }
+
+        let mut messages = Self::get_conversation(ctx, &msg.channel, &msg.retention_scope());
+        if messages.len() <= max_count {
+            return 0;
+        }
+
+        messages.sort_by(|a, b| (a.timestamp, a.message_id).cmp(&(b.timestamp, b.message_id)));
+        messages.truncate(messages.len() - max_count);
+        Self::archive_messages(ctx, &messages);
+        messages.len()
+    }
+
+    /// Archive messages older than their channel type's policy allows
+    /// Each policy only reads messages up to its own cutoff from the time indexes
+    /// Returns the number of messages archived
+    pub fn enforce_retention(ctx: &ReducerContext) -> usize {
+        let mut expired = Vec::new();
+
+        for policy in ctx.db.chat_retention_policy().iter() {
+            let Some(channel_type) = ChatChannelType::from_string(&policy.channel_type) else {
+                continue;
+            };
+            if policy.max_age_seconds == 0 {
+                continue;
+            }
+
+            let cutoff = ctx.timestamp - std::time::Duration::from_secs(policy.max_age_seconds);
+            match channel_type {
+                // Custom channels each have their own name, so walk the shared time index
+                ChatChannelType::Custom => expired.extend(ctx.db.chatmessage().timestamp()
+                    .filter(..cutoff)
+                    .filter(|msg| msg.channel_type() == ChatChannelType::Custom)),
+                _ => expired.extend(ctx.db.chatmessage().channel_time()
+                    .filter((policy.channel_type.as_str(), ..cutoff))),
+            }
+        }
+
+        Self::archive_messages(ctx, &expired);
+        expired.len()
+    }
+}
+
+impl ChatMessageArchive {
+    /// Find an archived message by ID
+    pub fn filter_by_message_id(ctx: &ReducerContext, message_id: u64) -> Option<ChatMessageArchive> {
+        ctx.db.chatmessage_archive().message_id().find(&message_id)
+    }
+}
+
+impl ChatRetentionSchedule {
+    /// Start the retention job if it is not already scheduled
+    pub fn ensure_scheduled(ctx: &ReducerContext) {
+        if ctx.db.chat_retention_schedule().count() == 0 {
+            let interval = std::time::Duration::from_secs(CHAT_RETENTION_INTERVAL_SECONDS);
+            ctx.db.chat_retention_schedule().insert(ChatRetentionSchedule {
+                scheduled_id: 0, // auto_inc
+                scheduled_at: ScheduleAt::Interval(interval.into()),
+            });
+        }
+    }
+}
+
+impl ChatRetentionPolicy {
+    /// Create or replace the policy for a channel type
+    pub fn set(ctx: &ReducerContext, channel_type: &ChatChannelType, max_age_seconds: u64, max_count: u32) {
+        let channel_type = channel_type.to_string();
+        ctx.db.chat_retention_policy().channel_type().delete(&channel_type);
+        ctx.db.chat_retention_policy().insert(ChatRetentionPolicy {
+            channel_type,
+            max_age_seconds,
+            max_count,
+        });
+    }
+
+    /// Install the default policies for channel types that have none yet
+    pub fn seed_defaults(ctx: &ReducerContext) {
+        for (channel_type, max_age_seconds, max_count) in DEFAULT_CHAT_RETENTION {
+            let exists = ctx.db.chat_retention_policy().channel_type().find(&channel_type.to_string()).is_some();
+            if let (false, Some(channel_type)) = (exists, ChatChannelType::from_string(channel_type)) {
+                Self::set(ctx, &channel_type, max_age_seconds, max_count);
+            }
+        }
+    }
+}
+
+/// Global chat is visible to every client
+#[client_visibility_filter]
+const CHAT_GLOBAL_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM chatmessage WHERE channel = 'global'"
+);
+
+/// Server announcements are visible to every client
+#[client_visibility_filter]
+const CHAT_SYSTEM_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM chatmessage WHERE channel = 'system'"
+);
+
+/// Zone chat is only visible to players currently standing in that zone
+#[client_visibility_filter]
+const CHAT_ZONE_VISIBILITY: Filter = Filter::Sql(
+    "SELECT chatmessage.* FROM chatmessage \
+     JOIN player ON chatmessage.zone = player.current_zone \
+     WHERE chatmessage.channel = 'zone' AND player.identity = :sender"
+);
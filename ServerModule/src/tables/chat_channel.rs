@@ -0,0 +1,220 @@
+//! User-created chat channel table definitions
+
+use spacetimedb::{table, client_visibility_filter, Filter, Identity, Timestamp, ReducerContext, Table};
+use super::chat::chatmessage;
+use super::chat_moderation::ChatChannelSettings;
+
+/// A player-created chat channel
+/// Private because it holds the password hash; members see their ChatChannelMember rows
+#[derive(Clone, Debug)]
+#[table(name = chat_channel)]
+pub struct ChatChannel {
+    #[primary_key]
+    #[auto_inc]
+    pub channel_id: u64,
+
+    /// Lowercase channel name, also used as ChatMessage.channel
+    #[unique]
+    pub name: String,
+
+    /// Current owner (can promote moderators, kick anyone)
+    pub owner_identity: Identity,
+
+    /// Password hash and salt; empty when the channel is open
+    pub password_hash: String,
+    pub password_salt: String,
+
+    pub created_at: Timestamp,
+}
+
+/// Membership of a player in a chat channel
+#[derive(Clone, Debug)]
+#[table(
+    name = chat_channel_member,
+    public,
+    index(name = channel_member, btree(columns = [channel_id, member_identity]))
+)]
+pub struct ChatChannelMember {
+    #[primary_key]
+    #[auto_inc]
+    pub membership_id: u64,
+
+    pub channel_id: u64,
+
+    /// Copy of ChatChannel.name so subscriptions can match messages to memberships
+    pub channel_name: String,
+
+    #[index(btree)]
+    pub member_identity: Identity,
+
+    pub member_username: String,
+
+    /// "owner", "moderator" or "member"
+    pub role: String,
+
+    pub joined_at: Timestamp,
+}
+
+/// Pending invitation to a chat channel (lets the invitee skip the password)
+#[derive(Clone, Debug)]
+#[table(
+    name = chat_channel_invite,
+    public,
+    index(name = channel_invitee, btree(columns = [channel_id, invitee_identity]))
+)]
+pub struct ChatChannelInvite {
+    #[primary_key]
+    #[auto_inc]
+    pub invite_id: u64,
+
+    pub channel_id: u64,
+    pub channel_name: String,
+
+    #[index(btree)]
+    pub invitee_identity: Identity,
+
+    pub invited_by: Identity,
+    pub created_at: Timestamp,
+}
+
+/// Roles a channel member can hold
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelRole {
+    Owner,
+    Moderator,
+    Member,
+}
+
+impl std::fmt::Display for ChannelRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ChannelRole::Owner => "owner",
+            ChannelRole::Moderator => "moderator",
+            ChannelRole::Member => "member",
+        })
+    }
+}
+
+impl ChannelRole {
+    pub fn from_string(s: &str) -> ChannelRole {
+        match s {
+            "owner" => ChannelRole::Owner,
+            "moderator" => ChannelRole::Moderator,
+            _ => ChannelRole::Member,
+        }
+    }
+
+    /// Owners and moderators can kick and mute
+    pub fn can_moderate(&self) -> bool {
+        matches!(self, ChannelRole::Owner | ChannelRole::Moderator)
+    }
+
+    /// Whether this role may kick or mute someone holding the other role
+    pub fn outranks(&self, other: &ChannelRole) -> bool {
+        self.rank() > other.rank()
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            ChannelRole::Owner => 2,
+            ChannelRole::Moderator => 1,
+            ChannelRole::Member => 0,
+        }
+    }
+}
+
+impl ChatChannel {
+    /// Find channel by name
+    pub fn filter_by_name(ctx: &ReducerContext, name: &str) -> Option<ChatChannel> {
+        ctx.db.chat_channel().name().find(&name.to_string())
+    }
+
+    /// Delete a channel with all its memberships, invites, settings and messages
+    pub fn delete_channel(ctx: &ReducerContext, channel: &ChatChannel) {
+        ctx.db.chat_channel_member().channel_member().delete(channel.channel_id);
+        ctx.db.chat_channel_invite().channel_invitee().delete(channel.channel_id);
+        ChatChannelSettings::clear(ctx, &channel.name);
+
+        let message_ids: Vec<u64> = ctx.db.chatmessage().channel_time()
+            .filter(channel.name.as_str())
+            .map(|msg| msg.message_id)
+            .collect();
+        for message_id in &message_ids {
+            ctx.db.chatmessage().message_id().delete(message_id);
+        }
+
+        ctx.db.chat_channel().channel_id().delete(&channel.channel_id);
+    }
+}
+
+impl ChatChannelMember {
+    /// Find a player's membership in a channel
+    pub fn find(ctx: &ReducerContext, channel_id: u64, member_identity: &Identity) -> Option<ChatChannelMember> {
+        ctx.db.chat_channel_member().channel_member()
+            .filter((channel_id, member_identity))
+            .next()
+    }
+
+    /// Get all members of a channel
+    pub fn get_members(ctx: &ReducerContext, channel_id: u64) -> Vec<ChatChannelMember> {
+        ctx.db.chat_channel_member().channel_member().filter(channel_id).collect()
+    }
+
+    /// Count the channels a player belongs to
+    pub fn count_for_player(ctx: &ReducerContext, member_identity: &Identity) -> usize {
+        ctx.db.chat_channel_member().member_identity().filter(member_identity).count()
+    }
+
+    /// Add a player to a channel
+    pub fn add_member(
+        ctx: &ReducerContext,
+        channel: &ChatChannel,
+        member_identity: Identity,
+        member_username: String,
+        role: ChannelRole
+    ) {
+        ctx.db.chat_channel_member().insert(ChatChannelMember {
+            membership_id: 0, // auto_inc
+            channel_id: channel.channel_id,
+            channel_name: channel.name.clone(),
+            member_identity,
+            member_username,
+            role: role.to_string(),
+            joined_at: ctx.timestamp,
+        });
+    }
+
+    /// Get this member's role
+    pub fn role(&self) -> ChannelRole {
+        ChannelRole::from_string(&self.role)
+    }
+}
+
+impl ChatChannelInvite {
+    /// Find a pending invite for a player
+    pub fn find(ctx: &ReducerContext, channel_id: u64, invitee_identity: &Identity) -> Option<ChatChannelInvite> {
+        ctx.db.chat_channel_invite().channel_invitee()
+            .filter((channel_id, invitee_identity))
+            .next()
+    }
+}
+
+/// Players only see their own channel memberships
+#[client_visibility_filter]
+const CHAT_CHANNEL_MEMBER_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM chat_channel_member WHERE member_identity = :sender"
+);
+
+/// Players only see invites addressed to them
+#[client_visibility_filter]
+const CHAT_CHANNEL_INVITE_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM chat_channel_invite WHERE invitee_identity = :sender"
+);
+
+/// Custom channel messages are only visible to that channel's members
+#[client_visibility_filter]
+const CHAT_CUSTOM_CHANNEL_VISIBILITY: Filter = Filter::Sql(
+    "SELECT chatmessage.* FROM chatmessage \
+     JOIN chat_channel_member ON chatmessage.channel = chat_channel_member.channel_name \
+     WHERE chat_channel_member.member_identity = :sender"
+);
@@ -0,0 +1,121 @@
+//! Chat word filter configuration and flagged message log
+
+use spacetimedb::{table, Identity, Timestamp, ReducerContext, Table};
+use shared_module::*;
+
+/// A word filter rule that can be changed at runtime by admins
+/// Private so players cannot read the list and work around it
+#[derive(Clone, Debug)]
+#[table(name = chat_word_filter)]
+pub struct ChatWordFilter {
+    #[primary_key]
+    #[auto_inc]
+    pub filter_id: u64,
+
+    /// Word or phrase to match (case-insensitive, whole words)
+    #[unique]
+    pub pattern: String,
+
+    /// "mask", "block" or "flag"
+    pub action: String,
+
+    /// Who added the rule and when
+    pub created_by: Identity,
+    pub created_at: Timestamp,
+}
+
+/// A message that matched a "flag" rule, kept for moderator review
+#[derive(Clone, Debug)]
+#[table(name = chat_flag)]
+pub struct ChatFlag {
+    #[primary_key]
+    #[auto_inc]
+    pub flag_id: u64,
+
+    #[index(btree)]
+    pub sender_identity: Identity,
+
+    /// Channel name, or "whisper" for direct messages
+    pub channel: String,
+
+    /// ChatMessage or DirectMessage ID the flag refers to
+    pub message_id: u64,
+
+    /// Copy of the message as delivered
+    pub message: String,
+
+    /// Comma-separated list of the patterns that matched
+    pub matched_patterns: String,
+
+    pub timestamp: Timestamp,
+
+    /// Whether a moderator has looked at this flag yet
+    pub reviewed: bool,
+}
+
+impl ChatWordFilter {
+    /// Get all active rules in the form the shared filter pipeline expects
+    pub fn get_rules(ctx: &ReducerContext) -> Vec<WordFilterRule> {
+        ctx.db.chat_word_filter().iter()
+            .filter_map(|rule| {
+                WordFilterAction::from_string(&rule.action).map(|action| WordFilterRule {
+                    pattern: rule.pattern,
+                    action,
+                })
+            })
+            .collect()
+    }
+
+    /// Add a rule, or change the action of an existing one
+    pub fn upsert_rule(
+        ctx: &ReducerContext,
+        pattern: String,
+        action: WordFilterAction,
+        created_by: Identity,
+        timestamp: Timestamp
+    ) {
+        if let Some(mut existing) = ctx.db.chat_word_filter().pattern().find(&pattern) {
+            existing.action = action.to_string();
+            existing.created_by = created_by;
+            existing.created_at = timestamp;
+            ctx.db.chat_word_filter().filter_id().update(existing);
+        } else {
+            ctx.db.chat_word_filter().insert(ChatWordFilter {
+                filter_id: 0, // auto_inc
+                pattern,
+                action: action.to_string(),
+                created_by,
+                created_at: timestamp,
+            });
+        }
+    }
+
+    /// Remove a rule by pattern, returning whether it existed
+    pub fn remove_rule(ctx: &ReducerContext, pattern: &str) -> bool {
+        ctx.db.chat_word_filter().pattern().delete(&pattern.to_string())
+    }
+}
+
+impl ChatFlag {
+    /// Record that a delivered message matched one or more "flag" rules
+    pub fn record(
+        ctx: &ReducerContext,
+        sender_identity: Identity,
+        channel: String,
+        message_id: u64,
+        message: String,
+        matched_patterns: &[String],
+        timestamp: Timestamp
+    ) {
+        ctx.db.chat_flag().insert(ChatFlag {
+            flag_id: 0, // auto_inc
+            sender_identity,
+            channel,
+            message_id,
+            message,
+            matched_patterns: matched_patterns.join(","),
+            timestamp,
+            reviewed: false,
+        });
+    }
+}
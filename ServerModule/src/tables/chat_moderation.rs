@@ -0,0 +1,296 @@
+//! Chat anti-spam and mute table definitions
+
+use spacetimedb::{table, client_visibility_filter, Filter, Identity, Timestamp, ReducerContext, Table};
+use super::chat::ChatMessage;
+
+/// Recently sent message, kept briefly for duplicate detection
+#[derive(Clone, Debug)]
+#[table(name = chat_recent_message)]
+pub struct ChatRecentMessage {
+    #[primary_key]
+    #[auto_inc]
+    pub entry_id: u64,
+
+    #[index(btree)]
+    pub sender_identity: Identity,
+
+    /// Duplicates only count within the channel they were sent to
+    pub channel: String,
+
+    /// Message reduced with normalize_for_spam_check
+    pub normalized: String,
+
+    #[index(btree)]
+    pub timestamp: Timestamp,
+}
+
+/// A recorded anti-spam violation, kept for moderator review
+#[derive(Clone, Debug)]
+#[table(name = chat_violation, public)]
+pub struct ChatViolation {
+    #[primary_key]
+    #[auto_inc]
+    pub violation_id: u64,
+
+    #[index(btree)]
+    pub identity: Identity,
+
+    /// "flood" or "duplicate"
+    pub kind: String,
+
+    pub channel: String,
+
+    /// The offending message as submitted (after sanitization)
+    pub message: String,
+
+    pub timestamp: Timestamp,
+}
+
+/// A chat mute; rows are kept after expiry as history
+#[derive(Clone, Debug)]
+#[table(name = chat_mute)]
+pub struct ChatMute {
+    #[primary_key]
+    #[auto_inc]
+    pub mute_id: u64,
+
+    #[index(btree)]
+    pub identity: Identity,
+
+    /// Channel the mute applies to; empty means every channel
+    pub channel: String,
+
+    pub muted_until: Timestamp,
+
+    pub reason: String,
+
+    /// Moderator who issued it, or the module identity for automatic mutes
+    pub issued_by: Identity,
+
+    pub created_at: Timestamp,
+}
+
+/// Audit record left behind when a moderator deletes a chat message
+#[derive(Clone, Debug)]
+#[table(name = chat_message_tombstone)]
+pub struct ChatMessageTombstone {
+    #[primary_key]
+    #[auto_inc]
+    pub tombstone_id: u64,
+
+    /// ID of the deleted ChatMessage
+    #[index(btree)]
+    pub message_id: u64,
+
+    /// Copy of the deleted message
+    pub channel: String,
+    pub sender_identity: Identity,
+    pub sender_username: String,
+    pub message: String,
+    pub sent_at: Timestamp,
+
+    /// Moderator who deleted it, why and when
+    pub deleted_by: Identity,
+    pub reason: String,
+    pub deleted_at: Timestamp,
+}
+
+/// Moderator restrictions on a channel; channels without a row are unrestricted
+/// Public so clients can show that a channel is locked or in slow mode
+#[derive(Clone, Debug)]
+#[table(name = chat_channel_settings, public)]
+pub struct ChatChannelSettings {
+    /// Channel name ("global", "zone", "whisper" or a custom channel)
+    #[primary_key]
+    pub channel: String,
+
+    /// Only moderators may post
+    pub read_only: bool,
+
+    /// Minimum seconds between messages from the same player (0 = off)
+    pub slow_mode_seconds: u32,
+
+    pub updated_by: Identity,
+    pub updated_at: Timestamp,
+}
+
+impl ChatRecentMessage {
+    /// Get a sender's recent messages in a channel, newest first
+    pub fn get_for_sender(ctx: &ReducerContext, sender_identity: &Identity, channel: &str) -> Vec<ChatRecentMessage> {
+        let mut entries: Vec<ChatRecentMessage> = ctx.db.chat_recent_message().sender_identity()
+            .filter(sender_identity)
+            .filter(|entry| entry.channel == channel)
+            .collect();
+
+        entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        entries
+    }
+
+    /// Remember a message, dropping entries outside the window or beyond the history size
+    pub fn remember(
+        ctx: &ReducerContext,
+        sender_identity: Identity,
+        channel: &str,
+        normalized: String,
+        window_start: Timestamp,
+        history_size: usize
+    ) {
+        let existing = Self::get_for_sender(ctx, &sender_identity, channel);
+
+        // Keep room for the new entry within the history size
+        for (index, entry) in existing.iter().enumerate() {
+            if entry.timestamp < window_start || index + 1 >= history_size {
+                ctx.db.chat_recent_message().entry_id().delete(&entry.entry_id);
+            }
+        }
+
+        ctx.db.chat_recent_message().insert(ChatRecentMessage {
+            entry_id: 0, // auto_inc
+            sender_identity,
+            channel: channel.to_string(),
+            normalized,
+            timestamp: ctx.timestamp,
+        });
+    }
+
+    /// Delete entries older than the cutoff, returning how many were removed
+    pub fn cleanup_before(ctx: &ReducerContext, cutoff: Timestamp) -> usize {
+        ctx.db.chat_recent_message().timestamp().delete(..cutoff) as usize
+    }
+}
+
+impl ChatViolation {
+    /// Record a violation
+    pub fn record(ctx: &ReducerContext, identity: Identity, kind: &str, channel: &str, message: &str) {
+        ctx.db.chat_violation().insert(ChatViolation {
+            violation_id: 0, // auto_inc
+            identity,
+            kind: kind.to_string(),
+            channel: channel.to_string(),
+            message: message.to_string(),
+            timestamp: ctx.timestamp,
+        });
+    }
+
+    /// Count an identity's violations since the given time
+    pub fn count_since(ctx: &ReducerContext, identity: &Identity, since: Timestamp) -> usize {
+        ctx.db.chat_violation().identity()
+            .filter(identity)
+            .filter(|violation| violation.timestamp >= since)
+            .count()
+    }
+}
+
+impl ChatMute {
+    /// Find the longest-running mute that currently silences an identity in a channel
+    pub fn find_active(ctx: &ReducerContext, identity: &Identity, channel: &str) -> Option<ChatMute> {
+        ctx.db.chat_mute().identity()
+            .filter(identity)
+            .filter(|mute| mute.muted_until > ctx.timestamp)
+            .filter(|mute| mute.channel.is_empty() || mute.channel == channel)
+            .max_by_key(|mute| mute.muted_until)
+    }
+
+    /// Count mutes ever issued to an identity by a given issuer
+    pub fn count_issued_by(ctx: &ReducerContext, identity: &Identity, issued_by: &Identity) -> usize {
+        ctx.db.chat_mute().identity()
+            .filter(identity)
+            .filter(|mute| mute.issued_by == *issued_by)
+            .count()
+    }
+
+    /// Mute an identity until the given time
+    pub fn create_mute(
+        ctx: &ReducerContext,
+        identity: Identity,
+        channel: String,
+        muted_until: Timestamp,
+        reason: String,
+        issued_by: Identity
+    ) -> ChatMute {
+        ctx.db.chat_mute().insert(ChatMute {
+            mute_id: 0, // auto_inc
+            identity,
+            channel,
+            muted_until,
+            reason,
+            issued_by,
+            created_at: ctx.timestamp,
+        })
+    }
+
+    /// End an identity's active mutes now, in one channel or (with None) everywhere
+    /// Rows are kept as history; returns how many mutes were lifted
+    pub fn lift(ctx: &ReducerContext, identity: &Identity, channel: Option<&str>) -> usize {
+        let active: Vec<ChatMute> = ctx.db.chat_mute().identity()
+            .filter(identity)
+            .filter(|mute| mute.muted_until > ctx.timestamp)
+            .filter(|mute| channel.is_none_or(|channel| mute.channel == channel))
+            .collect();
+
+        for mut mute in active.iter().cloned() {
+            mute.muted_until = ctx.timestamp;
+            ctx.db.chat_mute().mute_id().update(mute);
+        }
+
+        active.len()
+    }
+}
+
+impl ChatMessageTombstone {
+    /// Record the deletion of a message
+    pub fn record(ctx: &ReducerContext, deleted: &ChatMessage, deleted_by: Identity, reason: String) {
+        ctx.db.chat_message_tombstone().insert(ChatMessageTombstone {
+            tombstone_id: 0, // auto_inc
+            message_id: deleted.message_id,
+            channel: deleted.channel.clone(),
+            sender_identity: deleted.sender_identity,
+            sender_username: deleted.sender_username.clone(),
+            message: deleted.message.clone(),
+            sent_at: deleted.timestamp,
+            deleted_by,
+            reason,
+            deleted_at: ctx.timestamp,
+        });
+    }
+}
+
+impl ChatChannelSettings {
+    /// Find the restrictions on a channel
+    pub fn find(ctx: &ReducerContext, channel: &str) -> Option<ChatChannelSettings> {
+        ctx.db.chat_channel_settings().channel().find(&channel.to_string())
+    }
+
+    /// Set the restrictions on a channel; clearing both removes the row
+    pub fn set(
+        ctx: &ReducerContext,
+        channel: String,
+        read_only: bool,
+        slow_mode_seconds: u32,
+        updated_by: Identity
+    ) {
+        ctx.db.chat_channel_settings().channel().delete(&channel);
+
+        if read_only || slow_mode_seconds > 0 {
+            ctx.db.chat_channel_settings().insert(ChatChannelSettings {
+                channel,
+                read_only,
+                slow_mode_seconds,
+                updated_by,
+                updated_at: ctx.timestamp,
+            });
+        }
+    }
+
+    /// Remove any restrictions on a channel
+    pub fn clear(ctx: &ReducerContext, channel: &str) {
+        ctx.db.chat_channel_settings().channel().delete(&channel.to_string());
+    }
+}
+
+/// Chat violations are only visible to staff
+#[client_visibility_filter]
+const CHAT_VIOLATION_STAFF_VISIBILITY: Filter = Filter::Sql(
+    "SELECT chat_violation.* FROM chat_violation \
+     JOIN staff_member WHERE staff_member.identity = :sender"
+);
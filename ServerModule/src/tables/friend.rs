@@ -0,0 +1,194 @@
+//! Friends list and ignore list table definitions
+
+use spacetimedb::{table, client_visibility_filter, Filter, Identity, Timestamp, ReducerContext, Table};
+use super::notification::PlayerNotification;
+use super::player::Player;
+
+/// One side of a friendship; every friendship is stored as two rows, one per player
+/// Online state and zone come from the friend's Player row; clients subscribe to
+/// `SELECT player.* FROM player JOIN friendship ON player.identity = friendship.friend_identity`
+#[derive(Clone, Debug)]
+#[table(name = friendship, public)]
+pub struct Friendship {
+    #[primary_key]
+    #[auto_inc]
+    pub friendship_id: u64,
+
+    /// Whose friends list this row belongs to
+    #[index(btree)]
+    pub player_identity: Identity,
+
+    #[index(btree)]
+    pub friend_identity: Identity,
+    pub friend_username: String,
+
+    pub created_at: Timestamp,
+}
+
+/// A friend request waiting for the recipient's answer
+#[derive(Clone, Debug)]
+#[table(name = friend_request, public)]
+pub struct FriendRequest {
+    #[primary_key]
+    #[auto_inc]
+    pub request_id: u64,
+
+    #[index(btree)]
+    pub sender_identity: Identity,
+    pub sender_username: String,
+
+    #[index(btree)]
+    pub recipient_identity: Identity,
+    pub recipient_username: String,
+
+    pub created_at: Timestamp,
+}
+
+/// A player someone has chosen to ignore
+/// Whispers, invites and trade requests from ignored players are dropped silently
+#[derive(Clone, Debug)]
+#[table(name = ignored_player, public)]
+pub struct IgnoredPlayer {
+    #[primary_key]
+    #[auto_inc]
+    pub ignore_id: u64,
+
+    /// The player doing the ignoring
+    #[index(btree)]
+    pub player_identity: Identity,
+
+    pub ignored_identity: Identity,
+    pub ignored_username: String,
+
+    pub created_at: Timestamp,
+}
+
+impl Friendship {
+    /// Get a player's friends list
+    pub fn get_friends(ctx: &ReducerContext, identity: &Identity) -> Vec<Friendship> {
+        ctx.db.friendship().player_identity().filter(identity).collect()
+    }
+
+    /// Find one entry in a player's friends list
+    pub fn find(ctx: &ReducerContext, identity: &Identity, friend: &Identity) -> Option<Friendship> {
+        ctx.db.friendship().player_identity()
+            .filter(identity)
+            .find(|row| row.friend_identity == *friend)
+    }
+
+    /// Whether two players are friends
+    pub fn are_friends(ctx: &ReducerContext, a: &Identity, b: &Identity) -> bool {
+        Self::find(ctx, a, b).is_some()
+    }
+
+    /// Make two players friends
+    pub fn add(
+        ctx: &ReducerContext,
+        a: Identity,
+        a_username: String,
+        b: Identity,
+        b_username: String
+    ) {
+        for (player_identity, friend_identity, friend_username) in [(a, b, b_username), (b, a, a_username)] {
+            ctx.db.friendship().insert(Friendship {
+                friendship_id: 0, // auto_inc
+                player_identity,
+                friend_identity,
+                friend_username,
+                created_at: ctx.timestamp,
+            });
+        }
+    }
+
+    /// End a friendship on both sides
+    /// Returns whether the two were friends
+    pub fn remove(ctx: &ReducerContext, a: &Identity, b: &Identity) -> bool {
+        let rows: Vec<Friendship> = [Self::find(ctx, a, b), Self::find(ctx, b, a)]
+            .into_iter()
+            .flatten()
+            .collect();
+
+        for row in &rows {
+            ctx.db.friendship().friendship_id().delete(&row.friendship_id);
+        }
+
+        !rows.is_empty()
+    }
+
+    /// Let everyone who has this player as a friend know they came online or went offline
+    pub fn notify_presence(ctx: &ReducerContext, player: &Player) {
+        let message = if player.is_online {
+            format!("{} is now online", player.username)
+        } else {
+            format!("{} has gone offline", player.username)
+        };
+
+        for row in ctx.db.friendship().friend_identity().filter(&player.identity) {
+            PlayerNotification::send(ctx, row.player_identity, "friends", message.clone());
+        }
+    }
+}
+
+impl FriendRequest {
+    /// Find a pending request from one player to another
+    pub fn find(ctx: &ReducerContext, sender: &Identity, recipient: &Identity) -> Option<FriendRequest> {
+        ctx.db.friend_request().recipient_identity()
+            .filter(recipient)
+            .find(|request| request.sender_identity == *sender)
+    }
+
+    /// Count the requests a player has sent that are still unanswered
+    pub fn count_pending_from(ctx: &ReducerContext, sender: &Identity) -> usize {
+        ctx.db.friend_request().sender_identity().filter(sender).count()
+    }
+
+    /// Drop any pending requests between two players, in either direction
+    pub fn clear_between(ctx: &ReducerContext, a: &Identity, b: &Identity) {
+        for request in [Self::find(ctx, a, b), Self::find(ctx, b, a)].into_iter().flatten() {
+            ctx.db.friend_request().request_id().delete(&request.request_id);
+        }
+    }
+}
+
+impl IgnoredPlayer {
+    /// Get a player's ignore list
+    pub fn get_ignored(ctx: &ReducerContext, identity: &Identity) -> Vec<IgnoredPlayer> {
+        ctx.db.ignored_player().player_identity().filter(identity).collect()
+    }
+
+    /// Find one entry in a player's ignore list
+    pub fn find(ctx: &ReducerContext, identity: &Identity, ignored: &Identity) -> Option<IgnoredPlayer> {
+        ctx.db.ignored_player().player_identity()
+            .filter(identity)
+            .find(|row| row.ignored_identity == *ignored)
+    }
+
+    /// Whether a player is ignoring another
+    pub fn is_ignoring(ctx: &ReducerContext, identity: &Identity, other: &Identity) -> bool {
+        Self::find(ctx, identity, other).is_some()
+    }
+}
+
+/// Players only see their own friends list
+#[client_visibility_filter]
+const FRIENDSHIP_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM friendship WHERE player_identity = :sender"
+);
+
+/// Players see the friend requests they sent
+#[client_visibility_filter]
+const FRIEND_REQUEST_SENT_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM friend_request WHERE sender_identity = :sender"
+);
+
+/// Players see the friend requests they received
+#[client_visibility_filter]
+const FRIEND_REQUEST_RECEIVED_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM friend_request WHERE recipient_identity = :sender"
+);
+
+/// Players only see their own ignore list; the ignored player is never told
+#[client_visibility_filter]
+const IGNORED_PLAYER_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM ignored_player WHERE player_identity = :sender"
+);
@@ -0,0 +1,406 @@
+//! Guild table definitions
+
+use spacetimedb::{table, client_visibility_filter, Filter, Identity, Timestamp, ReducerContext, Table};
+use shared_module::*;
+
+/// A player guild
+#[derive(Clone, Debug)]
+#[table(name = guild, public)]
+pub struct Guild {
+    #[primary_key]
+    #[auto_inc]
+    pub guild_id: u64,
+
+    /// Display name as chosen at creation
+    pub name: String,
+
+    /// Lowercase name, used to keep names unique regardless of case
+    #[unique]
+    pub name_key: String,
+
+    pub leader_identity: Identity,
+
+    /// Message of the day shown to members
+    pub motd: String,
+
+    pub created_at: Timestamp,
+}
+
+/// A rank within a guild; lower rank_order is more senior (0 is the guild master)
+#[derive(Clone, Debug)]
+#[table(name = guild_rank, public)]
+pub struct GuildRank {
+    #[primary_key]
+    #[auto_inc]
+    pub rank_id: u64,
+
+    #[index(btree)]
+    pub guild_id: u64,
+
+    pub name: String,
+    pub rank_order: u32,
+
+    /// GUILD_PERM_* bits
+    pub permissions: u32,
+
+    /// Items a member of this rank may take from the guild bank per day
+    /// Ignored for the guild master, who may always withdraw
+    pub daily_withdraw_limit: u32,
+}
+
+/// A player's membership in a guild
+#[derive(Clone, Debug)]
+#[table(name = guild_member, public)]
+pub struct GuildMember {
+    #[primary_key]
+    #[auto_inc]
+    pub membership_id: u64,
+
+    #[index(btree)]
+    pub guild_id: u64,
+
+    /// A player can only be in one guild at a time
+    #[unique]
+    pub member_identity: Identity,
+
+    pub member_username: String,
+    pub rank_id: u64,
+    pub joined_at: Timestamp,
+}
+
+/// A pending invitation to join a guild
+#[derive(Clone, Debug)]
+#[table(name = guild_invite, public)]
+pub struct GuildInvite {
+    #[primary_key]
+    #[auto_inc]
+    pub invite_id: u64,
+
+    #[index(btree)]
+    pub guild_id: u64,
+    pub guild_name: String,
+
+    #[index(btree)]
+    pub invitee_identity: Identity,
+
+    pub invited_by: Identity,
+    pub created_at: Timestamp,
+}
+
+/// An item stack held in a guild's shared bank
+/// Mirrors the player inventory model; deposits and withdrawals live in CustomServerModule
+#[derive(Clone, Debug)]
+#[table(name = guild_bank_item, public)]
+pub struct GuildBankItem {
+    #[primary_key]
+    #[auto_inc]
+    pub bank_item_id: u64,
+
+    #[index(btree)]
+    pub guild_id: u64,
+
+    pub item_type: String,
+    pub item_id: String,
+    pub quantity: u32,
+    pub slot_index: u32,
+    pub instance_id: Option<u64>, // Set when the slot holds a unique item instance
+}
+
+/// A deposit into or withdrawal from a guild bank
+/// Withdrawals are summed per member to enforce the rank's daily limit
+#[derive(Clone, Debug)]
+#[table(name = guild_bank_log, public)]
+pub struct GuildBankLog {
+    #[primary_key]
+    #[auto_inc]
+    pub log_id: u64,
+
+    #[index(btree)]
+    pub guild_id: u64,
+
+    #[index(btree)]
+    pub member_identity: Identity,
+    pub member_username: String,
+
+    /// "deposit" or "withdraw"
+    pub action: String,
+    pub item_id: String,
+    pub quantity: u32,
+    pub timestamp: Timestamp,
+}
+
+impl Guild {
+    /// Find guild by ID
+    pub fn filter_by_guild_id(ctx: &ReducerContext, guild_id: u64) -> Option<Guild> {
+        ctx.db.guild().guild_id().find(&guild_id)
+    }
+
+    /// Find guild by name (case-insensitive)
+    pub fn filter_by_name(ctx: &ReducerContext, name: &str) -> Option<Guild> {
+        ctx.db.guild().name_key().find(&name.to_lowercase())
+    }
+
+    /// Create a guild with the default ranks and its founder as guild master
+    pub fn create_guild(
+        ctx: &ReducerContext,
+        name: String,
+        founder_identity: Identity,
+        founder_username: String
+    ) -> Guild {
+        let guild = ctx.db.guild().insert(Guild {
+            guild_id: 0, // auto_inc
+            name_key: name.to_lowercase(),
+            name,
+            leader_identity: founder_identity,
+            motd: String::new(),
+            created_at: ctx.timestamp,
+        });
+
+        let default_ranks = [
+            ("Guild Master", GUILD_PERM_ALL, u32::MAX),
+            ("Officer", GUILD_PERM_ALL & !GUILD_PERM_MANAGE_RANKS, 50),
+            ("Member", GUILD_PERM_SPEAK | GUILD_PERM_BANK_DEPOSIT | GUILD_PERM_BANK_WITHDRAW, 5),
+            ("Initiate", GUILD_PERM_SPEAK | GUILD_PERM_BANK_DEPOSIT, 0),
+        ];
+
+        let mut master_rank_id = 0;
+        for (rank_order, (rank_name, permissions, daily_withdraw_limit)) in default_ranks.into_iter().enumerate() {
+            let rank = GuildRank::create_rank(
+                ctx,
+                guild.guild_id,
+                rank_name.to_string(),
+                rank_order as u32,
+                permissions,
+                daily_withdraw_limit
+            );
+            if rank_order == 0 {
+                master_rank_id = rank.rank_id;
+            }
+        }
+
+        GuildMember::add_member(ctx, guild.guild_id, founder_identity, founder_username, master_rank_id);
+        guild
+    }
+
+    /// Delete a guild with its ranks, members, invites and bank history
+    /// Callers make sure the bank is empty first
+    pub fn disband(ctx: &ReducerContext, guild_id: u64) {
+        ctx.db.guild_bank_log().guild_id().delete(&guild_id);
+        ctx.db.guild_member().guild_id().delete(&guild_id);
+        ctx.db.guild_rank().guild_id().delete(&guild_id);
+        ctx.db.guild_invite().guild_id().delete(&guild_id);
+        ctx.db.guild().guild_id().delete(&guild_id);
+        log::info!("Guild {} disbanded", guild_id);
+    }
+}
+
+impl GuildRank {
+    /// Find rank by ID
+    pub fn filter_by_rank_id(ctx: &ReducerContext, rank_id: u64) -> Option<GuildRank> {
+        ctx.db.guild_rank().rank_id().find(&rank_id)
+    }
+
+    /// Get a guild's ranks, most senior first
+    pub fn get_ranks(ctx: &ReducerContext, guild_id: u64) -> Vec<GuildRank> {
+        let mut ranks: Vec<GuildRank> = ctx.db.guild_rank().guild_id().filter(&guild_id).collect();
+        ranks.sort_by_key(|rank| rank.rank_order);
+        ranks
+    }
+
+    /// Find a guild's rank by name (case-insensitive)
+    pub fn find_by_name(ctx: &ReducerContext, guild_id: u64, name: &str) -> Option<GuildRank> {
+        ctx.db.guild_rank().guild_id()
+            .filter(&guild_id)
+            .find(|rank| rank.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The most junior rank, given to new members
+    pub fn lowest_rank(ctx: &ReducerContext, guild_id: u64) -> Option<GuildRank> {
+        Self::get_ranks(ctx, guild_id).pop()
+    }
+
+    /// Add a rank to a guild
+    pub fn create_rank(
+        ctx: &ReducerContext,
+        guild_id: u64,
+        name: String,
+        rank_order: u32,
+        permissions: u32,
+        daily_withdraw_limit: u32
+    ) -> GuildRank {
+        ctx.db.guild_rank().insert(GuildRank {
+            rank_id: 0, // auto_inc
+            guild_id,
+            name,
+            rank_order,
+            permissions,
+            daily_withdraw_limit,
+        })
+    }
+
+    /// Whether this rank grants the given GUILD_PERM_* bits
+    pub fn has_permission(&self, permission: u32) -> bool {
+        self.permissions & permission == permission
+    }
+
+    /// Whether this rank sits above the other in the hierarchy
+    pub fn outranks(&self, other: &GuildRank) -> bool {
+        self.rank_order < other.rank_order
+    }
+
+    /// Whether this is the guild master's rank
+    pub fn is_guild_master(&self) -> bool {
+        self.rank_order == 0
+    }
+}
+
+impl GuildMember {
+    /// Find the guild membership of a player
+    pub fn find_by_member(ctx: &ReducerContext, identity: &Identity) -> Option<GuildMember> {
+        ctx.db.guild_member().member_identity().find(identity)
+    }
+
+    /// Get all members of a guild
+    pub fn get_members(ctx: &ReducerContext, guild_id: u64) -> Vec<GuildMember> {
+        ctx.db.guild_member().guild_id().filter(&guild_id).collect()
+    }
+
+    /// Add a player to a guild at the given rank
+    pub fn add_member(
+        ctx: &ReducerContext,
+        guild_id: u64,
+        member_identity: Identity,
+        member_username: String,
+        rank_id: u64
+    ) {
+        ctx.db.guild_member().insert(GuildMember {
+            membership_id: 0, // auto_inc
+            guild_id,
+            member_identity,
+            member_username,
+            rank_id,
+            joined_at: ctx.timestamp,
+        });
+    }
+
+    /// Get this member's rank
+    pub fn rank(&self, ctx: &ReducerContext) -> Option<GuildRank> {
+        GuildRank::filter_by_rank_id(ctx, self.rank_id)
+    }
+}
+
+impl GuildBankItem {
+    /// Get the stacks held in a guild's bank
+    pub fn get_items(ctx: &ReducerContext, guild_id: u64) -> Vec<GuildBankItem> {
+        ctx.db.guild_bank_item().guild_id().filter(&guild_id).collect()
+    }
+
+    /// The lowest free bank slot, if any
+    pub fn next_free_slot(ctx: &ReducerContext, guild_id: u64) -> Option<u32> {
+        let used_slots: std::collections::HashSet<u32> = Self::get_items(ctx, guild_id)
+            .iter()
+            .map(|item| item.slot_index)
+            .collect();
+
+        (0..GUILD_BANK_SLOTS as u32).find(|slot| !used_slots.contains(slot))
+    }
+}
+
+impl GuildBankLog {
+    /// Record a bank transaction
+    pub fn record(
+        ctx: &ReducerContext,
+        guild_id: u64,
+        member: &GuildMember,
+        action: &str,
+        item_id: &str,
+        quantity: u32
+    ) {
+        ctx.db.guild_bank_log().insert(GuildBankLog {
+            log_id: 0, // auto_inc
+            guild_id,
+            member_identity: member.member_identity,
+            member_username: member.member_username.clone(),
+            action: action.to_string(),
+            item_id: item_id.to_string(),
+            quantity,
+            timestamp: ctx.timestamp,
+        });
+    }
+
+    /// Total items a member has withdrawn from a guild's bank since a point in time
+    pub fn withdrawn_since(ctx: &ReducerContext, guild_id: u64, identity: &Identity, since: Timestamp) -> u32 {
+        ctx.db.guild_bank_log().member_identity()
+            .filter(identity)
+            .filter(|entry| entry.guild_id == guild_id && entry.action == "withdraw" && entry.timestamp >= since)
+            .fold(0u32, |total, entry| total.saturating_add(entry.quantity))
+    }
+}
+
+impl GuildInvite {
+    /// Find a pending invite to a guild for a player
+    pub fn find(ctx: &ReducerContext, guild_id: u64, invitee: &Identity) -> Option<GuildInvite> {
+        ctx.db.guild_invite().invitee_identity()
+            .filter(invitee)
+            .find(|invite| invite.guild_id == guild_id)
+    }
+
+    /// Whether the invite is too old to accept
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        now.duration_since(self.created_at).unwrap_or_default().as_secs() > GUILD_INVITE_EXPIRY_SECONDS
+    }
+}
+
+/// Players only see their own guild
+#[client_visibility_filter]
+const GUILD_VISIBILITY: Filter = Filter::Sql(
+    "SELECT guild.* FROM guild \
+     JOIN guild_member ON guild.guild_id = guild_member.guild_id \
+     WHERE guild_member.member_identity = :sender"
+);
+
+/// Guild ranks are visible to the guild's members
+#[client_visibility_filter]
+const GUILD_RANK_VISIBILITY: Filter = Filter::Sql(
+    "SELECT guild_rank.* FROM guild_rank \
+     JOIN guild_member ON guild_rank.guild_id = guild_member.guild_id \
+     WHERE guild_member.member_identity = :sender"
+);
+
+/// Guild members see the rest of their guild's roster
+#[client_visibility_filter]
+const GUILD_MEMBER_VISIBILITY: Filter = Filter::Sql(
+    "SELECT member.* FROM guild_member member \
+     JOIN guild_member viewer ON member.guild_id = viewer.guild_id \
+     WHERE viewer.member_identity = :sender"
+);
+
+/// Players only see invites addressed to them
+#[client_visibility_filter]
+const GUILD_INVITE_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM guild_invite WHERE invitee_identity = :sender"
+);
+
+/// Guild chat is only visible to members of that guild
+#[client_visibility_filter]
+const CHAT_GUILD_VISIBILITY: Filter = Filter::Sql(
+    "SELECT chatmessage.* FROM chatmessage \
+     JOIN guild_member ON chatmessage.group_id = guild_member.guild_id \
+     WHERE chatmessage.channel = 'guild' AND guild_member.member_identity = :sender"
+);
+
+/// Guild bank contents are visible to the guild's members
+#[client_visibility_filter]
+const GUILD_BANK_ITEM_VISIBILITY: Filter = Filter::Sql(
+    "SELECT guild_bank_item.* FROM guild_bank_item \
+     JOIN guild_member ON guild_bank_item.guild_id = guild_member.guild_id \
+     WHERE guild_member.member_identity = :sender"
+);
+
+/// The bank log is visible to the guild's members
+#[client_visibility_filter]
+const GUILD_BANK_LOG_VISIBILITY: Filter = Filter::Sql(
+    "SELECT guild_bank_log.* FROM guild_bank_log \
+     JOIN guild_member ON guild_bank_log.guild_id = guild_member.guild_id \
+     WHERE guild_member.member_identity = :sender"
+);
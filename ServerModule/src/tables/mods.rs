@@ -3,10 +3,33 @@
 pub mod user;
 pub mod player;
 pub mod chat;
+pub mod whisper;
+pub mod chat_filter;
+pub mod chat_channel;
+pub mod chat_moderation;
+pub mod rate_limit;
+pub mod notification;
+pub mod party;
+pub mod guild;
+pub mod friend;
+pub mod report;
 pub mod session;
+pub mod staff;
 
 // Re-export all table types
 pub use user::*;
 pub use player::*;
 pub use chat::*;
-pub use session::*;
\ No newline at end of file
+pub use whisper::*;
+pub use chat_filter::*;
+pub use chat_channel::*;
+pub use chat_moderation::*;
+pub use rate_limit::*;
+pub use notification::*;
+pub use party::*;
+pub use guild::*;
+pub use friend::*;
+pub use report::*;
+pub use session::*;
+pub use staff::*;
+pub use zone::*;
\ No newline at end of file
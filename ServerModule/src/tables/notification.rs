@@ -0,0 +1,58 @@
+//! Player notification table definition
+
+use spacetimedb::{table, client_visibility_filter, Filter, Identity, Timestamp, ReducerContext, Table};
+
+/// A system notice addressed to a single player
+/// Used to explain things that happen without a reducer error, e.g. a dropped spam message
+#[derive(Clone, Debug)]
+#[table(name = player_notification, public)]
+pub struct PlayerNotification {
+    #[primary_key]
+    #[auto_inc]
+    pub notification_id: u64,
+
+    /// Who the notice is for
+    #[index(btree)]
+    pub recipient_identity: Identity,
+
+    /// Subsystem that raised it ("chat", "social", ...)
+    pub category: String,
+
+    pub message: String,
+
+    #[index(btree)]
+    pub created_at: Timestamp,
+}
+
+impl PlayerNotification {
+    /// Send a notice to a player
+    pub fn send(ctx: &ReducerContext, recipient_identity: Identity, category: &str, message: String) {
+        ctx.db.player_notification().insert(PlayerNotification {
+            notification_id: 0, // auto_inc
+            recipient_identity,
+            category: category.to_string(),
+            message,
+            created_at: ctx.timestamp,
+        });
+    }
+
+    /// Delete notices older than the cutoff time
+    pub fn cleanup_before(ctx: &ReducerContext, cutoff_time: Timestamp) -> usize {
+        let expired: Vec<u64> = ctx.db.player_notification().created_at()
+            .filter(..cutoff_time)
+            .map(|notice| notice.notification_id)
+            .collect();
+
+        for notification_id in &expired {
+            ctx.db.player_notification().notification_id().delete(notification_id);
+        }
+
+        expired.len()
+    }
+}
+
+/// Players only see their own notifications
+#[client_visibility_filter]
+const PLAYER_NOTIFICATION_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM player_notification WHERE recipient_identity = :sender"
+);
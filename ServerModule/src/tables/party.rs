@@ -0,0 +1,254 @@
+//! Party table definitions
+
+use spacetimedb::{table, client_visibility_filter, Filter, Identity, Timestamp, ReducerContext, Table};
+use shared_module::*;
+use super::player::Player;
+
+/// A group of players adventuring together
+#[derive(Clone, Debug)]
+#[table(name = party, public)]
+pub struct Party {
+    #[primary_key]
+    #[auto_inc]
+    pub party_id: u64,
+
+    pub leader_identity: Identity,
+
+    pub created_at: Timestamp,
+}
+
+/// A player's membership in a party
+/// Carries a copy of the member's position and vitals so party members can
+/// track each other wherever they are
+#[derive(Clone, Debug)]
+#[table(name = party_member, public)]
+pub struct PartyMember {
+    #[primary_key]
+    #[auto_inc]
+    pub membership_id: u64,
+
+    #[index(btree)]
+    pub party_id: u64,
+
+    /// A player can only be in one party at a time
+    #[unique]
+    pub member_identity: Identity,
+
+    pub member_username: String,
+    pub joined_at: Timestamp,
+
+    /// Copied from Player whenever it changes
+    pub zone: String,
+    pub position_x: f32,
+    pub position_y: f32,
+    pub position_z: f32,
+    pub health: f32,
+    pub max_health: f32,
+    pub level: u32,
+    pub is_online: bool,
+}
+
+/// A pending invitation to join the inviter's party
+/// The party itself is only created once the first invite is accepted
+#[derive(Clone, Debug)]
+#[table(name = party_invite, public)]
+pub struct PartyInvite {
+    #[primary_key]
+    #[auto_inc]
+    pub invite_id: u64,
+
+    pub inviter_identity: Identity,
+    pub inviter_username: String,
+
+    #[index(btree)]
+    pub invitee_identity: Identity,
+
+    pub created_at: Timestamp,
+}
+
+impl Party {
+    /// Find party by ID
+    pub fn filter_by_party_id(ctx: &ReducerContext, party_id: u64) -> Option<Party> {
+        ctx.db.party().party_id().find(&party_id)
+    }
+
+    /// Start a new party led by the given player
+    pub fn create_party(ctx: &ReducerContext, leader: &Player) -> Party {
+        let party = ctx.db.party().insert(Party {
+            party_id: 0, // auto_inc
+            leader_identity: leader.identity,
+            created_at: ctx.timestamp,
+        });
+
+        PartyMember::add_member(ctx, party.party_id, leader);
+        party
+    }
+
+    /// Remove a party and all of its memberships
+    pub fn disband(ctx: &ReducerContext, party_id: u64) {
+        ctx.db.party_member().party_id().delete(&party_id);
+        ctx.db.party().party_id().delete(&party_id);
+        log::info!("Party {} disbanded", party_id);
+    }
+
+    /// Remove a member, handing leadership on and disbanding parties that drop below two members
+    pub fn remove_member(ctx: &ReducerContext, membership: &PartyMember) {
+        ctx.db.party_member().membership_id().delete(&membership.membership_id);
+
+        let remaining = PartyMember::get_members(ctx, membership.party_id);
+        if remaining.len() < 2 {
+            Self::disband(ctx, membership.party_id);
+            return;
+        }
+
+        if let Some(mut party) = Self::filter_by_party_id(ctx, membership.party_id) {
+            if party.leader_identity == membership.member_identity {
+                if let Some(successor) = PartyMember::pick_successor(&remaining) {
+                    party.leader_identity = successor.member_identity;
+                    ctx.db.party().party_id().update(party);
+                }
+            }
+        }
+    }
+
+    /// React to a member going offline: pass leadership to someone online,
+    /// or disband once nobody in the party is online
+    pub fn handle_member_offline(ctx: &ReducerContext, identity: &Identity) {
+        let Some(membership) = PartyMember::find_by_member(ctx, identity) else {
+            return;
+        };
+
+        let online: Vec<PartyMember> = PartyMember::get_members(ctx, membership.party_id)
+            .into_iter()
+            .filter(|member| member.is_online)
+            .collect();
+
+        if online.is_empty() {
+            Self::disband(ctx, membership.party_id);
+            return;
+        }
+
+        if let Some(mut party) = Self::filter_by_party_id(ctx, membership.party_id) {
+            if party.leader_identity == *identity {
+                if let Some(successor) = PartyMember::pick_successor(&online) {
+                    party.leader_identity = successor.member_identity;
+                    ctx.db.party().party_id().update(party);
+                }
+            }
+        }
+    }
+}
+
+impl PartyMember {
+    /// Find the party membership of a player
+    pub fn find_by_member(ctx: &ReducerContext, identity: &Identity) -> Option<PartyMember> {
+        ctx.db.party_member().member_identity().find(identity)
+    }
+
+    /// Get all members of a party
+    pub fn get_members(ctx: &ReducerContext, party_id: u64) -> Vec<PartyMember> {
+        ctx.db.party_member().party_id().filter(&party_id).collect()
+    }
+
+    /// Add a player to a party
+    pub fn add_member(ctx: &ReducerContext, party_id: u64, player: &Player) {
+        ctx.db.party_member().insert(PartyMember {
+            membership_id: 0, // auto_inc
+            party_id,
+            member_identity: player.identity,
+            member_username: player.username.clone(),
+            joined_at: ctx.timestamp,
+            zone: player.current_zone.clone(),
+            position_x: player.position_x,
+            position_y: player.position_y,
+            position_z: player.position_z,
+            health: player.health,
+            max_health: player.max_health,
+            level: player.level,
+            is_online: player.is_online,
+        });
+    }
+
+    /// Refresh the copied position and vitals after the player changed
+    pub fn sync_from_player(ctx: &ReducerContext, player: &Player) {
+        if let Some(mut membership) = Self::find_by_member(ctx, &player.identity) {
+            membership.zone = player.current_zone.clone();
+            membership.position_x = player.position_x;
+            membership.position_y = player.position_y;
+            membership.position_z = player.position_z;
+            membership.health = player.health;
+            membership.max_health = player.max_health;
+            membership.level = player.level;
+            membership.is_online = player.is_online;
+            ctx.db.party_member().membership_id().update(membership);
+        }
+    }
+
+    /// Online party members in the same zone within range of a point, including the player themselves
+    pub fn get_members_in_range(
+        ctx: &ReducerContext,
+        party_id: u64,
+        zone: &str,
+        x: f32,
+        y: f32,
+        z: f32,
+        range: f32
+    ) -> Vec<PartyMember> {
+        Self::get_members(ctx, party_id)
+            .into_iter()
+            .filter(|member| member.is_online && member.zone == zone)
+            .filter(|member| {
+                calculate_distance(x, y, z, member.position_x, member.position_y, member.position_z) <= range
+            })
+            .collect()
+    }
+
+    /// The longest-standing member, who takes over when the leader leaves
+    fn pick_successor(candidates: &[PartyMember]) -> Option<&PartyMember> {
+        candidates.iter().min_by_key(|member| member.joined_at)
+    }
+}
+
+impl PartyInvite {
+    /// Find a pending invite between two players
+    pub fn find(ctx: &ReducerContext, inviter: &Identity, invitee: &Identity) -> Option<PartyInvite> {
+        ctx.db.party_invite().invitee_identity()
+            .filter(invitee)
+            .find(|invite| invite.inviter_identity == *inviter)
+    }
+
+    /// Whether the invite is too old to accept
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        now.duration_since(self.created_at).unwrap_or_default().as_secs() > PARTY_INVITE_EXPIRY_SECONDS
+    }
+}
+
+/// Players only see the party they belong to
+#[client_visibility_filter]
+const PARTY_VISIBILITY: Filter = Filter::Sql(
+    "SELECT party.* FROM party \
+     JOIN party_member ON party.party_id = party_member.party_id \
+     WHERE party_member.member_identity = :sender"
+);
+
+/// Party members see each other's membership rows (and with them positions)
+#[client_visibility_filter]
+const PARTY_MEMBER_VISIBILITY: Filter = Filter::Sql(
+    "SELECT member.* FROM party_member member \
+     JOIN party_member viewer ON member.party_id = viewer.party_id \
+     WHERE viewer.member_identity = :sender"
+);
+
+/// Party chat is only visible to members of that party
+#[client_visibility_filter]
+const CHAT_PARTY_VISIBILITY: Filter = Filter::Sql(
+    "SELECT chatmessage.* FROM chatmessage \
+     JOIN party_member ON chatmessage.group_id = party_member.party_id \
+     WHERE chatmessage.channel = 'party' AND party_member.member_identity = :sender"
+);
+
+/// Players only see invites addressed to them
+#[client_visibility_filter]
+const PARTY_INVITE_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM party_invite WHERE invitee_identity = :sender"
+);
@@ -0,0 +1,88 @@
+//! Rate limit bucket table definition
+
+use spacetimedb::{table, Identity, Timestamp, ReducerContext, Table};
+
+/// Token bucket tracking how often a user performs one kind of action
+/// One row per (identity, bucket key), e.g. "chat:global" or "trade_request"
+#[derive(Clone, Debug)]
+#[table(
+    name = rate_limit_bucket,
+    index(name = identity_key, btree(columns = [identity, bucket_key]))
+)]
+pub struct RateLimitBucket {
+    #[primary_key]
+    #[auto_inc]
+    pub bucket_id: u64,
+
+    pub identity: Identity,
+    pub bucket_key: String,
+
+    /// Tokens left in the bucket; one is spent per action
+    pub tokens: f64,
+
+    /// When tokens were last topped up
+    #[index(btree)]
+    pub last_refill: Timestamp,
+}
+
+impl RateLimitBucket {
+    /// Find the bucket for an identity and key
+    pub fn find(ctx: &ReducerContext, identity: &Identity, bucket_key: &str) -> Option<RateLimitBucket> {
+        ctx.db.rate_limit_bucket().identity_key().filter((identity, bucket_key)).next()
+    }
+
+    /// Whether try_consume would succeed right now, without spending anything
+    pub fn has_token(
+        ctx: &ReducerContext,
+        identity: &Identity,
+        bucket_key: &str,
+        capacity: f64,
+        refill_per_second: f64
+    ) -> bool {
+        let Some(bucket) = Self::find(ctx, identity, bucket_key) else {
+            return true;
+        };
+
+        let elapsed = ctx.timestamp.duration_since(bucket.last_refill).unwrap_or_default();
+        (bucket.tokens + elapsed.as_secs_f64() * refill_per_second).min(capacity) >= 1.0
+    }
+
+    /// Refill the bucket for the time elapsed and try to spend one token
+    /// Returns false (and spends nothing) when the bucket is empty
+    pub fn try_consume(
+        ctx: &ReducerContext,
+        identity: &Identity,
+        bucket_key: &str,
+        capacity: f64,
+        refill_per_second: f64
+    ) -> bool {
+        let Some(mut bucket) = Self::find(ctx, identity, bucket_key) else {
+            ctx.db.rate_limit_bucket().insert(RateLimitBucket {
+                bucket_id: 0, // auto_inc
+                identity: *identity,
+                bucket_key: bucket_key.to_string(),
+                tokens: capacity - 1.0,
+                last_refill: ctx.timestamp,
+            });
+            return true;
+        };
+
+        let elapsed = ctx.timestamp.duration_since(bucket.last_refill).unwrap_or_default();
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * refill_per_second).min(capacity);
+        bucket.last_refill = ctx.timestamp;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        ctx.db.rate_limit_bucket().bucket_id().update(bucket);
+        allowed
+    }
+
+    /// Delete buckets untouched since the cutoff, returning how many were removed
+    /// A bucket idle long enough to have refilled behaves exactly like a missing one
+    pub fn cleanup_idle(ctx: &ReducerContext, idle_since: Timestamp) -> usize {
+        ctx.db.rate_limit_bucket().last_refill().delete(..idle_since) as usize
+    }
+}
@@ -0,0 +1,264 @@
+//! Player report ticket table definitions
+
+use spacetimedb::{table, client_visibility_filter, Filter, Identity, Timestamp, ReducerContext, Table};
+use super::chat::chatmessage;
+use super::whisper::direct_message;
+use super::player::Player;
+
+/// A report filed by one player against another
+/// Reporters see their own tickets; staff see every ticket and work the queue
+/// by subscribing to the status index
+#[derive(Clone, Debug)]
+#[table(name = player_report, public)]
+pub struct PlayerReport {
+    #[primary_key]
+    #[auto_inc]
+    pub report_id: u64,
+
+    #[index(btree)]
+    pub reporter_identity: Identity,
+    pub reporter_username: String,
+
+    #[index(btree)]
+    pub reported_identity: Identity,
+    pub reported_username: String,
+
+    /// ReportCategory as a string
+    pub category: String,
+
+    /// Free text from the reporter (sanitized)
+    pub description: String,
+
+    /// Where the reported player was when the report was filed
+    pub reported_zone: String,
+    pub reported_position_x: f32,
+    pub reported_position_y: f32,
+    pub reported_position_z: f32,
+
+    /// ReportStatus as a string
+    #[index(btree)]
+    pub status: String,
+
+    /// Moderator handling the ticket
+    pub assigned_to: Option<Identity>,
+
+    /// Moderator's closing note
+    pub resolution_note: String,
+
+    /// Sanction issued when resolving, e.g. "mute" and the ChatMute ID (empty and 0 when none)
+    pub sanction_type: String,
+    pub sanction_id: u64,
+
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+}
+
+/// A chat line attached to a report as evidence
+/// Visible to the reporter and to staff, like the report itself
+#[derive(Clone, Debug)]
+#[table(name = report_chat_snapshot, public)]
+pub struct ReportChatSnapshot {
+    #[primary_key]
+    #[auto_inc]
+    pub snapshot_id: u64,
+
+    #[index(btree)]
+    pub report_id: u64,
+
+    /// ChatMessage ID, or DirectMessage ID when channel is "whisper"
+    pub message_id: u64,
+
+    pub channel: String,
+    pub message: String,
+    pub timestamp: Timestamp,
+}
+
+/// Reporters only see the tickets they filed
+#[client_visibility_filter]
+const PLAYER_REPORT_REPORTER_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM player_report WHERE reporter_identity = :sender"
+);
+
+/// Staff see every ticket
+#[client_visibility_filter]
+const PLAYER_REPORT_STAFF_VISIBILITY: Filter = Filter::Sql(
+    "SELECT player_report.* FROM player_report JOIN staff_member WHERE staff_member.identity = :sender"
+);
+
+/// Reporters see the evidence attached to their own tickets
+#[client_visibility_filter]
+const REPORT_CHAT_SNAPSHOT_REPORTER_VISIBILITY: Filter = Filter::Sql(
+    "SELECT report_chat_snapshot.* FROM report_chat_snapshot JOIN player_report ON report_chat_snapshot.report_id = player_report.report_id WHERE player_report.reporter_identity = :sender"
+);
+
+/// Staff see all evidence
+#[client_visibility_filter]
+const REPORT_CHAT_SNAPSHOT_STAFF_VISIBILITY: Filter = Filter::Sql(
+    "SELECT report_chat_snapshot.* FROM report_chat_snapshot JOIN staff_member WHERE staff_member.identity = :sender"
+);
+
+/// Why a player was reported
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReportCategory {
+    Harassment,
+    Spam,
+    Cheating,
+    OffensiveName,
+    Other,
+}
+
+impl std::fmt::Display for ReportCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ReportCategory::Harassment => "harassment",
+            ReportCategory::Spam => "spam",
+            ReportCategory::Cheating => "cheating",
+            ReportCategory::OffensiveName => "offensive_name",
+            ReportCategory::Other => "other",
+        })
+    }
+}
+
+impl ReportCategory {
+    pub fn from_string(s: &str) -> Option<ReportCategory> {
+        match s {
+            "harassment" => Some(ReportCategory::Harassment),
+            "spam" => Some(ReportCategory::Spam),
+            "cheating" => Some(ReportCategory::Cheating),
+            "offensive_name" => Some(ReportCategory::OffensiveName),
+            "other" => Some(ReportCategory::Other),
+            _ => None,
+        }
+    }
+}
+
+/// Where a report is in the moderator queue
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReportStatus {
+    Open,
+    Assigned,
+    Resolved,
+    Dismissed,
+}
+
+impl std::fmt::Display for ReportStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ReportStatus::Open => "open",
+            ReportStatus::Assigned => "assigned",
+            ReportStatus::Resolved => "resolved",
+            ReportStatus::Dismissed => "dismissed",
+        })
+    }
+}
+
+impl ReportStatus {
+    pub fn from_string(s: &str) -> Option<ReportStatus> {
+        match s {
+            "open" => Some(ReportStatus::Open),
+            "assigned" => Some(ReportStatus::Assigned),
+            "resolved" => Some(ReportStatus::Resolved),
+            "dismissed" => Some(ReportStatus::Dismissed),
+            _ => None,
+        }
+    }
+
+    /// Resolved and dismissed tickets are closed
+    pub fn is_closed(&self) -> bool {
+        matches!(self, ReportStatus::Resolved | ReportStatus::Dismissed)
+    }
+}
+
+impl PlayerReport {
+    /// File a report, snapshotting the reported player's position and recent chat
+    pub fn create_report(
+        ctx: &ReducerContext,
+        reporter: &Player,
+        reported: &Player,
+        category: ReportCategory,
+        description: String,
+        chat_since: Timestamp,
+        chat_limit: usize
+    ) -> PlayerReport {
+        let report = ctx.db.player_report().insert(PlayerReport {
+            report_id: 0, // auto_inc
+            reporter_identity: reporter.identity,
+            reporter_username: reporter.username.clone(),
+            reported_identity: reported.identity,
+            reported_username: reported.username.clone(),
+            category: category.to_string(),
+            description,
+            reported_zone: reported.current_zone.clone(),
+            reported_position_x: reported.position_x,
+            reported_position_y: reported.position_y,
+            reported_position_z: reported.position_z,
+            status: ReportStatus::Open.to_string(),
+            assigned_to: None,
+            resolution_note: String::new(),
+            sanction_type: String::new(),
+            sanction_id: 0,
+            created_at: ctx.timestamp,
+            updated_at: ctx.timestamp,
+        });
+
+        // Channel messages come from the time index, so only the recent window is read
+        // Only messages the reporter can read themselves are kept, since the reporter sees the evidence
+        let mut evidence: Vec<ReportChatSnapshot> = ctx.db.chatmessage().timestamp()
+            .filter(chat_since..)
+            .filter(|msg| msg.sender_identity == reported.identity && msg.is_visible_to(ctx, reporter))
+            .map(|msg| ReportChatSnapshot {
+                snapshot_id: 0, // auto_inc
+                report_id: report.report_id,
+                message_id: msg.message_id,
+                channel: msg.channel,
+                message: msg.message,
+                timestamp: msg.timestamp,
+            })
+            .collect();
+
+        // Whispers are only included when the reporter received them
+        evidence.extend(ctx.db.direct_message().sender_recipient()
+            .filter((&reported.identity, &reporter.identity))
+            .filter(|msg| msg.timestamp >= chat_since)
+            .map(|msg| ReportChatSnapshot {
+                snapshot_id: 0, // auto_inc
+                report_id: report.report_id,
+                message_id: msg.message_id,
+                channel: "whisper".to_string(),
+                message: msg.message,
+                timestamp: msg.timestamp,
+            }));
+
+        evidence.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        evidence.truncate(chat_limit);
+
+        for snapshot in evidence {
+            ctx.db.report_chat_snapshot().insert(snapshot);
+        }
+
+        report
+    }
+
+    /// Find report by ID
+    pub fn filter_by_report_id(ctx: &ReducerContext, report_id: u64) -> Option<PlayerReport> {
+        ctx.db.player_report().report_id().find(&report_id)
+    }
+
+    /// Whether the reporter already has an open ticket against the same player
+    pub fn has_open_report(ctx: &ReducerContext, reporter: &Identity, reported: &Identity) -> bool {
+        ctx.db.player_report().reporter_identity()
+            .filter(reporter)
+            .any(|report| report.reported_identity == *reported && !report.status().is_closed())
+    }
+
+    /// Get this report's status
+    pub fn status(&self) -> ReportStatus {
+        ReportStatus::from_string(&self.status).unwrap_or(ReportStatus::Open)
+    }
+
+    /// Save changes to a report
+    pub fn save(ctx: &ReducerContext, mut report: PlayerReport) {
+        report.updated_at = ctx.timestamp;
+        ctx.db.player_report().report_id().update(report);
+    }
+}
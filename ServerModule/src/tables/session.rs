@@ -1,6 +1,7 @@
 //! Game session table definition
 
-use spacetimedb::{table, Identity, Timestamp, ReducerContext, ConnectionId};
+use spacetimedb::{table, Identity, Timestamp, ReducerContext, ConnectionId, Table};
+use shared_module::INACTIVITY_TIMEOUT_SECONDS;
 
 /// Active game sessions
 /// Tracks who is currently connected for cleanup and management
@@ -76,8 +77,53 @@ impl GameSession {
             .collect()
     }
     
-    /// Remove a session
+    /// Seconds the session has lasted so far
+    /// Time after the player went inactive is not counted
+    pub fn played_seconds(&self, now: Timestamp) -> u64 {
+        let idle_limit = self.last_activity.duration_since(self.login_time).unwrap_or_default().as_secs()
+            + INACTIVITY_TIMEOUT_SECONDS;
+        now.duration_since(self.login_time).unwrap_or_default().as_secs().min(idle_limit)
+    }
+    
+    /// Remove a session, adding its length to the player's total played time
     pub fn remove_session(ctx: &ReducerContext, identity: &Identity) {
+        if let Some(session) = Self::filter_by_identity(ctx, identity) {
+            PlayerPlaytime::add(ctx, identity, session.played_seconds(ctx.timestamp));
+        }
         ctx.db.gamesession().identity().delete(identity);
     }
+}
+
+/// Total time an account has spent in finished sessions
+#[derive(Clone, Debug)]
+#[table(name = player_playtime)]
+pub struct PlayerPlaytime {
+    #[primary_key]
+    pub identity: Identity,
+    pub total_seconds: u64,
+}
+
+impl PlayerPlaytime {
+    /// Played time from finished sessions
+    pub fn total_seconds(ctx: &ReducerContext, identity: &Identity) -> u64 {
+        ctx.db.player_playtime().identity().find(identity)
+            .map(|playtime| playtime.total_seconds)
+            .unwrap_or(0)
+    }
+    
+    /// Credit time to an account
+    pub fn add(ctx: &ReducerContext, identity: &Identity, seconds: u64) {
+        match ctx.db.player_playtime().identity().find(identity) {
+            Some(mut playtime) => {
+                playtime.total_seconds += seconds;
+                ctx.db.player_playtime().identity().update(playtime);
+            },
+            None => {
+                ctx.db.player_playtime().insert(PlayerPlaytime {
+                    identity: *identity,
+                    total_seconds: seconds,
+                });
+            },
+        }
+    }
 }
\ No newline at end of file
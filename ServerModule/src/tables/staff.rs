@@ -0,0 +1,106 @@
+//! Staff (moderator and admin) role table definition
+
+use spacetimedb::{table, client_visibility_filter, Filter, Identity, Timestamp, ReducerContext, Table};
+
+/// An account with moderation or administration rights
+/// Each account only sees its own row, so the staff list is not exposed to players;
+/// staff-only visibility filters on other tables join against it
+#[derive(Clone, Debug)]
+#[table(name = staff_member, public)]
+pub struct StaffMember {
+    #[primary_key]
+    pub identity: Identity,
+
+    /// "moderator" or "admin"
+    pub role: String,
+
+    /// Who granted the role and when
+    pub granted_by: Identity,
+    pub granted_at: Timestamp,
+}
+
+/// Hex identity of the server owner, set at build time with MMO_OWNER_IDENTITY
+/// Databases published before staff roles existed never ran the init grant,
+/// so the owner is made admin the next time they connect instead
+const OWNER_IDENTITY: Option<&str> = option_env!("MMO_OWNER_IDENTITY");
+
+/// Staff roles, lowest to highest
+#[derive(Clone, Debug, PartialEq)]
+pub enum StaffRole {
+    Moderator,
+    Admin,
+}
+
+impl std::fmt::Display for StaffRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StaffRole::Moderator => "moderator",
+            StaffRole::Admin => "admin",
+        })
+    }
+}
+
+impl StaffRole {
+    pub fn from_string(s: &str) -> Option<StaffRole> {
+        match s {
+            "moderator" => Some(StaffRole::Moderator),
+            "admin" => Some(StaffRole::Admin),
+            _ => None,
+        }
+    }
+}
+
+impl StaffMember {
+    /// Find the staff role held by an identity, if any
+    pub fn find_role(ctx: &ReducerContext, identity: &Identity) -> Option<StaffRole> {
+        ctx.db.staff_member().identity().find(identity)
+            .and_then(|member| StaffRole::from_string(&member.role))
+    }
+
+    /// Grant or change a staff role
+    pub fn grant(ctx: &ReducerContext, identity: Identity, role: StaffRole, granted_by: Identity) {
+        let member = StaffMember {
+            identity,
+            role: role.to_string(),
+            granted_by,
+            granted_at: ctx.timestamp,
+        };
+
+        if ctx.db.staff_member().identity().find(&identity).is_some() {
+            ctx.db.staff_member().identity().update(member);
+        } else {
+            ctx.db.staff_member().insert(member);
+        }
+    }
+
+    /// Make the connecting client an admin if it is the configured owner and holds no role
+    /// Safe to run on every connect; an owner demoted or revoked by another admin keeps that
+    pub fn bootstrap_owner(ctx: &ReducerContext) {
+        let Some(owner) = OWNER_IDENTITY.and_then(|hex| Identity::from_hex(hex.trim()).ok()) else {
+            return;
+        };
+
+        if ctx.sender != owner || ctx.db.staff_member().identity().find(&owner).is_some() {
+            return;
+        }
+
+        // Only bootstrap a database that has no admin at all
+        if ctx.db.staff_member().iter().any(|member| member.role == StaffRole::Admin.to_string()) {
+            return;
+        }
+
+        Self::grant(ctx, owner, StaffRole::Admin, owner);
+        log::info!("Granted admin to server owner {:?}", owner);
+    }
+
+    /// Remove a staff role, returning whether one was held
+    pub fn revoke(ctx: &ReducerContext, identity: &Identity) -> bool {
+        ctx.db.staff_member().identity().delete(identity)
+    }
+}
+
+/// Accounts only see their own staff row
+#[client_visibility_filter]
+const STAFF_MEMBER_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM staff_member WHERE identity = :sender"
+);
@@ -1,6 +1,9 @@
 //! Player table definition
 
 use spacetimedb::{table, Identity, Timestamp, ReducerContext};
+use shared_module::*;
+use super::party::{Party, PartyMember};
+use super::friend::Friendship;
 
 /// Active player in the game world
 /// This represents a player who is currently online and in the game
@@ -71,6 +74,7 @@ impl Player {
             player.position_z = z;
             player.rotation_yaw = yaw;
             player.last_seen = timestamp;
+            PartyMember::sync_from_player(ctx, &player);
             ctx.db.game_players().identity().update(player);
         }
     }
@@ -83,12 +87,42 @@ impl Player {
         timestamp: Timestamp
     ) {
         if let Some(mut player) = Self::filter_by_identity(ctx, identity) {
+            let was_online = player.is_online;
             player.is_online = is_online;
             player.last_seen = timestamp;
-            ctx.db.game_players().identity().update(player);
+            PartyMember::sync_from_player(ctx, &player);
+            ctx.db.game_players().identity().update(player.clone());
+            
+            if was_online != is_online {
+                Friendship::notify_presence(ctx, &player);
+            }
+            
+            if !is_online {
+                Party::handle_member_offline(ctx, identity);
+            }
         }
     }
     
+    /// Add experience, applying any level-ups
+    /// Returns the new level if the player levelled up
+    pub fn grant_experience(ctx: &ReducerContext, identity: &Identity, amount: u64) -> Option<u32> {
+        let mut player = Self::filter_by_identity(ctx, identity)?;
+        let starting_level = player.level;
+        
+        player.experience = player.experience.saturating_add(amount);
+        while player.level < MAX_PLAYER_LEVEL && player.experience >= experience_to_next_level(player.level) {
+            player.experience -= experience_to_next_level(player.level);
+            player.level += 1;
+        }
+        
+        let levelled_up = player.level > starting_level;
+        let new_level = player.level;
+        PartyMember::sync_from_player(ctx, &player);
+        ctx.db.game_players().identity().update(player);
+        
+        levelled_up.then_some(new_level)
+    }
+    
     /// Create a new player
     pub fn create_player(
         ctx: &ReducerContext,
@@ -0,0 +1,111 @@
+//! Direct message (whisper) table definitions
+
+use spacetimedb::{table, client_visibility_filter, Filter, Identity, Timestamp, ReducerContext, Table};
+
+/// A private message between two players
+/// This table is private: clients only ever see their own WhisperDelivery rows
+#[derive(Clone, Debug)]
+#[table(
+    name = direct_message,
+    index(name = sender_recipient, btree(columns = [sender_identity, recipient_identity]))
+)]
+pub struct DirectMessage {
+    /// Unique message identifier
+    #[primary_key]
+    #[auto_inc]
+    pub message_id: u64,
+
+    /// Who sent the whisper
+    pub sender_identity: Identity,
+
+    /// Who the whisper was addressed to
+    #[index(btree)]
+    pub recipient_identity: Identity,
+
+    /// Display names at send time (copied for quick access)
+    pub sender_username: String,
+    pub recipient_username: String,
+
+    /// Message body (already sanitized)
+    pub message: String,
+
+    /// When the whisper was sent
+    pub timestamp: Timestamp,
+}
+
+/// Per-participant copy of a whisper
+/// Each direct message produces one row for the sender and one for the recipient,
+/// and the visibility filter below only lets a client see the rows it owns
+#[derive(Clone, Debug)]
+#[table(name = whisper_delivery, public)]
+pub struct WhisperDelivery {
+    #[primary_key]
+    #[auto_inc]
+    pub delivery_id: u64,
+
+    /// Participant this copy belongs to
+    #[index(btree)]
+    pub owner_identity: Identity,
+
+    /// The DirectMessage this row delivers
+    #[index(btree)]
+    pub message_id: u64,
+
+    pub sender_identity: Identity,
+    pub recipient_identity: Identity,
+    pub sender_username: String,
+    pub recipient_username: String,
+    pub message: String,
+    pub timestamp: Timestamp,
+}
+
+impl DirectMessage {
+    /// Store a whisper and deliver it to both participants
+    pub fn send(
+        ctx: &ReducerContext,
+        sender_identity: Identity,
+        sender_username: String,
+        recipient_identity: Identity,
+        recipient_username: String,
+        message: String,
+        timestamp: Timestamp
+    ) -> DirectMessage {
+        let stored = ctx.db.direct_message().insert(DirectMessage {
+            message_id: 0, // auto_inc
+            sender_identity,
+            recipient_identity,
+            sender_username,
+            recipient_username,
+            message,
+            timestamp,
+        });
+
+        for owner_identity in [sender_identity, recipient_identity] {
+            ctx.db.whisper_delivery().insert(WhisperDelivery {
+                delivery_id: 0, // auto_inc
+                owner_identity,
+                message_id: stored.message_id,
+                sender_identity: stored.sender_identity,
+                recipient_identity: stored.recipient_identity,
+                sender_username: stored.sender_username.clone(),
+                recipient_username: stored.recipient_username.clone(),
+                message: stored.message.clone(),
+                timestamp: stored.timestamp,
+            });
+        }
+
+        stored
+    }
+
+    /// Delete a whisper together with both of its deliveries
+    pub fn delete_message(ctx: &ReducerContext, message_id: u64) {
+        ctx.db.whisper_delivery().message_id().delete(&message_id);
+        ctx.db.direct_message().message_id().delete(&message_id);
+    }
+}
+
+/// Whisper deliveries are only visible to the participant that owns them
+#[client_visibility_filter]
+const WHISPER_DELIVERY_VISIBILITY: Filter = Filter::Sql(
+    "SELECT * FROM whisper_delivery WHERE owner_identity = :sender"
+);
@@ -0,0 +1,151 @@
+//! Chat flood and spam detection
+
+use spacetimedb::{ReducerContext, Identity};
+use shared_module::*;
+use crate::tables::*;
+use crate::utils::validation::can_moderate_chat_channel;
+
+/// Outcome of the anti-spam check for one message
+pub enum SpamVerdict {
+    /// The message may be posted
+    Allowed,
+    /// The message must be dropped; carries the reason to show the sender
+    Rejected(String),
+}
+
+/// Check whether a player is currently muted in a channel
+pub fn check_chat_mute(
+    ctx: &ReducerContext,
+    identity: &Identity,
+    channel: &str
+) -> Result<(), String> {
+    if let Some(mute) = ChatMute::find_active(ctx, identity, channel) {
+        let remaining = mute.muted_until.duration_since(ctx.timestamp).unwrap_or_default();
+        return Err(format!("You are muted for another {} seconds", remaining.as_secs().max(1)));
+    }
+    
+    Ok(())
+}
+
+/// Enforce read-only and slow mode restrictions set by moderators
+/// Channel moderators are exempt so they can still post in a locked channel.
+/// Slow mode is only checked here; the token is spent by consume_chat_slow_mode
+/// once the message has passed every other check.
+pub fn check_chat_channel_restrictions(
+    ctx: &ReducerContext,
+    identity: &Identity,
+    channel: &str
+) -> Result<(), String> {
+    let settings = match ChatChannelSettings::find(ctx, channel) {
+        Some(settings) => settings,
+        None => return Ok(()),
+    };
+    
+    if can_moderate_chat_channel(ctx, identity, channel) {
+        return Ok(());
+    }
+    
+    if settings.read_only {
+        return Err("This channel is read-only".to_string());
+    }
+    
+    if settings.slow_mode_seconds > 0 {
+        let refill_per_second = 1.0 / settings.slow_mode_seconds as f64;
+        if !RateLimitBucket::has_token(ctx, identity, &slow_mode_bucket_key(channel), 1.0, refill_per_second) {
+            return Err(format!("Slow mode is on: one message every {} seconds", settings.slow_mode_seconds));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Spend the slow mode token for a message that is about to be posted
+pub fn consume_chat_slow_mode(ctx: &ReducerContext, identity: &Identity, channel: &str) {
+    let Some(settings) = ChatChannelSettings::find(ctx, channel) else {
+        return;
+    };
+    
+    if settings.slow_mode_seconds > 0 && !can_moderate_chat_channel(ctx, identity, channel) {
+        let refill_per_second = 1.0 / settings.slow_mode_seconds as f64;
+        RateLimitBucket::try_consume(ctx, identity, &slow_mode_bucket_key(channel), 1.0, refill_per_second);
+    }
+}
+
+fn slow_mode_bucket_key(channel: &str) -> String {
+    format!("slow:{}", channel)
+}
+
+/// Run flood and duplicate detection for a message that is about to be posted
+///
+/// Violations are written to the database, so callers must not turn a rejection
+/// into a reducer error (that would roll the record back); drop the message instead.
+pub fn check_chat_spam(
+    ctx: &ReducerContext,
+    identity: &Identity,
+    channel: &str,
+    message: &str
+) -> SpamVerdict {
+    // Flood: one token bucket per sender per channel
+    let bucket_key = format!("chat:{}", channel);
+    if !RateLimitBucket::try_consume(ctx, identity, &bucket_key, CHAT_BURST_CAPACITY, CHAT_REFILL_PER_SECOND) {
+        return record_violation(ctx, identity, "flood", channel, message, "You are sending messages too quickly");
+    }
+    
+    // Duplicates: compare against what this sender said in this channel within the window
+    // Short messages are never treated as duplicates
+    let normalized = normalize_for_spam_check(message);
+    if normalized.chars().count() < CHAT_DUPLICATE_MIN_LENGTH {
+        return SpamVerdict::Allowed;
+    }
+    
+    let window_start = ctx.timestamp - std::time::Duration::from_secs(CHAT_DUPLICATE_WINDOW_SECONDS);
+    
+    let is_duplicate = ChatRecentMessage::get_for_sender(ctx, identity, channel)
+        .iter()
+        .filter(|entry| entry.timestamp >= window_start)
+        .any(|entry| message_similarity(&entry.normalized, &normalized) >= CHAT_DUPLICATE_SIMILARITY);
+    
+    if is_duplicate {
+        return record_violation(ctx, identity, "duplicate", channel, message, "Please don't repeat the same message");
+    }
+    
+    ChatRecentMessage::remember(ctx, *identity, channel, normalized, window_start, CHAT_DUPLICATE_HISTORY);
+    SpamVerdict::Allowed
+}
+
+/// Record a violation and escalate to an automatic mute when they pile up
+fn record_violation(
+    ctx: &ReducerContext,
+    identity: &Identity,
+    kind: &str,
+    channel: &str,
+    message: &str,
+    reason: &str
+) -> SpamVerdict {
+    ChatViolation::record(ctx, *identity, kind, channel, message);
+    log::warn!("Chat {} violation by {:?} in {}", kind, identity, channel);
+    
+    let window_start = ctx.timestamp - std::time::Duration::from_secs(CHAT_VIOLATION_WINDOW_SECONDS);
+    let recent_violations = ChatViolation::count_since(ctx, identity, window_start);
+    
+    if recent_violations < CHAT_VIOLATIONS_BEFORE_MUTE || ChatMute::find_active(ctx, identity, "").is_some() {
+        return SpamVerdict::Rejected(reason.to_string());
+    }
+    
+    // Each automatic mute lasts longer than the one before
+    let previous_mutes = ChatMute::count_issued_by(ctx, identity, &ctx.identity());
+    let level = previous_mutes.min(CHAT_AUTO_MUTE_DURATIONS_SECONDS.len() - 1);
+    let duration = std::time::Duration::from_secs(CHAT_AUTO_MUTE_DURATIONS_SECONDS[level]);
+    
+    ChatMute::create_mute(
+        ctx,
+        *identity,
+        String::new(),
+        ctx.timestamp + duration,
+        format!("Automatic: repeated {} violations", kind),
+        ctx.identity()
+    );
+    
+    log::info!("Auto-muted {:?} for {} seconds", identity, duration.as_secs());
+    SpamVerdict::Rejected(format!("{}. You have been muted for {} seconds", reason, duration.as_secs()))
+}
@@ -1,12 +1,39 @@
 //! Database cleanup utilities
 
-use spacetimedb::ReducerContext;
+use spacetimedb::{ReducerContext, Table};
 use shared_module::*;
 use crate::tables::*;
 
-/// Clean up old chat messages to prevent database bloat
+/// Archive chat messages past their channel type's retention policy
 pub fn cleanup_old_chat_messages(ctx: &ReducerContext) {
-    ChatMessage::cleanup_old_messages(ctx, MAX_CHAT_HISTORY);
+    let archived = ChatMessage::enforce_retention(ctx);
+    
+    if archived > 0 {
+        log::info!("Archived {} chat messages past retention", archived);
+    }
+}
+
+/// Drop duplicate-detection history past its window and rate limit buckets that have sat idle
+pub fn cleanup_chat_spam_state(ctx: &ReducerContext) {
+    let duplicate_cutoff = ctx.timestamp - std::time::Duration::from_secs(CHAT_DUPLICATE_WINDOW_SECONDS);
+    let recent_removed = ChatRecentMessage::cleanup_before(ctx, duplicate_cutoff);
+    
+    let idle_cutoff = ctx.timestamp - std::time::Duration::from_secs(RATE_LIMIT_BUCKET_IDLE_SECONDS);
+    let buckets_removed = RateLimitBucket::cleanup_idle(ctx, idle_cutoff);
+    
+    if recent_removed + buckets_removed > 0 {
+        log::info!("Removed {} recent chat entries and {} idle rate limit buckets", recent_removed, buckets_removed);
+    }
+}
+
+/// Clean up player notifications past their retention period
+pub fn cleanup_old_notifications(ctx: &ReducerContext) {
+    let cutoff_time = ctx.timestamp - std::time::Duration::from_secs(NOTIFICATION_RETENTION_SECONDS);
+    let removed = PlayerNotification::cleanup_before(ctx, cutoff_time);
+    
+    if removed > 0 {
+        log::info!("Removed {} old notifications", removed);
+    }
 }
 
 /// Clean up sessions for users who haven't been active
@@ -58,6 +85,12 @@ pub fn perform_database_maintenance(ctx: &ReducerContext) {
     // Clean up old chat messages
     cleanup_old_chat_messages(ctx);
     
+    // Clean up old notifications
+    cleanup_old_notifications(ctx);
+    
+    // Clean up anti-spam history and idle rate limit buckets
+    cleanup_chat_spam_state(ctx);
+    
     // Clean up stale sessions
     cleanup_stale_sessions(ctx);
     
@@ -73,7 +106,7 @@ pub fn get_database_stats(ctx: &ReducerContext) -> DatabaseStats {
     let active_users = ctx.db.user().iter().filter(|u| u.is_active).count();
     let online_players = Player::get_online_players(ctx).len();
     let total_sessions = GameSession::get_all_sessions(ctx).len();
-    let total_messages = ctx.db.chatmessage().iter().count();
+    let total_messages = ctx.db.chatmessage().count() as usize;
     
     DatabaseStats {
         total_users,
@@ -95,22 +128,17 @@ pub struct DatabaseStats {
 }
 
 /// Archive old data instead of deleting (for data retention policies)
+/// Moves every chat message older than the threshold into the archive table,
+/// regardless of its channel's policy
 pub fn archive_old_data(ctx: &ReducerContext, archive_threshold_days: u64) {
     let threshold_duration = std::time::Duration::from_secs(archive_threshold_days * 24 * 60 * 60);
     let cutoff_time = ctx.timestamp - threshold_duration;
     
-    // Find old chat messages
-    let old_messages: Vec<ChatMessage> = ctx.db.chatmessage().iter()
-        .filter(|msg| msg.timestamp < cutoff_time)
-        .cloned()
-        .collect();
+    // Find old chat messages using the timestamp index
+    let old_messages = ChatMessage::get_messages_before(ctx, cutoff_time);
     
     if !old_messages.is_empty() {
-        log::info!("Found {} old messages to archive", old_messages.len());
-        
-        // In a real implementation, you would:
-        // 1. Export the messages to an archive system
-        // 2. Delete them from the active database
-        // For now, we'll just log the count
+        ChatMessage::archive_messages(ctx, &old_messages);
+        log::info!("Archived {} old messages", old_messages.len());
     }
-}
\ No newline at end of file
+}
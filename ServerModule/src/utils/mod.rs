@@ -3,8 +3,10 @@
 pub mod session;
 pub mod validation;
 pub mod cleanup;
+pub mod anti_spam;
 
 // Re-export utility modules
 pub use session::*;
 pub use validation::*;
-pub use cleanup::*;
\ No newline at end of file
+pub use cleanup::*;
+pub use anti_spam::*;
\ No newline at end of file
@@ -6,9 +6,12 @@ use crate::tables::*;
 
 /// Handle client connection
 pub fn handle_client_connected(ctx: &ReducerContext) {
-    // For now, just log the connection
-    // More complex initialization could be added here
     log::debug!("Client connected: {:?}", ctx.sender);
+
+    // Upgraded databases never ran init, so its idempotent setup runs here as well
+    StaffMember::bootstrap_owner(ctx);
+    ChatRetentionPolicy::seed_defaults(ctx);
+    ChatRetentionSchedule::ensure_scheduled(ctx);
 }
 
 /// Handle client disconnection and cleanup
@@ -66,25 +66,52 @@ pub fn validate_target_player(
     Ok(target.clone())
 }
 
-/// Validate admin permissions (placeholder implementation)
+/// Validate admin permissions
 pub fn validate_admin_permissions(ctx: &ReducerContext) -> Result<(), String> {
-    // In a real implementation, you'd check if the user has admin rights
-    // For now, we'll just check if they're a valid user
     validate_authenticated_user(ctx)?;
     
-    // TODO: Implement proper admin role checking
-    // This is just a placeholder
-    Ok(())
+    match StaffMember::find_role(ctx, &ctx.sender) {
+        Some(StaffRole::Admin) => Ok(()),
+        _ => Err("Admin permissions required".to_string()),
+    }
+}
+
+/// Validate moderator permissions (admins count as moderators)
+pub fn validate_moderator_permissions(ctx: &ReducerContext) -> Result<StaffRole, String> {
+    validate_authenticated_user(ctx)?;
+    
+    StaffMember::find_role(ctx, &ctx.sender)
+        .ok_or_else(|| "Moderator permissions required".to_string())
+}
+
+/// Whether an identity may moderate a chat channel
+/// Staff moderate every channel; custom channel owners and moderators moderate their own
+pub fn can_moderate_chat_channel(
+    ctx: &ReducerContext,
+    identity: &Identity,
+    channel: &str
+) -> bool {
+    if StaffMember::find_role(ctx, identity).is_some() {
+        return true;
+    }
+    
+    ChatChannel::filter_by_name(ctx, channel)
+        .and_then(|custom_channel| ChatChannelMember::find(ctx, custom_channel.channel_id, identity))
+        .is_some_and(|membership| membership.role().can_moderate())
 }
 
 /// Validate rate limiting for actions
+/// Each action type gets its own token bucket per user
 pub fn validate_rate_limit(
-    _ctx: &ReducerContext,
-    _action_type: &str
+    ctx: &ReducerContext,
+    action_type: &str
 ) -> Result<(), String> {
-    // TODO: Implement proper rate limiting
-    // This would track action counts per user per time window
-    // For now, we'll just allow everything
+    let capacity = MAX_RPC_CALLS_PER_SECOND as f64;
+    
+    if !RateLimitBucket::try_consume(ctx, &ctx.sender, action_type, capacity, capacity) {
+        return Err("Too many requests, please slow down".to_string());
+    }
+    
     Ok(())
 }
 
@@ -108,28 +135,51 @@ pub fn validate_world_position(x: f32, y: f32, z: f32) -> Result<(), String> {
     Ok(())
 }
 
-/// Validate chat channel access
+/// Validate chat channel access and resolve who the channel reaches
 pub fn validate_chat_channel_access(
     ctx: &ReducerContext,
     channel: &str
-) -> Result<(), String> {
-    let _player = validate_player_in_game(ctx)?;
+) -> Result<(Player, ChatScope), String> {
+    let player = validate_player_in_game(ctx)?;
     
-    match channel {
-        "global" => Ok(()), // Everyone can access global
-        "zone" => Ok(()),   // Everyone can access zone chat
+    let scope = match channel {
+        "global" => ChatScope::Global, // Everyone can access global
+        "system" => ChatScope::Global, // Everyone reads announcements; only the server posts them
+        "zone" => ChatScope::Zone(player.current_zone.clone()), // Only the sender's zone hears it
         "guild" => {
-            // TODO: Check if player is in a guild
-            Ok(())
+            // Guild chat only reaches the sender's own guild, and only ranks allowed to speak
+            let membership = GuildMember::find_by_member(ctx, &player.identity)
+                .ok_or("You are not in a guild")?;
+            let rank = membership.rank(ctx)
+                .ok_or("Guild rank not found")?;
+
+            if !rank.has_permission(GUILD_PERM_SPEAK) {
+                return Err("Your guild rank cannot speak in guild chat".to_string());
+            }
+
+            ChatScope::Group(membership.guild_id)
         },
         "party" => {
-            // TODO: Check if player is in a party
-            Ok(())
+            // Party chat only reaches the sender's own party
+            let membership = PartyMember::find_by_member(ctx, &player.identity)
+                .ok_or("You are not in a party")?;
+            ChatScope::Group(membership.party_id)
         },
-        channel if channel.starts_with("whisper:") => {
-            // Whisper channels are always accessible
-            Ok(())
+        "whisper" => {
+            // Whispers go through send_whisper, never through a channel name
+            return Err("Use send_whisper for private messages".to_string());
         },
-        _ => Err("Invalid chat channel".to_string())
-    }
+        custom => {
+            // Player-created channels are only open to their members
+            let custom_channel = ChatChannel::filter_by_name(ctx, custom)
+                .ok_or("Invalid chat channel")?;
+            
+            ChatChannelMember::find(ctx, custom_channel.channel_id, &player.identity)
+                .ok_or("You are not a member of this channel")?;
+            
+            ChatScope::Group(custom_channel.channel_id)
+        }
+    };
+    
+    Ok((player, scope))
 }
\ No newline at end of file
@@ -14,6 +14,7 @@ pub const MAX_MESSAGE_SIZE_BYTES: u32 = 65536;
 pub const HEARTBEAT_INTERVAL_SECONDS: u64 = 30;
 pub const INACTIVITY_TIMEOUT_SECONDS: u64 = 300;
 pub const POSITION_UPDATE_INTERVAL_MS: u64 = 100;
+pub const NOTIFICATION_RETENTION_SECONDS: u64 = 7 * 24 * 60 * 60;
 
 // Default values that make sense for most situations
 pub const DEFAULT_ZONE_ID: u32 = 1;
@@ -22,6 +23,163 @@ pub const DEFAULT_MAX_DISTANCE: f32 = 1000.0;
 // Chat system limits
 pub const MAX_CHAT_MESSAGE_LENGTH: usize = 500;
 pub const MAX_CHAT_HISTORY: usize = 100;
+pub const MAX_COMBINING_MARKS_PER_CHAR: usize = 3;
+pub const MAX_WORD_FILTER_PATTERN_LENGTH: usize = 64;
+
+// Chat retention: (channel type, max age in seconds, max live messages per conversation)
+pub const CHAT_RETENTION_INTERVAL_SECONDS: u64 = 300;
+pub const DEFAULT_CHAT_RETENTION: [(&str, u64, u32); 6] = [
+    ("global", 7 * 24 * 60 * 60, 1000),
+    ("zone", 3 * 24 * 60 * 60, 200),
+    ("system", 30 * 24 * 60 * 60, 100),
+    ("party", 24 * 60 * 60, 200),
+    ("guild", 30 * 24 * 60 * 60, 500),
+    ("custom", 14 * 24 * 60 * 60, 500),
+];
+
+// Chat anti-spam tuning
+pub const CHAT_BURST_CAPACITY: f64 = 5.0;
+pub const CHAT_REFILL_PER_SECOND: f64 = 1.0;
+pub const CHAT_DUPLICATE_WINDOW_SECONDS: u64 = 30;
+pub const CHAT_DUPLICATE_HISTORY: usize = 5;
+pub const CHAT_DUPLICATE_SIMILARITY: f32 = 0.85;
+pub const CHAT_DUPLICATE_MIN_LENGTH: usize = 8; // Shorter messages ("ok", "lol") may repeat freely
+pub const CHAT_VIOLATION_WINDOW_SECONDS: u64 = 600;
+pub const CHAT_VIOLATIONS_BEFORE_MUTE: usize = 3;
+pub const CHAT_AUTO_MUTE_DURATIONS_SECONDS: [u64; 4] = [60, 300, 1800, 86400];
+// Every rate limit bucket refills completely within this time, so idle buckets can be dropped
+pub const RATE_LIMIT_BUCKET_IDLE_SECONDS: u64 = 3600;
+
+// Custom chat channel limits
+pub const MIN_CHAT_CHANNEL_NAME_LENGTH: usize = 3;
+pub const MAX_CHAT_CHANNEL_NAME_LENGTH: usize = 24;
+pub const MAX_CHAT_CHANNEL_MEMBERS: usize = 200;
+pub const MAX_CHAT_CHANNELS_PER_PLAYER: usize = 10;
+pub const RESERVED_CHAT_CHANNELS: [&str; 6] = ["global", "zone", "guild", "party", "whisper", "system"];
+
+// Chat moderation limits
+pub const MAX_MODERATOR_MUTE_SECONDS: u64 = 30 * 24 * 60 * 60;
+pub const MAX_CHAT_SLOW_MODE_SECONDS: u32 = 3600;
+pub const SYSTEM_SENDER_NAME: &str = "System";
+
+// Player reports
+pub const REPORT_CHAT_WINDOW_SECONDS: u64 = 30 * 60;
+pub const REPORT_CHAT_SNAPSHOT_SIZE: usize = 25;
+pub const REPORT_BURST_CAPACITY: f64 = 3.0;
+pub const REPORT_REFILL_PER_SECOND: f64 = 3.0 / 3600.0;
+
+// Parties
+pub const MAX_PARTY_SIZE: usize = 5;
+pub const PARTY_INVITE_EXPIRY_SECONDS: u64 = 60;
+pub const PARTY_XP_SHARE_RANGE: f32 = 100.0;
+pub const PARTY_XP_BONUS_PER_MEMBER: f64 = 0.1;
+
+// Guilds
+pub const MIN_GUILD_NAME_LENGTH: usize = 3;
+pub const MAX_GUILD_NAME_LENGTH: usize = 24;
+pub const MAX_GUILD_MEMBERS: usize = 500;
+pub const MAX_GUILD_RANKS: usize = 10;
+pub const MAX_GUILD_MOTD_LENGTH: usize = 200;
+pub const GUILD_INVITE_EXPIRY_SECONDS: u64 = 5 * 60;
+pub const GUILD_BANK_SLOTS: usize = 98;
+pub const GUILD_WITHDRAW_WINDOW_SECONDS: u64 = 24 * 60 * 60;
+
+// Guild rank permission bits
+pub const GUILD_PERM_SPEAK: u32 = 1 << 0;
+pub const GUILD_PERM_INVITE: u32 = 1 << 1;
+pub const GUILD_PERM_KICK: u32 = 1 << 2;
+pub const GUILD_PERM_SET_RANK: u32 = 1 << 3;
+pub const GUILD_PERM_EDIT_MOTD: u32 = 1 << 4;
+pub const GUILD_PERM_MANAGE_RANKS: u32 = 1 << 5;
+pub const GUILD_PERM_BANK_DEPOSIT: u32 = 1 << 6;
+pub const GUILD_PERM_BANK_WITHDRAW: u32 = 1 << 7;
+pub const GUILD_PERM_ALL: u32 = (1 << 8) - 1;
+
+// Friends and ignore lists
+pub const MAX_FRIENDS: usize = 100;
+pub const MAX_PENDING_FRIEND_REQUESTS: usize = 20;
+pub const MAX_IGNORED_PLAYERS: usize = 100;
+
+// Trading
+pub const TRADE_REQUEST_EXPIRY_SECONDS: u64 = 60;
+pub const MAX_TRADE_DISTANCE: f32 = 10.0;
+pub const MAX_TRADE_ITEMS: usize = 8;
+
+// Currency
+pub const MAX_WALLET_BALANCE: u64 = 1_000_000_000_000;
+
+// NPC vendors
+pub const VENDOR_INTERACTION_RANGE: f32 = 5.0;
+pub const DEFAULT_VENDOR_BUY_MULTIPLIER: f32 = 1.0;
+pub const DEFAULT_VENDOR_SELL_MULTIPLIER: f32 = 0.25;
+pub const MAX_VENDOR_PRICE_MULTIPLIER: f32 = 100.0;
+pub const MAX_BUYBACK_ENTRIES: usize = 12;
+pub const VENDOR_RESTOCK_CHECK_INTERVAL_SECONDS: u64 = 60;
+
+// Auction house
+pub const AUCTION_DURATION_HOURS: [u32; 3] = [12, 24, 48];
+pub const AUCTION_DEPOSIT_RATE_PER_DAY: f64 = 0.05;
+pub const AUCTION_SALES_CUT: f64 = 0.05;
+pub const AUCTION_MIN_BID_INCREMENT: f64 = 0.05;
+pub const MAX_AUCTION_LISTINGS_PER_PLAYER: usize = 50;
+pub const AUCTION_EXPIRY_CHECK_INTERVAL_SECONDS: u64 = 60;
+pub const AUCTION_HOUSE_SENDER_NAME: &str = "Auction House";
+
+// Leaderboards
+pub const LEADERBOARD_REFRESH_INTERVAL_SECONDS: u64 = 5 * 60;
+pub const LEADERBOARD_ARCHIVE_SIZE: usize = 100;
+pub const DEFAULT_PVP_RATING: u32 = 1500;
+pub const PVP_RATING_K_FACTOR: f64 = 32.0;
+
+// Duels
+pub const DUEL_CHALLENGE_EXPIRY_SECONDS: u64 = 60;
+pub const MAX_DUEL_DISTANCE: f32 = 5.0;
+pub const DUEL_UNARMED_DAMAGE: u32 = 2;
+pub const DUEL_ATTACKS_PER_SECOND: f64 = 1.0;
+
+// Inventory
+pub const INVENTORY_SLOTS: usize = 100;
+
+// Item instances
+// Quality tiers: (name, roll weight, stat multiplier, affix count)
+pub const ITEM_QUALITY_TIERS: [(&str, u32, f32, usize); 4] = [
+    ("common", 70, 1.0, 0),
+    ("uncommon", 20, 1.1, 1),
+    ("rare", 8, 1.25, 2),
+    ("epic", 2, 1.5, 3),
+];
+// Bonus stats an affix can roll: (stat, min value, max value)
+pub const ITEM_AFFIX_STATS: [(&str, u32, u32); 5] = [
+    ("strength", 1, 5),
+    ("agility", 1, 5),
+    ("stamina", 1, 5),
+    ("intellect", 1, 5),
+    ("critical_strike", 1, 3),
+];
+pub const ITEM_WEAR_PER_ATTACK: u32 = 1;
+pub const ITEM_WEAR_PER_HIT_TAKEN: u32 = 1;
+pub const ITEM_REPAIR_COST_RATE: f64 = 0.5; // A full repair costs half the item's value
+
+// NPCs
+pub const DEFAULT_NPC_MAX_HEALTH: f32 = 50.0;
+
+// Achievements
+pub const ACHIEVEMENT_SENDER_NAME: &str = "Achievements";
+
+// Quests
+pub const MAX_ACTIVE_QUESTS: usize = 25;
+pub const QUEST_INTERACTION_RANGE: f32 = 5.0;
+pub const DAILY_QUEST_RESET_HOUR_UTC: u64 = 4;
+
+// Mail
+pub const MAX_MAIL_SUBJECT_LENGTH: usize = 64;
+pub const MAX_MAIL_BODY_LENGTH: usize = 2000;
+pub const MAX_MAIL_ATTACHMENTS: usize = 8;
+pub const MAX_MAILBOX_SIZE: usize = 100;
+pub const MAIL_EXPIRY_SECONDS: u64 = 30 * 24 * 60 * 60;
+pub const MAIL_EXPIRY_CHECK_INTERVAL_SECONDS: u64 = 60;
+pub const MAIL_BURST_CAPACITY: f64 = 10.0;
+pub const MAIL_REFILL_PER_SECOND: f64 = 10.0 / 3600.0;
 
 // Player limits
 pub const MIN_USERNAME_LENGTH: usize = 3;
@@ -31,4 +189,6 @@ pub const MAX_MOVEMENT_DISTANCE: f32 = 50.0;
 
 // Zone and world limits
 pub const DEFAULT_STARTING_ZONE: &str = "default";
-pub const MAX_PLAYERS_PER_ZONE: u32 = 2000;
\ No newline at end of file
+pub const MAX_PLAYER_LEVEL: u32 = 60;
+pub const MAX_PLAYERS_PER_ZONE: u32 = 2000;
+pub const WORLD_CHUNK_SIZE: f32 = 16.0; // World units along each side of a chunk
\ No newline at end of file
@@ -3,8 +3,6 @@
 //! This module defines the "contract" between client and server.
 //! Think of it as the common language that all parts of your system speak.
 
-use spacetimedb::{Identity, Timestamp};
-
 // Module organization
 pub mod types;
 pub mod constants;
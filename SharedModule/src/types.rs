@@ -1,7 +1,6 @@
 //! Core type definitions shared across all modules
 
 use serde::{Deserialize, Serialize};
-use spacetimedb::{Identity, Timestamp};
 
 /// Core object identification system
 /// Every object in your game world gets a unique ID and class definition
@@ -124,4 +123,112 @@ impl PropertyValue {
             owner_only: true,
         }
     }
-}
\ No newline at end of file
+}
+
+/// What the word filter does when a rule matches a chat message
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum WordFilterAction {
+    Mask,          // Replace the matched word with asterisks
+    Block,         // Reject the whole message
+    Flag,          // Let the message through but record it for moderators
+}
+
+impl std::fmt::Display for WordFilterAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            WordFilterAction::Mask => "mask",
+            WordFilterAction::Block => "block",
+            WordFilterAction::Flag => "flag",
+        })
+    }
+}
+
+impl WordFilterAction {
+    pub fn from_string(s: &str) -> Option<WordFilterAction> {
+        match s {
+            "mask" => Some(WordFilterAction::Mask),
+            "block" => Some(WordFilterAction::Block),
+            "flag" => Some(WordFilterAction::Flag),
+            _ => None,
+        }
+    }
+}
+
+/// A single word filter rule
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WordFilterRule {
+    pub pattern: String,
+    pub action: WordFilterAction,
+}
+
+/// Result of running a message through the word filter pipeline
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WordFilterOutcome {
+    pub message: String,
+    pub flagged_patterns: Vec<String>,
+}
+
+/// Typed contents of GameItem.properties_json, one schema per item type
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum ItemProperties {
+    Weapon(WeaponProperties),
+    Armor(ArmorProperties),
+    Consumable(ConsumableProperties),
+    Material(MaterialProperties),
+}
+
+/// Properties of "weapon" items
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WeaponProperties {
+    pub damage: u32,
+    pub durability: u32,
+    #[serde(default)]
+    pub binding: ItemBinding,
+}
+
+/// Properties of "armor" items
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ArmorProperties {
+    pub defense: u32,
+    pub durability: u32,
+    #[serde(default)]
+    pub binding: ItemBinding,
+}
+
+/// When a unique item becomes soulbound to its owner
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemBinding {
+    #[default]
+    None,
+    OnPickup,
+    OnEquip,
+}
+
+/// Properties of "consumable" items: the effects applied, in order, when one is used
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ConsumableProperties {
+    pub effects: Vec<ItemEffect>,
+}
+
+/// One effect of a consumable, handled by the effect registered under its name
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ItemEffect {
+    pub effect: String,
+    #[serde(default)]
+    pub amount: f32,
+    #[serde(default)]
+    pub duration_seconds: u32,
+}
+
+/// Properties of "material" items
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialProperties {
+    #[serde(default)]
+    pub crafting_material: bool,
+}
@@ -3,9 +3,13 @@
 use spacetimedb::{Identity, Timestamp};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+use unicode_segmentation::UnicodeSegmentation;
 
 // Import constants from our constants module
 use crate::constants::*;
+use crate::types::{ItemProperties, WordFilterAction, WordFilterOutcome, WordFilterRule};
 
 /// Generate a unique ID using various entropy sources
 pub fn generate_unique_id(identity: &Identity, timestamp: Timestamp) -> u64 {
@@ -45,6 +49,48 @@ pub fn validate_username(username: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Validate a custom chat channel name and return it in canonical (lowercase) form
+pub fn validate_chat_channel_name(name: &str) -> Result<String, String> {
+    let name = name.trim().to_lowercase();
+    
+    if name.chars().count() < MIN_CHAT_CHANNEL_NAME_LENGTH {
+        return Err(format!("Channel name must be at least {} characters", MIN_CHAT_CHANNEL_NAME_LENGTH));
+    }
+    
+    if name.chars().count() > MAX_CHAT_CHANNEL_NAME_LENGTH {
+        return Err(format!("Channel name cannot exceed {} characters", MAX_CHAT_CHANNEL_NAME_LENGTH));
+    }
+    
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+        return Err("Channel name can only contain letters, numbers, dashes and underscores".to_string());
+    }
+    
+    if RESERVED_CHAT_CHANNELS.contains(&name.as_str()) {
+        return Err("That channel name is reserved".to_string());
+    }
+    
+    Ok(name)
+}
+
+/// Validate a guild name and return it with surrounding and repeated spaces removed
+pub fn validate_guild_name(name: &str) -> Result<String, String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    
+    if name.chars().count() < MIN_GUILD_NAME_LENGTH {
+        return Err(format!("Guild name must be at least {} characters", MIN_GUILD_NAME_LENGTH));
+    }
+    
+    if name.chars().count() > MAX_GUILD_NAME_LENGTH {
+        return Err(format!("Guild name cannot exceed {} characters", MAX_GUILD_NAME_LENGTH));
+    }
+    
+    if !name.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '\'' || c == '-') {
+        return Err("Guild name can only contain letters, numbers, spaces, apostrophes and dashes".to_string());
+    }
+    
+    Ok(name)
+}
+
 /// Validate password strength
 pub fn validate_password(password: &str) -> Result<(), String> {
     if password.len() < MIN_PASSWORD_LENGTH {
@@ -68,6 +114,11 @@ pub fn calculate_distance(x1: f32, y1: f32, z1: f32, x2: f32, y2: f32, z2: f32)
     ((x2 - x1).powi(2) + (y2 - y1).powi(2) + (z2 - z1).powi(2)).sqrt()
 }
 
+/// Experience needed to advance from the given player level to the next
+pub fn experience_to_next_level(level: u32) -> u64 {
+    (100.0 * (level.max(1) as f64).powf(1.5)) as u64
+}
+
 /// Check if movement is valid (not teleporting)
 pub fn validate_movement(
     old_x: f32, old_y: f32, old_z: f32,
@@ -90,22 +141,496 @@ pub fn validate_movement(
 
 /// Sanitize chat message content
 pub fn sanitize_chat_message(message: &str) -> Result<String, String> {
-    let trimmed = message.trim();
+    sanitize_text(message, MAX_CHAT_MESSAGE_LENGTH)
+}
+
+/// Sanitize player-written text (chat, mail, reports)
+/// Works on any script: strips control, bidi-override and invisible characters,
+/// normalizes to NFC, caps stacked combining marks and counts length in graphemes
+pub fn sanitize_text(message: &str, max_length: usize) -> Result<String, String> {
+    // Bound the work done on hostile input before touching individual characters
+    if message.len() > MAX_MESSAGE_SIZE_BYTES as usize {
+        return Err("Message too long".to_string());
+    }
+    
+    // Line breaks and tabs become plain spaces, everything invisible is dropped
+    let stripped: String = message
+        .chars()
+        .filter_map(|c| {
+            if c.is_whitespace() {
+                Some(' ')
+            } else if c.is_control() || is_bidi_control(c) || is_invisible_format(c) {
+                None
+            } else {
+                Some(c)
+            }
+        })
+        .collect();
+    
+    // Compose so that visually identical text is stored (and filtered) identically
+    let normalized: String = stripped.nfc().collect();
+    
+    // Limit stacked combining marks per base character ("zalgo" text)
+    let mut capped = String::with_capacity(normalized.len());
+    let mut marks_in_a_row = 0;
+    for c in normalized.chars() {
+        if is_combining_mark(c) {
+            marks_in_a_row += 1;
+            if marks_in_a_row > MAX_COMBINING_MARKS_PER_CHAR {
+                continue;
+            }
+        } else {
+            marks_in_a_row = 0;
+        }
+        capped.push(c);
+    }
+    
+    let trimmed = capped.trim();
     
     if trimmed.is_empty() {
         return Err("Message cannot be empty".to_string());
     }
     
-    if trimmed.len() > MAX_CHAT_MESSAGE_LENGTH {
+    if trimmed.graphemes(true).count() > max_length {
         return Err("Message too long".to_string());
     }
     
-    // Remove any potentially harmful characters or sequences
-    // This is a basic implementation - you might want more sophisticated filtering
-    let sanitized = trimmed
-        .chars()
-        .filter(|&c| c.is_ascii_graphic() || c.is_ascii_whitespace())
-        .collect();
+    Ok(trimmed.to_string())
+}
+
+/// Characters that reorder surrounding text when rendered (used for spoofing)
+fn is_bidi_control(c: char) -> bool {
+    matches!(c,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' |
+        '\u{202A}'..='\u{202E}' |
+        '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// Zero-width characters that only serve to hide text from filters
+/// (ZWJ and ZWNJ are kept because emoji sequences and several scripts need them)
+fn is_invisible_format(c: char) -> bool {
+    matches!(c, '\u{00AD}' | '\u{200B}' | '\u{2060}' | '\u{FEFF}')
+}
+
+/// Run a sanitized chat message through the word filter rules
+/// Masked words are replaced with asterisks, a blocking rule rejects the message,
+/// and flagging rules are reported back so the caller can record them
+pub fn apply_word_filters(message: &str, rules: &[WordFilterRule]) -> Result<WordFilterOutcome, String> {
+    let folded: Vec<char> = message.chars().map(fold_char).collect();
+    let mut output: Vec<char> = message.chars().collect();
+    let mut flagged_patterns = Vec::new();
+    
+    for rule in rules {
+        let pattern: Vec<char> = rule.pattern.nfc().map(fold_char).collect();
+        let matches = find_word_matches(&folded, &pattern);
+        
+        if matches.is_empty() {
+            continue;
+        }
+        
+        match rule.action {
+            WordFilterAction::Block => {
+                return Err("Message contains blocked language".to_string());
+            },
+            WordFilterAction::Mask => {
+                for start in matches {
+                    output[start..start + pattern.len()].fill('*');
+                }
+            },
+            WordFilterAction::Flag => {
+                flagged_patterns.push(rule.pattern.clone());
+            },
+        }
+    }
     
-    Ok(sanitized)
-}
\ No newline at end of file
+    Ok(WordFilterOutcome {
+        message: output.into_iter().collect(),
+        flagged_patterns,
+    })
+}
+
+/// Case-fold a single character for filter matching
+fn fold_char(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Find every whole-word occurrence of a pattern, returning start offsets in chars
+fn find_word_matches(text: &[char], pattern: &[char]) -> Vec<usize> {
+    if pattern.is_empty() || pattern.len() > text.len() {
+        return Vec::new();
+    }
+    
+    let first = pattern[0];
+    let last = pattern[pattern.len() - 1];
+    
+    (0..=text.len() - pattern.len())
+        .filter(|&start| {
+            let end = start + pattern.len();
+            text[start..end] == *pattern
+                && (start == 0 || !needs_word_boundary(first, text[start - 1]))
+                && (end == text.len() || !needs_word_boundary(last, text[end]))
+        })
+        .collect()
+}
+
+/// Whether a match edge must be separated from its neighbour to count as a word
+/// Scripts written without spaces (CJK, kana, Thai) match anywhere
+fn needs_word_boundary(edge: char, neighbour: char) -> bool {
+    let unspaced = matches!(edge,
+        '\u{0E00}'..='\u{0E7F}' |
+        '\u{3040}'..='\u{30FF}' |
+        '\u{3400}'..='\u{4DBF}' |
+        '\u{4E00}'..='\u{9FFF}'
+    );
+    !unspaced && neighbour.is_alphanumeric()
+}
+
+
+/// Reduce a chat message to the form used for duplicate detection
+/// Case, punctuation, spacing and stretched letters ("heeeey") are ignored
+pub fn normalize_for_spam_check(message: &str) -> String {
+    let mut normalized = String::with_capacity(message.len());
+    let mut previous = None;
+    
+    for c in message.chars().filter(|c| c.is_alphanumeric()).map(fold_char) {
+        if previous != Some(c) {
+            normalized.push(c);
+        }
+        previous = Some(c);
+    }
+    
+    normalized
+}
+
+/// Similarity of two normalized messages between 0.0 and 1.0
+/// Uses the Dice coefficient over character bigrams, so small edits still score high
+pub fn message_similarity(a: &str, b: &str) -> f32 {
+    if a == b {
+        return 1.0;
+    }
+    
+    let a_chars: Vec<char> = a.chars().collect();
+    let b_chars: Vec<char> = b.chars().collect();
+    
+    if a_chars.len() < 2 || b_chars.len() < 2 {
+        return 0.0;
+    }
+    
+    let mut b_bigrams: Vec<(char, char)> = b_chars.windows(2).map(|w| (w[0], w[1])).collect();
+    let total = (a_chars.len() - 1 + b_bigrams.len()) as f32;
+    let mut shared = 0;
+    
+    for window in a_chars.windows(2) {
+        if let Some(pos) = b_bigrams.iter().position(|&bigram| bigram == (window[0], window[1])) {
+            b_bigrams.swap_remove(pos);
+            shared += 1;
+        }
+    }
+    
+    (2 * shared) as f32 / total
+}
+
+/// Add currency to a balance, refusing to overflow or pass the wallet cap
+pub fn add_currency(balance: u64, amount: u64) -> Result<u64, String> {
+    balance.checked_add(amount)
+        .filter(|total| *total <= MAX_WALLET_BALANCE)
+        .ok_or_else(|| "That would exceed the maximum wallet balance".to_string())
+}
+
+/// Take currency from a balance, refusing to go below zero
+pub fn subtract_currency(balance: u64, amount: u64) -> Result<u64, String> {
+    balance.checked_sub(amount)
+        .ok_or_else(|| "Not enough currency".to_string())
+}
+
+/// Total price of a vendor transaction: item value scaled by the vendor multiplier, per unit
+/// Anything with a value is worth at least 1 per unit
+pub fn vendor_price(value: u32, multiplier: f32, quantity: u32) -> Result<u64, String> {
+    let unit_price = match value {
+        0 => 0,
+        _ => ((value as f64 * multiplier.max(0.0) as f64).round() as u64).max(1),
+    };
+
+    unit_price.checked_mul(quantity as u64)
+        .filter(|total| *total <= MAX_WALLET_BALANCE)
+        .ok_or_else(|| "That is more than anyone could pay".to_string())
+}
+
+/// Deposit for listing an auction, scaled by the stack's vendor value and the listing duration
+pub fn auction_deposit(value: u32, quantity: u32, duration_hours: u32) -> u64 {
+    let stack_value = value as f64 * quantity as f64;
+    (stack_value * AUCTION_DEPOSIT_RATE_PER_DAY * duration_hours as f64 / 24.0).ceil() as u64
+}
+
+/// The house's share of a completed sale
+pub fn auction_sales_cut(price: u64) -> u64 {
+    (price as f64 * AUCTION_SALES_CUT).floor() as u64
+}
+
+/// The lowest bid an auction will accept next
+pub fn minimum_next_bid(start_bid: u64, current_bid: u64, has_bids: bool) -> u64 {
+    if !has_bids {
+        return start_bid.max(1);
+    }
+
+    let increment = ((current_bid as f64 * AUCTION_MIN_BID_INCREMENT).ceil() as u64).max(1);
+    current_bid.saturating_add(increment)
+}
+
+/// New Elo ratings for the winner and loser of a PvP match
+pub fn elo_ratings_after_match(winner_rating: u32, loser_rating: u32) -> (u32, u32) {
+    let expected_win = 1.0 / (1.0 + 10f64.powf((loser_rating as f64 - winner_rating as f64) / 400.0));
+    let change = (PVP_RATING_K_FACTOR * (1.0 - expected_win)).round() as u32;
+
+    (winner_rating.saturating_add(change), loser_rating.saturating_sub(change))
+}
+
+/// Damage one duel hit deals: the attacker's weapon damage less half the defender's armor, at least 1
+pub fn duel_hit_damage(weapon_damage: u32, armor_defense: u32) -> u32 {
+    weapon_damage.saturating_sub(armor_defense / 2).max(1)
+}
+
+/// Parse and check an item's properties against the schema for its type
+pub fn parse_item_properties(item_type: &str, properties_json: &str) -> Result<ItemProperties, String> {
+    fn parse<T: serde::de::DeserializeOwned>(item_type: &str, json: &str) -> Result<T, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid {} properties: {}", item_type, e))
+    }
+
+    let properties = match item_type {
+        "weapon" => ItemProperties::Weapon(parse(item_type, properties_json)?),
+        "armor" => ItemProperties::Armor(parse(item_type, properties_json)?),
+        "consumable" => ItemProperties::Consumable(parse(item_type, properties_json)?),
+        "material" => ItemProperties::Material(parse(item_type, properties_json)?),
+        _ => return Err(format!("Unknown item type '{}'", item_type)),
+    };
+
+    match &properties {
+        ItemProperties::Weapon(weapon) if weapon.damage == 0 || weapon.durability == 0 => {
+            Err("Weapons need damage and durability".to_string())
+        },
+        ItemProperties::Armor(armor) if armor.durability == 0 => {
+            Err("Armor needs durability".to_string())
+        },
+        ItemProperties::Consumable(consumable) if consumable.effects.is_empty() => {
+            Err("Consumables need at least one effect".to_string())
+        },
+        ItemProperties::Consumable(consumable) if consumable.effects.iter().any(|effect| !effect.amount.is_finite()) => {
+            Err("Effect amounts must be finite".to_string())
+        },
+        _ => Ok(properties),
+    }
+}
+
+/// The quality tier a roll in `0..total tier weight` lands in
+/// Returns (name, stat multiplier, affix count)
+pub fn item_quality_for_roll(roll: u32) -> (&'static str, f32, usize) {
+    let mut remaining = roll;
+    for (name, weight, multiplier, affixes) in ITEM_QUALITY_TIERS {
+        if remaining < weight {
+            return (name, multiplier, affixes);
+        }
+        remaining -= weight;
+    }
+
+    let (name, _, multiplier, affixes) = ITEM_QUALITY_TIERS[ITEM_QUALITY_TIERS.len() - 1];
+    (name, multiplier, affixes)
+}
+
+/// Cost of restoring an item to full durability, in proportion to the durability lost
+/// Any repair of an item with a value costs at least 1
+pub fn item_repair_cost(value: u32, durability: u32, max_durability: u32) -> u64 {
+    let missing = max_durability.saturating_sub(durability);
+    if missing == 0 || value == 0 {
+        return 0;
+    }
+
+    let cost = value as f64 * ITEM_REPAIR_COST_RATE * missing as f64 / max_durability as f64;
+    (cost.ceil() as u64).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_sanitize_keeps_non_ascii_text() {
+        assert_eq!(sanitize_chat_message("Привет, мир").unwrap(), "Привет, мир");
+        assert_eq!(sanitize_chat_message("你好世界").unwrap(), "你好世界");
+        assert_eq!(sanitize_chat_message("  Café crème ").unwrap(), "Café crème");
+    }
+    
+    #[test]
+    fn test_sanitize_strips_control_and_bidi() {
+        assert_eq!(sanitize_chat_message("a\u{202E}b\u{0007}c").unwrap(), "abc");
+        assert_eq!(sanitize_chat_message("line\none").unwrap(), "line one");
+        assert_eq!(sanitize_chat_message("in\u{200B}visible").unwrap(), "invisible");
+        assert!(sanitize_chat_message("\u{202E}\u{200B}").is_err());
+    }
+    
+    #[test]
+    fn test_sanitize_normalizes_and_caps_combining_marks() {
+        // Decomposed e + acute composes to a single character
+        assert_eq!(sanitize_chat_message("e\u{0301}").unwrap(), "\u{00E9}");
+        
+        let zalgo = format!("a{}", "\u{0300}\u{0316}".repeat(10));
+        let sanitized = sanitize_chat_message(&zalgo).unwrap();
+        assert_eq!(sanitized.chars().count(), 1 + MAX_COMBINING_MARKS_PER_CHAR);
+    }
+    
+    #[test]
+    fn test_sanitize_counts_graphemes_not_bytes() {
+        // Each of these is three bytes but one grapheme
+        let at_limit = "界".repeat(MAX_CHAT_MESSAGE_LENGTH);
+        assert!(sanitize_chat_message(&at_limit).is_ok());
+        
+        let over_limit = "界".repeat(MAX_CHAT_MESSAGE_LENGTH + 1);
+        assert!(sanitize_chat_message(&over_limit).is_err());
+    }
+    
+    #[test]
+    fn test_word_filters() {
+        let rules = vec![
+            WordFilterRule { pattern: "darn".to_string(), action: WordFilterAction::Mask },
+            WordFilterRule { pattern: "gold4sale".to_string(), action: WordFilterAction::Block },
+            WordFilterRule { pattern: "scam".to_string(), action: WordFilterAction::Flag },
+        ];
+        
+        let masked = apply_word_filters("Darn it, darned thing", &rules).unwrap();
+        assert_eq!(masked.message, "**** it, darned thing");
+        assert!(masked.flagged_patterns.is_empty());
+        
+        assert!(apply_word_filters("buy GOLD4SALE now", &rules).is_err());
+        
+        let flagged = apply_word_filters("is this a scam?", &rules).unwrap();
+        assert_eq!(flagged.message, "is this a scam?");
+        assert_eq!(flagged.flagged_patterns, vec!["scam".to_string()]);
+    }
+    
+    #[test]
+    fn test_spam_similarity() {
+        assert_eq!(normalize_for_spam_check("HEEEY  there!!"), "heythere");
+        
+        let a = normalize_for_spam_check("WTS iron sword, cheap! PM me");
+        let b = normalize_for_spam_check("wts iron sword cheap pm me!!!");
+        assert_eq!(message_similarity(&a, &b), 1.0);
+        
+        let c = normalize_for_spam_check("WTS iron sword, cheap! PM me now");
+        assert!(message_similarity(&a, &c) >= CHAT_DUPLICATE_SIMILARITY);
+        
+        let d = normalize_for_spam_check("anyone up for the dragon raid tonight?");
+        assert!(message_similarity(&a, &d) < CHAT_DUPLICATE_SIMILARITY);
+    }
+    
+    #[test]
+    fn test_currency_arithmetic() {
+        assert_eq!(add_currency(10, 5).unwrap(), 15);
+        assert!(add_currency(MAX_WALLET_BALANCE, 1).is_err());
+        assert!(add_currency(u64::MAX, u64::MAX).is_err());
+        
+        assert_eq!(subtract_currency(10, 10).unwrap(), 0);
+        assert!(subtract_currency(10, 11).is_err());
+    }
+    
+    #[test]
+    fn test_vendor_price() {
+        assert_eq!(vendor_price(100, 1.0, 3).unwrap(), 300);
+        assert_eq!(vendor_price(10, 0.25, 4).unwrap(), 12);
+        
+        // Cheap items never sell for nothing, worthless ones always do
+        assert_eq!(vendor_price(1, 0.25, 1).unwrap(), 1);
+        assert_eq!(vendor_price(0, 2.0, 5).unwrap(), 0);
+        
+        assert!(vendor_price(u32::MAX, MAX_VENDOR_PRICE_MULTIPLIER, u32::MAX).is_err());
+    }
+    
+    #[test]
+    fn test_auction_pricing() {
+        // 5% per day of the stack's value, rounded up
+        assert_eq!(auction_deposit(100, 10, 24), 50);
+        assert_eq!(auction_deposit(100, 10, 12), 25);
+        assert_eq!(auction_deposit(1, 1, 12), 1);
+        assert_eq!(auction_deposit(0, 5, 48), 0);
+        
+        assert_eq!(auction_sales_cut(1000), 50);
+        assert_eq!(auction_sales_cut(19), 0);
+        
+        assert_eq!(minimum_next_bid(100, 0, false), 100);
+        assert_eq!(minimum_next_bid(0, 0, false), 1);
+        assert_eq!(minimum_next_bid(100, 100, true), 105);
+        assert_eq!(minimum_next_bid(100, 10, true), 11);
+    }
+    
+    #[test]
+    fn test_elo_ratings() {
+        // Evenly matched players swap half the K factor
+        assert_eq!(elo_ratings_after_match(1500, 1500), (1516, 1484));
+        
+        // Beating a much weaker player is worth little; an upset is worth a lot
+        let (favourite, _) = elo_ratings_after_match(1900, 1500);
+        assert!(favourite - 1900 < 5);
+        let (underdog, _) = elo_ratings_after_match(1500, 1900);
+        assert!(underdog - 1500 > 25);
+        
+        assert_eq!(elo_ratings_after_match(1500, 0).1, 0);
+    }
+    
+    #[test]
+    fn test_duel_hit_damage() {
+        assert_eq!(duel_hit_damage(25, 0), 25);
+        assert_eq!(duel_hit_damage(25, 10), 20);
+        
+        // Heavy armor never makes a hit harmless
+        assert_eq!(duel_hit_damage(2, 40), 1);
+    }
+    
+    #[test]
+    fn test_parse_item_properties() {
+        let sword = parse_item_properties("weapon", r#"{"damage": 25, "durability": 100}"#).unwrap();
+        assert_eq!(sword, ItemProperties::Weapon(crate::types::WeaponProperties { damage: 25, durability: 100, binding: crate::types::ItemBinding::None }));
+        
+        let potion = parse_item_properties("consumable", r#"{"effects": [{"effect": "heal", "amount": 50}]}"#).unwrap();
+        match potion {
+            ItemProperties::Consumable(consumable) => {
+                assert_eq!(consumable.effects.len(), 1);
+                assert_eq!(consumable.effects[0].effect, "heal");
+                assert_eq!(consumable.effects[0].duration_seconds, 0);
+            },
+            other => panic!("expected consumable, got {:?}", other),
+        }
+        
+        assert!(parse_item_properties("material", "{}").is_ok());
+        
+        // Wrong or missing fields, empty effect lists and unknown types are rejected
+        assert!(parse_item_properties("weapon", r#"{"damage": 25}"#).is_err());
+        assert!(parse_item_properties("armor", r#"{"defense": 10, "durability": 50, "heal_amount": 5}"#).is_err());
+        assert!(parse_item_properties("consumable", r#"{"effects": []}"#).is_err());
+        assert!(parse_item_properties("trinket", "{}").is_err());
+        
+        let armor = parse_item_properties("armor", r#"{"defense": 10, "durability": 50, "binding": "on_equip"}"#).unwrap();
+        assert!(matches!(armor, ItemProperties::Armor(armor) if armor.binding == crate::types::ItemBinding::OnEquip));
+        assert!(parse_item_properties("armor", r#"{"defense": 10, "durability": 50, "binding": "sometimes"}"#).is_err());
+    }
+    
+    #[test]
+    fn test_item_quality_rolls() {
+        let total: u32 = ITEM_QUALITY_TIERS.iter().map(|(_, weight, _, _)| weight).sum();
+        
+        assert_eq!(item_quality_for_roll(0).0, "common");
+        assert_eq!(item_quality_for_roll(69).0, "common");
+        assert_eq!(item_quality_for_roll(70).0, "uncommon");
+        assert_eq!(item_quality_for_roll(total - 1).0, "epic");
+        
+        // Out-of-range rolls land in the last tier rather than panicking
+        assert_eq!(item_quality_for_roll(total + 10).0, "epic");
+    }
+    
+    #[test]
+    fn test_item_repair_cost() {
+        assert_eq!(item_repair_cost(100, 100, 100), 0);
+        assert_eq!(item_repair_cost(100, 0, 100), 50);
+        assert_eq!(item_repair_cost(100, 50, 100), 25);
+        assert_eq!(item_repair_cost(10, 99, 100), 1);
+        assert_eq!(item_repair_cost(0, 0, 100), 0);
+    }
+}
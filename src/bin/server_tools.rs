@@ -3,11 +3,17 @@
 //! This utility provides tools for managing SpacetimeDB servers,
 //! including deployment, monitoring, and database operations.
 
+use std::collections::HashSet;
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{self, Command};
 
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use shared_module::parse_item_properties;
+
 fn main() {
     println!("SpacetimeDB MMO Template - Server Tools");
     println!("=======================================");
@@ -26,6 +32,7 @@ fn main() {
         "logs" => show_server_logs(&args[2..]),
         "init-db" => initialize_database(&args[2..]),
         "generate-schema" => generate_schema(),
+        "import-content" => import_content(&args[2..]),
         "test" => run_server_tests(),
         "help" | "--help" | "-h" => print_usage(),
         _ => {
@@ -46,17 +53,21 @@ fn print_usage() {
     println!("  logs <host>          Show server logs");
     println!("  init-db <host>       Initialize database with default data");
     println!("  generate-schema      Generate database schema documentation");
+    println!("  import-content <dir> Validate item, NPC and zone files and import them");
+    println!("                       [--database <name>] [--dry-run]");
     println!("  test                 Run server tests");
     println!("  help                 Show this help message");
     println!();
     println!("Environment Variables:");
     println!("  SPACETIMEDB_HOST     Default SpacetimeDB host");
     println!("  SPACETIMEDB_TOKEN    Authentication token");
+    println!("  SPACETIMEDB_DATABASE Default database for import-content");
     println!();
     println!("Examples:");
     println!("  server_tools build");
     println!("  server_tools deploy localhost");
     println!("  server_tools status production.spacetimedb.com");
+    println!("  server_tools import-content content --database mmo_database");
 }
 
 fn build_server_module() {
@@ -229,6 +240,20 @@ Stores active player state in the game world.
 | last_seen | Timestamp | Last activity |
 | current_zone | String | Current zone/area |
 
+### ChatMessage Table (chatmessage)
+Stores chat messages posted to channels.
+
+| Column | Type | Description |
+|--------|------|-------------|
+| message_id | u64 (PK, auto-increment) | Unique message identifier, increasing with every message |
+| sender_identity | Identity | Sender's identity |
+| sender_username | String | Sender's display name |
+| channel | String (Index with message_id, Index with timestamp) | Channel name |
+| zone | String | Sender's zone when sent (audience for zone chat) |
+| group_id | u64 | Party or guild the message is addressed to (0 if none) |
+| timestamp | Timestamp (Index) | Send time |
+| message | String | Message body |
+
 ## Reducers
 
 ### Authentication
@@ -244,10 +269,301 @@ Stores active player state in the game world.
 ### Chat System
 - `send_chat_message(message, channel)` - Send chat message
 
+Clients page through channel history by subscribing to `chatmessage` with a message ID cursor:
+`SELECT * FROM chatmessage WHERE channel = 'guild' AND message_id < <oldest loaded ID>` for older messages,
+or `... AND message_id > <newest loaded ID>` for newer ones. Visibility filters limit the results to what the player may read.
+
+Messages past their channel type's retention policy (`chat_retention_policy`) are moved to `chatmessage_archive`.
+
+### Content Import (admin)
+- `begin_content_import(version)` - Open an import
+- `upsert_game_item(...)`, `upsert_npc_template(...)`, `upsert_zone_definition(...)` - Add or update a definition
+- `finish_content_import(version)` - Retire definitions the version no longer contains
+
+`server_tools import-content <dir>` validates `items`, `npcs` and `zones` files (.json, .toml or .csv) and calls these reducers.
+Definition checksums and versions are tracked in `content_record` and `content_import`.
+
 For complete documentation, see the source code in ServerModule and CustomServerModule.
 "#, std::env::var("BUILD_DATE").unwrap_or_else(|_| "Unknown".to_string()))
 }
 
+/// An item definition in a content file
+/// `properties` may be written inline (JSON/TOML) or as a JSON string (CSV)
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ItemFileEntry {
+    item_id: String,
+    item_name: String,
+    item_type: String,
+    #[serde(default)]
+    description: String,
+    max_stack_size: u32,
+    value: u32,
+    #[serde(default)]
+    properties: serde_json::Value,
+}
+
+/// An NPC template in a content file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NpcFileEntry {
+    npc_type: String,
+    max_health: f32,
+    #[serde(default)]
+    aggressive: bool,
+    #[serde(default)]
+    vendor: bool,
+}
+
+/// A zone definition in a content file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ZoneFileEntry {
+    zone_id: String,
+    display_name: String,
+    #[serde(default)]
+    min_level: u32,
+    spawn_x: f32,
+    spawn_y: f32,
+    spawn_z: f32,
+}
+
+/// Validate a content directory and push it to the database through the admin import reducers
+/// The directory holds items, npcs and zones files, each as .json, .toml or .csv
+fn import_content(args: &[String]) {
+    let mut content_dir = None;
+    let mut database = env::var("SPACETIMEDB_DATABASE").ok();
+    let mut dry_run = false;
+    
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--database" => database = rest.next().cloned(),
+            "--dry-run" => dry_run = true,
+            _ if content_dir.is_none() => content_dir = Some(PathBuf::from(arg)),
+            _ => {
+                eprintln!("Unexpected argument: {}", arg);
+                process::exit(1);
+            }
+        }
+    }
+    
+    let content_dir = content_dir.unwrap_or_else(|| {
+        eprintln!("Error: Content directory required");
+        eprintln!("Usage: server_tools import-content <dir> [--database <name>] [--dry-run]");
+        process::exit(1);
+    });
+    
+    let mut sources = Vec::new();
+    let items: Vec<ItemFileEntry> = load_content_file(&content_dir, "items", &mut sources);
+    let npcs: Vec<NpcFileEntry> = load_content_file(&content_dir, "npcs", &mut sources);
+    let zones: Vec<ZoneFileEntry> = load_content_file(&content_dir, "zones", &mut sources);
+    
+    let mut errors = Vec::new();
+    let item_properties = validate_items(&items, &mut errors);
+    validate_npcs(&npcs, &mut errors);
+    validate_zones(&zones, &mut errors);
+    
+    if !errors.is_empty() {
+        eprintln!("Content validation failed:");
+        for error in &errors {
+            eprintln!("  {}", error);
+        }
+        process::exit(1);
+    }
+    
+    // The version is derived from the files, so importing unchanged content changes nothing
+    let mut hasher = Sha256::new();
+    for source in &sources {
+        hasher.update((source.len() as u64).to_le_bytes());
+        hasher.update(source.as_bytes());
+    }
+    let digest: String = hasher.finalize()[..8].iter().map(|byte| format!("{:02x}", byte)).collect();
+    let version = format!("content-{}", digest);
+    
+    println!("Validated {} items, {} NPC templates and {} zones (version {})", items.len(), npcs.len(), zones.len(), version);
+    
+    if dry_run {
+        println!("Dry run: nothing was imported.");
+        return;
+    }
+    
+    let database = database.unwrap_or_else(|| {
+        eprintln!("Error: Database required (use --database or SPACETIMEDB_DATABASE)");
+        process::exit(1);
+    });
+    
+    call_reducer(&database, "begin_content_import", &[serde_json::json!(version)]);
+    
+    for (item, properties_json) in items.iter().zip(item_properties) {
+        call_reducer(&database, "upsert_game_item", &[
+            serde_json::json!(version),
+            serde_json::json!(item.item_id),
+            serde_json::json!(item.item_name),
+            serde_json::json!(item.item_type),
+            serde_json::json!(item.description),
+            serde_json::json!(item.max_stack_size),
+            serde_json::json!(item.value),
+            serde_json::json!(properties_json),
+        ]);
+    }
+    
+    for npc in &npcs {
+        call_reducer(&database, "upsert_npc_template", &[
+            serde_json::json!(version),
+            serde_json::json!(npc.npc_type),
+            serde_json::json!(npc.max_health),
+            serde_json::json!(npc.aggressive),
+            serde_json::json!(npc.vendor),
+        ]);
+    }
+    
+    for zone in &zones {
+        call_reducer(&database, "upsert_zone_definition", &[
+            serde_json::json!(version),
+            serde_json::json!(zone.zone_id),
+            serde_json::json!(zone.display_name),
+            serde_json::json!(zone.min_level),
+            serde_json::json!(zone.spawn_x),
+            serde_json::json!(zone.spawn_y),
+            serde_json::json!(zone.spawn_z),
+        ]);
+    }
+    
+    call_reducer(&database, "finish_content_import", &[serde_json::json!(version)]);
+    
+    println!("Content version {} imported into {}", version, database);
+}
+
+/// Load `<dir>/<kind>.json`, `.toml` or `.csv`; a missing file means no definitions of that kind
+/// The file's text is added to `sources` for versioning
+fn load_content_file<T: DeserializeOwned>(dir: &Path, kind: &str, sources: &mut Vec<String>) -> Vec<T> {
+    let candidates: Vec<PathBuf> = ["json", "toml", "csv"].iter()
+        .map(|extension| dir.join(format!("{}.{}", kind, extension)))
+        .filter(|path| path.exists())
+        .collect();
+    
+    let path = match candidates.as_slice() {
+        [] => return Vec::new(),
+        [path] => path,
+        _ => {
+            eprintln!("Error: More than one {} file in {}", kind, dir.display());
+            process::exit(1);
+        }
+    };
+    
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Error: Could not read {}: {}", path.display(), e);
+        process::exit(1);
+    });
+    
+    let parsed = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => serde_json::from_str(&text).map_err(|e| e.to_string()),
+        Some("toml") => {
+            // TOML needs a table at the top, so entries live under [[<kind>]]
+            toml::from_str::<toml::Table>(&text)
+                .map_err(|e| e.to_string())
+                .and_then(|mut table| {
+                    let entries = table.remove(kind).unwrap_or(toml::Value::Array(Vec::new()));
+                    entries.try_into().map_err(|e: toml::de::Error| e.to_string())
+                })
+        },
+        _ => csv::Reader::from_reader(text.as_bytes())
+            .deserialize()
+            .collect::<Result<Vec<T>, csv::Error>>()
+            .map_err(|e| e.to_string()),
+    };
+    
+    let entries = parsed.unwrap_or_else(|e| {
+        eprintln!("Error: {} is not valid: {}", path.display(), e);
+        process::exit(1);
+    });
+    
+    sources.push(format!("{}\n{}", kind, text));
+    entries
+}
+
+/// Check item definitions, returning each item's properties as the JSON stored in game_items
+fn validate_items(items: &[ItemFileEntry], errors: &mut Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    
+    items.iter().map(|item| {
+        if item.item_id.is_empty() || item.item_name.is_empty() {
+            errors.push("items: every item needs an item_id and item_name".to_string());
+        }
+        if !seen.insert(item.item_id.clone()) {
+            errors.push(format!("items: '{}' is defined more than once", item.item_id));
+        }
+        if item.max_stack_size == 0 {
+            errors.push(format!("items: '{}' needs a max_stack_size of at least 1", item.item_id));
+        }
+        
+        let properties_json = match &item.properties {
+            serde_json::Value::String(json) => json.clone(),
+            serde_json::Value::Null => "{}".to_string(),
+            value => value.to_string(),
+        };
+        
+        if let Err(e) = parse_item_properties(&item.item_type, &properties_json) {
+            errors.push(format!("items: '{}': {}", item.item_id, e));
+        }
+        
+        properties_json
+    }).collect()
+}
+
+fn validate_npcs(npcs: &[NpcFileEntry], errors: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    
+    for npc in npcs {
+        if npc.npc_type.is_empty() {
+            errors.push("npcs: every template needs an npc_type".to_string());
+        }
+        if !seen.insert(npc.npc_type.clone()) {
+            errors.push(format!("npcs: '{}' is defined more than once", npc.npc_type));
+        }
+        if !npc.max_health.is_finite() || npc.max_health <= 0.0 {
+            errors.push(format!("npcs: '{}' needs positive max_health", npc.npc_type));
+        }
+    }
+}
+
+fn validate_zones(zones: &[ZoneFileEntry], errors: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    
+    for zone in zones {
+        if zone.zone_id.is_empty() || zone.display_name.is_empty() {
+            errors.push("zones: every zone needs a zone_id and display_name".to_string());
+        }
+        if !seen.insert(zone.zone_id.clone()) {
+            errors.push(format!("zones: '{}' is defined more than once", zone.zone_id));
+        }
+        if !zone.spawn_x.is_finite() || !zone.spawn_y.is_finite() || !zone.spawn_z.is_finite() {
+            errors.push(format!("zones: '{}' has an invalid spawn point", zone.zone_id));
+        }
+    }
+}
+
+/// Call a reducer through the spacetime CLI, stopping the import on the first failure
+/// The reducers run in order, so a failure leaves the import open to be rerun
+fn call_reducer(database: &str, reducer: &str, args: &[serde_json::Value]) {
+    let mut command = Command::new("spacetime");
+    command.arg("call");
+    if let Ok(host) = env::var("SPACETIMEDB_HOST") {
+        command.args(["--server", &host]);
+    }
+    command.arg(database).arg(reducer);
+    for arg in args {
+        command.arg(arg.to_string());
+    }
+    
+    let status = command.status().expect("Failed to execute spacetime call");
+    if !status.success() {
+        eprintln!("Reducer {} failed; fix the problem and run the import again", reducer);
+        process::exit(1);
+    }
+}
+
 fn run_server_tests() {
     println!("Running server tests...");
     